@@ -291,14 +291,14 @@ fn body_async(
     resolver,
     acl,
   } = invoke;
-  parse_args(function, message, acl, attributes).map(|args| {
+  parse_args(function, message, acl, attributes, true).map(|args| {
     #[cfg(feature = "tracing")]
     quote! {
       use tracing::Instrument;
 
       let span = tracing::debug_span!("ipc::request::run");
       #resolver.respond_async_serialized(async move {
-        let result = $path(#(#args?),*);
+        let result = $path(#(#args.await?),*);
         let kind = (&result).async_kind();
         kind.future(result).await
       }
@@ -309,7 +309,7 @@ fn body_async(
     #[cfg(not(feature = "tracing"))]
     quote! {
       #resolver.respond_async_serialized(async move {
-        let result = $path(#(#args?),*);
+        let result = $path(#(#args.await?),*);
         let kind = (&result).async_kind();
         kind.future(result).await
       });
@@ -333,7 +333,7 @@ fn body_blocking(
     resolver,
     acl,
   } = invoke;
-  let args = parse_args(function, message, acl, attributes)?;
+  let args = parse_args(function, message, acl, attributes, false)?;
 
   // the body of a `match` to early return any argument that wasn't successful in parsing.
   let match_body = quote!({
@@ -357,17 +357,20 @@ fn body_blocking(
 }
 
 /// Parse all arguments for the command wrapper to use from the signature of the command function.
+///
+/// The arguments of async commands are futures to await, so they can be async arguments.
 fn parse_args(
   function: &ItemFn,
   message: &Ident,
   acl: &Ident,
   attributes: &WrapperAttributes,
+  is_async: bool,
 ) -> syn::Result<Vec<TokenStream2>> {
   function
     .sig
     .inputs
     .iter()
-    .map(|arg| parse_arg(&function.sig.ident, arg, message, acl, attributes))
+    .map(|arg| parse_arg(&function.sig.ident, arg, message, acl, attributes, is_async))
     .collect()
 }
 
@@ -378,6 +381,7 @@ fn parse_arg(
   message: &Ident,
   acl: &Ident,
   attributes: &WrapperAttributes,
+  is_async: bool,
 ) -> syn::Result<TokenStream2> {
   // we have no use for self arguments
   let mut arg = match arg {
@@ -423,7 +427,12 @@ fn parse_arg(
 
   let root = &attributes.root;
 
-  Ok(quote!(#root::command::CommandArg::from_command(
+  let from_command = if is_async {
+    quote!(#root::command::private::from_command_async)
+  } else {
+    quote!(#root::command::CommandArg::from_command)
+  };
+  Ok(quote!(#from_command(
     #root::command::CommandItem {
       name: stringify!(#command),
       key: #key,
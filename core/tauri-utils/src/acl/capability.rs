@@ -63,6 +63,9 @@ pub enum CapabilityContext {
   /// Capability refers to remote usage.
   Remote {
     /// Remote domains this capability refers to. Can use glob patterns.
+    ///
+    /// A domain can be prefixed with a scheme and suffixed with a port to restrict them,
+    /// e.g. `https://*.tauri.app` or `localhost:8443`.
     domains: Vec<String>,
   },
 }
@@ -63,6 +63,10 @@ pub enum Error {
   #[error("failed to run glob: {0}")]
   Glob(#[from] glob::PatternError),
 
+  /// Invalid port in a remote domain pattern
+  #[error("invalid port in remote domain {0}")]
+  InvalidRemotePort(String),
+
   /// Invalid TOML encountered
   #[error("failed to parse TOML: {0}")]
   Toml(#[from] toml::de::Error),
@@ -189,11 +193,81 @@ pub struct PermissionSet {
 pub enum ExecutionContext {
   /// A local URL is used (the Tauri app URL).
   Local,
+  /// A local URL matching the given pattern is used, e.g. `tauri://localhost` or `https://tauri.localhost`.
+  ///
+  /// Capability files can't express it, build it with [`ExecutionContext::local_url`].
+  LocalUrl {
+    /// The local URL (`scheme://host[:port]`) trying to access the IPC (glob pattern).
+    url: Pattern,
+  },
   /// Remote URL is tring to use the IPC.
   Remote {
     /// The domain trying to access the IPC (glob pattern).
     domain: Pattern,
   },
+  /// Remote URL with a restricted scheme or port is trying to use the IPC.
+  RemoteUrl {
+    /// The scheme the remote URL must use, e.g. `https`. Any scheme matches if not set.
+    scheme: Option<String>,
+    /// The domain trying to access the IPC (glob pattern).
+    domain: Pattern,
+    /// The port the remote URL must use. Any port matches if not set.
+    port: Option<u16>,
+  },
+}
+
+impl ExecutionContext {
+  /// Parses a remote context from a domain glob pattern optionally prefixed with a scheme
+  /// and suffixed with a port, e.g. `*.tauri.app`, `https://tauri.app` or `localhost:8443`.
+  ///
+  /// Patterns without a scheme nor a port give an [`ExecutionContext::Remote`].
+  pub fn remote(pattern: &str) -> Result<Self, Error> {
+    let (scheme, rest) = match pattern.split_once("://") {
+      Some((scheme, rest)) => (Some(scheme.to_lowercase()), rest),
+      None => (None, pattern),
+    };
+    let (domain, port) = match rest.rsplit_once(':') {
+      Some((domain, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => (
+        domain,
+        Some(
+          port
+            .parse()
+            .map_err(|_| Error::InvalidRemotePort(pattern.to_string()))?,
+        ),
+      ),
+      _ => (rest, None),
+    };
+    let domain = Pattern::new(domain)?;
+
+    Ok(if scheme.is_none() && port.is_none() {
+      Self::Remote { domain }
+    } else {
+      Self::RemoteUrl {
+        scheme,
+        domain,
+        port,
+      }
+    })
+  }
+
+  /// Parses a local context restricted to the local URLs matching the glob pattern,
+  /// e.g. `tauri://localhost` or `https://tauri.localhost`.
+  ///
+  /// Only reachable from Rust, e.g. with the `RuntimeAuthorityBuilder` of `tauri`:
+  /// capabilities only resolve to [`ExecutionContext::Local`] and the remote contexts.
+  pub fn local_url(pattern: &str) -> Result<Self, Error> {
+    Ok(Self::LocalUrl {
+      url: Pattern::new(&pattern.to_lowercase())?,
+    })
+  }
+
+  /// The domain pattern of a remote context.
+  pub fn domain(&self) -> Option<&Pattern> {
+    match self {
+      Self::Local | Self::LocalUrl { .. } => None,
+      Self::Remote { domain } | Self::RemoteUrl { domain, .. } => Some(domain),
+    }
+  }
 }
 
 #[cfg(feature = "build")]
@@ -210,11 +284,65 @@ mod build_ {
         Self::Local => {
           quote! { #prefix::Local }
         }
+        Self::LocalUrl { url } => {
+          let url = url.as_str();
+          quote! { #prefix::LocalUrl { url: #url.parse().unwrap() } }
+        }
         Self::Remote { domain } => {
           let domain = domain.as_str();
           quote! { #prefix::Remote { domain: #domain.parse().unwrap() } }
         }
+        Self::RemoteUrl {
+          scheme,
+          domain,
+          port,
+        } => {
+          let scheme = match scheme {
+            Some(scheme) => quote! { ::core::option::Option::Some(#scheme.into()) },
+            None => quote! { ::core::option::Option::None },
+          };
+          let domain = domain.as_str();
+          let port = match port {
+            Some(port) => quote! { ::core::option::Option::Some(#port) },
+            None => quote! { ::core::option::Option::None },
+          };
+          quote! { #prefix::RemoteUrl { scheme: #scheme, domain: #domain.parse().unwrap(), port: #port } }
+        }
       });
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::ExecutionContext;
+
+  #[test]
+  fn remote_execution_context() {
+    let remote = |pattern| ExecutionContext::remote(pattern).unwrap();
+
+    assert_eq!(
+      remote("*.tauri.app"),
+      ExecutionContext::Remote {
+        domain: "*.tauri.app".parse().unwrap()
+      }
+    );
+    assert_eq!(
+      remote("HTTPS://tauri.app"),
+      ExecutionContext::RemoteUrl {
+        scheme: Some("https".into()),
+        domain: "tauri.app".parse().unwrap(),
+        port: None,
+      }
+    );
+    assert_eq!(
+      remote("localhost:8443"),
+      ExecutionContext::RemoteUrl {
+        scheme: None,
+        domain: "localhost".parse().unwrap(),
+        port: Some(8443),
+      }
+    );
+    assert!(ExecutionContext::remote("localhost:99999").is_err());
+  }
+}
@@ -9,8 +9,6 @@ use std::{
   hash::{Hash, Hasher},
 };
 
-use glob::Pattern;
-
 use crate::platform::Target;
 
 use super::{
@@ -234,9 +232,9 @@ fn resolve_command(
     }
     CapabilityContext::Remote { domains } => domains
       .iter()
-      .map(|domain| ExecutionContext::Remote {
-        domain: Pattern::new(domain)
-          .unwrap_or_else(|e| panic!("invalid glob pattern for remote domain {domain}: {e}")),
+      .map(|domain| {
+        ExecutionContext::remote(domain)
+          .unwrap_or_else(|e| panic!("invalid pattern for remote domain {domain}: {e}"))
       })
       .collect(),
   };
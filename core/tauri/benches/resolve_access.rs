@@ -0,0 +1,89 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Compares [`RuntimeAuthority::resolve_access`] against a linear scan of the access control list
+//! with 500 registered commands.
+//!
+//! Run with `cargo bench -p tauri --bench resolve_access`.
+
+use std::{
+  hint::black_box,
+  time::{Duration, Instant},
+};
+
+use tauri::{
+  command::{Origin, RuntimeAuthority, RuntimeAuthorityBuilder},
+  utils::acl::{resolved::ResolvedCommand, ExecutionContext},
+};
+
+const COMMANDS: usize = 500;
+const ITERATIONS: u32 = 20_000;
+
+fn command(i: usize) -> String {
+  format!("plugin:plugin-{}:command-{i}", i % 25)
+}
+
+fn authority() -> RuntimeAuthority {
+  let mut builder = RuntimeAuthorityBuilder::new();
+  for i in 0..COMMANDS {
+    builder = builder
+      .allow_command(command(i), ExecutionContext::Local, ["main", "settings-*"])
+      .allow_command(
+        command(i),
+        ExecutionContext::remote("https://*.tauri.app").unwrap(),
+        ["main"],
+      );
+  }
+  builder
+    .deny_command("plugin:plugin-0:delete", ExecutionContext::Local, ["*"])
+    .build()
+    .unwrap()
+}
+
+/// How the access control list was resolved before commands were indexed by name.
+fn linear_scan<'a>(
+  authority: &'a RuntimeAuthority,
+  command: &str,
+  window: &str,
+) -> Option<&'a ResolvedCommand> {
+  if authority
+    .denied_commands_iter()
+    .any(|(key, _)| key.name == command && key.context == ExecutionContext::Local)
+  {
+    return None;
+  }
+  authority
+    .allowed_commands_iter()
+    .find(|(key, _)| key.name == command && key.context == ExecutionContext::Local)
+    .map(|(_, resolved)| resolved)
+    .filter(|resolved| resolved.windows.iter().any(|w| w.matches(window)))
+}
+
+fn measure(name: &str, mut f: impl FnMut(&str)) -> Duration {
+  let commands: Vec<String> = (0..COMMANDS).map(command).collect();
+  let start = Instant::now();
+  for i in 0..ITERATIONS {
+    f(&commands[i as usize % COMMANDS]);
+  }
+  let elapsed = start.elapsed() / ITERATIONS;
+  println!("{name:>14}: {elapsed:?} per call");
+  elapsed
+}
+
+fn main() {
+  let authority = authority();
+
+  println!("resolving access with {COMMANDS} registered commands");
+  let linear = measure("linear scan", |command| {
+    black_box(linear_scan(&authority, command, "settings-1"));
+  });
+  let indexed = measure("resolve_access", |command| {
+    let _ = black_box(authority.resolve_access(command, "settings-1", &Origin::Local));
+  });
+  println!(
+    "{:>14}: {:.1}x",
+    "speedup",
+    linear.as_secs_f64() / indexed.as_secs_f64()
+  );
+}
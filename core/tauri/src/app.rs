@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: MIT
 
 use crate::{
-  command::{CommandArg, CommandItem},
+  command::{CommandArg, CommandItem, RuntimeAuthority},
   ipc::{
     channel::ChannelDataIpcQueue, CallbackFn, Invoke, InvokeError, InvokeHandler, InvokeResponder,
     InvokeResponse,
@@ -974,6 +974,9 @@ pub struct Builder<R: Runtime> {
 
   /// The device event filter.
   device_event_filter: DeviceEventFilter,
+
+  /// Hooks that configure the runtime authority.
+  runtime_authority_hooks: Vec<Box<dyn FnOnce(&mut RuntimeAuthority) + Send>>,
 }
 
 #[derive(Template)]
@@ -1032,6 +1035,7 @@ impl<R: Runtime> Builder<R> {
       enable_macos_default_menu: true,
       window_event_listeners: Vec::new(),
       device_event_filter: Default::default(),
+      runtime_authority_hooks: Vec::new(),
     }
   }
 }
@@ -1110,6 +1114,26 @@ impl<R: Runtime> Builder<R> {
     self
   }
 
+  /// Configures the [`RuntimeAuthority`] used to authorize IPC calls.
+  ///
+  /// # Examples
+  /// ```
+  /// use std::time::Duration;
+  ///
+  /// tauri::Builder::default()
+  ///   .configure_runtime_authority(|authority| {
+  ///     authority.cache_command("list_items", Duration::from_secs(5));
+  ///   });
+  /// ```
+  #[must_use]
+  pub fn configure_runtime_authority<F>(mut self, f: F) -> Self
+  where
+    F: FnOnce(&mut RuntimeAuthority) + Send + 'static,
+  {
+    self.runtime_authority_hooks.push(Box::new(f));
+    self
+  }
+
   /// Defines the page load hook.
   #[must_use]
   pub fn on_page_load<F>(mut self, on_page_load: F) -> Self
@@ -1461,7 +1485,7 @@ impl<R: Runtime> Builder<R> {
       }));
     }
 
-    let manager = Arc::new(AppManager::with_handlers(
+    let mut manager = AppManager::with_handlers(
       context,
       self.plugins,
       self.invoke_handler,
@@ -1472,7 +1496,13 @@ impl<R: Runtime> Builder<R> {
       #[cfg(desktop)]
       HashMap::new(),
       (self.invoke_responder, self.invoke_initialization_script),
-    ));
+    )?;
+
+    for hook in self.runtime_authority_hooks {
+      hook(&mut manager.runtime_authority);
+    }
+
+    let manager = Arc::new(manager);
 
     // set up all the windows defined in the config
     for config in manager.config().tauri.windows.clone() {
@@ -0,0 +1,4441 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Additional [`CommandArg`] implementations for common argument patterns.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  marker::PhantomData,
+  path::PathBuf,
+  sync::RwLock,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri_utils::acl::resolved::ResolvedCommand;
+use url::Url;
+
+use crate::{
+  ipc::{InvokeBody, InvokeError},
+  runtime::Dispatch,
+  Manager, Runtime,
+};
+
+use super::{CommandArg, CommandItem, CommandScope, Origin, PathScope, StringScope};
+
+/// A store of default argument values, used by [`DefaultFromState`].
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) to provide the defaults.
+#[derive(Debug, Default)]
+pub struct SettingsStore(RwLock<BTreeMap<String, JsonValue>>);
+
+impl SettingsStore {
+  /// Creates an empty store.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the value for the given setting key.
+  pub fn set<T: Serialize>(&self, key: impl Into<String>, value: T) -> serde_json::Result<()> {
+    let value = serde_json::to_value(value)?;
+    self.0.write().unwrap().insert(key.into(), value);
+    Ok(())
+  }
+
+  /// Gets the value for the given setting key.
+  pub fn get(&self, key: &str) -> Option<JsonValue> {
+    self.0.read().unwrap().get(key).cloned()
+  }
+}
+
+/// A key of the [`SettingsStore`], used by [`DefaultFromState`].
+pub trait SettingKey {
+  /// The setting key.
+  const KEY: &'static str;
+}
+
+/// A command argument that falls back to a default read from the [`SettingsStore`] when omitted.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::{DefaultFromState, SettingKey};
+///
+/// struct Theme;
+///
+/// impl SettingKey for Theme {
+///   const KEY: &'static str = "theme";
+/// }
+///
+/// #[tauri::command]
+/// fn apply_theme(theme: DefaultFromState<Theme, String>) {
+///   println!("applying theme {}", theme.into_inner());
+/// }
+/// ```
+pub struct DefaultFromState<K: SettingKey, T>(pub T, PhantomData<K>);
+
+impl<K: SettingKey, T> DefaultFromState<K, T> {
+  /// Returns the inner value.
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<K: SettingKey, T> std::ops::Deref for DefaultFromState<K, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<K: SettingKey, T: std::fmt::Debug> std::fmt::Debug for DefaultFromState<K, T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("DefaultFromState").field(&self.0).finish()
+  }
+}
+
+impl<'de, R: Runtime, K: SettingKey, T: DeserializeOwned> CommandArg<'de, R>
+  for DefaultFromState<K, T>
+{
+  /// Deserializes the value if present on the payload, otherwise reads the default from the [`SettingsStore`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let value = match command.message.payload() {
+      InvokeBody::Json(v) => v.get(command.key).filter(|v| !v.is_null()).cloned(),
+      InvokeBody::Raw(_) => None,
+    };
+
+    let value = match value {
+      Some(value) => value,
+      None => command
+        .message
+        .state_ref()
+        .try_get::<SettingsStore>()
+        .and_then(|store| store.get(K::KEY))
+        .ok_or_else(|| {
+          InvokeError::from_anyhow(anyhow::anyhow!(
+            "command {} missing key {} and no default stored for setting {}",
+            command.name,
+            command.key,
+            K::KEY
+          ))
+        })?,
+    };
+
+    serde_json::from_value(value)
+      .map(|value| Self(value, PhantomData))
+      .map_err(|e| crate::Error::InvalidArgs(command.name, command.key, e).into())
+  }
+}
+
+/// A command argument that falls back to `T::default()` when omitted, instead of failing with a missing key.
+///
+/// A `null` value is treated as omitted, and so is the argument of an IPC call using a bytes payload.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::OrDefault;
+///
+/// #[derive(Default, serde::Deserialize)]
+/// struct SearchOptions {
+///   limit: Option<usize>,
+///   case_sensitive: bool,
+/// }
+///
+/// #[tauri::command]
+/// fn search(query: String, options: OrDefault<SearchOptions>) {
+///   println!("searching {query} (case sensitive: {})", options.case_sensitive);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrDefault<T>(pub T);
+
+impl<T> OrDefault<T> {
+  /// Returns the inner value.
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<T> std::ops::Deref for OrDefault<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<'de, R: Runtime, T: Deserialize<'de> + Default> CommandArg<'de, R> for OrDefault<T> {
+  /// Deserializes the value if present on the payload, otherwise uses `T::default()`.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    match command.message.payload() {
+      InvokeBody::Json(_) => {
+        Option::<T>::from_command(command).map(|value| Self(value.unwrap_or_default()))
+      }
+      InvokeBody::Raw(_) => Ok(Self(T::default())),
+    }
+  }
+}
+
+/// A dotted path into the invoke payload, used by [`JsonPath`].
+pub trait JsonPathKey {
+  /// The path, e.g. `payload.user.id`.
+  const PATH: &'static str;
+}
+
+/// A command argument deserialized from a nested field of the invoke payload.
+///
+/// The path is walked from the root of the payload, one object key per `.` separated segment,
+/// ignoring the argument name. Commands invoked with a bytes payload fail.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::{JsonPath, JsonPathKey};
+///
+/// struct UserId;
+///
+/// impl JsonPathKey for UserId {
+///   const PATH: &'static str = "payload.user.id";
+/// }
+///
+/// #[tauri::command]
+/// fn load_profile(user_id: JsonPath<UserId, u64>) {
+///   println!("loading the profile of user {}", user_id.0);
+/// }
+/// ```
+pub struct JsonPath<P: JsonPathKey, T>(pub T, PhantomData<P>);
+
+impl<P: JsonPathKey, T> JsonPath<P, T> {
+  /// Returns the inner value.
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<P: JsonPathKey, T> std::ops::Deref for JsonPath<P, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<P: JsonPathKey, T: std::fmt::Debug> std::fmt::Debug for JsonPath<P, T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("JsonPath")
+      .field(&P::PATH)
+      .field(&self.0)
+      .finish()
+  }
+}
+
+impl<'de, R: Runtime, P: JsonPathKey, T: Deserialize<'de>> CommandArg<'de, R> for JsonPath<P, T> {
+  /// Walks the path into the payload and deserializes the value found there.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let mut value = match &command.message.payload {
+      InvokeBody::Json(value) => value,
+      _ => return Err(command.payload_type_mismatch().into()),
+    };
+
+    let mut walked = 0;
+    for segment in P::PATH.split('.') {
+      let parent = &P::PATH[..walked.max(1) - 1];
+      value = match value {
+        JsonValue::Object(map) => map.get(segment).ok_or_else(|| {
+          InvokeError::from_anyhow(anyhow::anyhow!(
+            "command {name} missing key `{segment}` of path `{}`",
+            P::PATH
+          ))
+        })?,
+        _ => {
+          return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+            "command {name} expected an object at `{parent}` of path `{}`",
+            P::PATH
+          )))
+        }
+      };
+      walked += segment.len() + 1;
+    }
+
+    T::deserialize(value)
+      .map(|value| Self(value, PhantomData))
+      .map_err(|e| crate::Error::InvalidArgs(name, command.key, e).into())
+  }
+}
+
+/// The name of a request header, used by [`Header`].
+pub trait HeaderKey {
+  /// The header name, matched case-insensitively.
+  const NAME: &'static str;
+}
+
+/// A request header sent by the frontend with the invoke, e.g. a trace id.
+///
+/// `Header<K>` requires the header while `Header<K, Option<String>>` is `None` when it is missing.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::{Header, HeaderKey};
+///
+/// struct TraceId;
+///
+/// impl HeaderKey for TraceId {
+///   const NAME: &'static str = "x-trace-id";
+/// }
+///
+/// #[tauri::command]
+/// fn handler(trace: Header<TraceId>, tenant: Header<TraceId, Option<String>>) {
+///   println!("[{}] tenant {:?}", trace.0, tenant.0);
+/// }
+/// ```
+pub struct Header<K: HeaderKey, T = String>(pub T, PhantomData<K>);
+
+impl<K: HeaderKey, T> Header<K, T> {
+  /// Returns the inner value.
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<K: HeaderKey, T: std::fmt::Debug> std::fmt::Debug for Header<K, T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("Header")
+      .field(&K::NAME)
+      .field(&self.0)
+      .finish()
+  }
+}
+
+/// Reads the header value, failing if it is not valid UTF-8.
+fn header_value<R: Runtime>(
+  command: &CommandItem<'_, R>,
+  name: &str,
+) -> Result<Option<String>, InvokeError> {
+  command
+    .message
+    .headers()
+    .get(name)
+    .map(|value| {
+      value.to_str().map(ToString::to_string).map_err(|_| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {} got a non UTF-8 value for header {name}",
+          command.name
+        ))
+      })
+    })
+    .transpose()
+}
+
+impl<'de, R: Runtime, K: HeaderKey> CommandArg<'de, R> for Header<K, String> {
+  /// Reads the header, failing if it is missing.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    header_value(&command, K::NAME)?
+      .map(|value| Self(value, PhantomData))
+      .ok_or_else(|| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {} missing header {}",
+          command.name,
+          K::NAME
+        ))
+      })
+  }
+}
+
+impl<'de, R: Runtime, K: HeaderKey> CommandArg<'de, R> for Header<K, Option<String>> {
+  /// Reads the header, `None` if it is missing.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    header_value(&command, K::NAME).map(|value| Self(value, PhantomData))
+  }
+}
+
+/// All request headers sent with the invoke, keyed by their lowercase name.
+///
+/// Headers with a non UTF-8 value are skipped, and repeated headers keep their first value.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::Headers;
+///
+/// #[tauri::command]
+/// fn handler(headers: Headers) {
+///   if let Some(trace) = headers.0.get("x-trace-id") {
+///     println!("trace {trace}");
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers(pub BTreeMap<String, String>);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for Headers {
+  /// Copies the headers of the invoke.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let mut headers = BTreeMap::new();
+    for (name, value) in command.message.headers() {
+      if let Ok(value) = value.to_str() {
+        headers
+          .entry(name.as_str().to_string())
+          .or_insert_with(|| value.to_string());
+      }
+    }
+    Ok(Self(headers))
+  }
+}
+
+/// The paths of the last files dropped on the window that invoked the command.
+///
+/// The drop is consumed by the first command taking it, and discarded when a new drag is cancelled.
+/// Empty if nothing was dropped on the window since. If the command has a [`CommandScope`]
+/// configured, each path must be allowed by a [`PathScope`] entry and not denied by any.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::DroppedFiles;
+///
+/// #[tauri::command]
+/// fn import(files: DroppedFiles) {
+///   for path in files.0 {
+///     println!("importing {}", path.display());
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DroppedFiles(pub Vec<PathBuf>);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for DroppedFiles {
+  /// Reads the dropped files of the window and validates them against the command scope.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let window = &command.message.window;
+    let paths = window.manager.window.take_dropped_files(window.label());
+
+    if command.acl.as_ref().and_then(|acl| acl.scope).is_some() {
+      let scope = CommandScope::<PathScope>::from_command(CommandItem {
+        name: command.name,
+        key: command.key,
+        message: command.message,
+        acl: command.acl,
+      })?;
+      for path in &paths {
+        if !scope.is_allowed(path) {
+          return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+            "dropped file {} is not allowed by the scope of command {}",
+            path.display(),
+            command.name
+          )));
+        }
+      }
+    }
+
+    Ok(Self(paths))
+  }
+}
+
+/// The source of the OS notification permission read by [`NotificationPermission`].
+///
+/// Notification plugins manage it with [`Manager::manage`](crate::Manager::manage) to expose the permission of the platform.
+#[derive(Clone)]
+pub struct NotificationPermissionSource(
+  std::sync::Arc<dyn Fn() -> NotificationPermission + Send + Sync>,
+);
+
+impl NotificationPermissionSource {
+  /// Creates a source reading the permission from the given function.
+  pub fn new<F: Fn() -> NotificationPermission + Send + Sync + 'static>(permission: F) -> Self {
+    Self(std::sync::Arc::new(permission))
+  }
+}
+
+impl std::fmt::Debug for NotificationPermissionSource {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("NotificationPermissionSource")
+      .field(&(self.0)())
+      .finish()
+  }
+}
+
+/// The current OS notification permission, read from the managed [`NotificationPermissionSource`]
+/// so commands can fail fast when notifications are denied.
+///
+/// The argument does not read the command payload.
+/// If no source is managed the permission is reported as [`NotificationPermission::Default`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::NotificationPermission;
+///
+/// #[tauri::command]
+/// fn notify(permission: NotificationPermission, title: String) -> Result<(), String> {
+///   if permission == NotificationPermission::Denied {
+///     return Err("notifications are disabled".into());
+///   }
+///   println!("notifying {title}");
+///   Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationPermission {
+  /// The user allowed notifications.
+  Granted,
+  /// The user denied notifications.
+  Denied,
+  /// The user has not decided yet.
+  Default,
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for NotificationPermission {
+  /// Reads the permission from the [`NotificationPermissionSource`] managed by the app.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let app = command.message.window.app_handle();
+    Ok(
+      app
+        .try_state::<NotificationPermissionSource>()
+        .map(|source| (source.0)())
+        .unwrap_or(Self::Default),
+    )
+  }
+}
+
+/// A set of flags that can be built from variant names, used by [`Flags`].
+///
+/// Mirrors the `bitflags::Flags` API so it is trivial to implement for `bitflags` types.
+pub trait FlagSet: Sized {
+  /// The set with no flags.
+  fn empty() -> Self;
+
+  /// Gets the flag with the given variant name.
+  fn from_name(name: &str) -> Option<Self>;
+
+  /// The union of both sets.
+  fn union(self, other: Self) -> Self;
+}
+
+/// A command argument that deserializes an array of flag names into a combined [`FlagSet`].
+///
+/// An empty array is the empty set, and unknown flag names are rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::{FlagSet, Flags};
+///
+/// #[derive(Clone, Copy)]
+/// struct Permissions(u8);
+///
+/// impl FlagSet for Permissions {
+///   fn empty() -> Self {
+///     Self(0)
+///   }
+///
+///   fn from_name(name: &str) -> Option<Self> {
+///     match name {
+///       "READ" => Some(Self(1)),
+///       "WRITE" => Some(Self(2)),
+///       _ => None,
+///     }
+///   }
+///
+///   fn union(self, other: Self) -> Self {
+///     Self(self.0 | other.0)
+///   }
+/// }
+///
+/// #[tauri::command]
+/// fn share(permissions: Flags<Permissions>) {
+///   println!("sharing with permissions {}", permissions.0 .0);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Flags<F: FlagSet>(pub F);
+
+impl<F: FlagSet> Flags<F> {
+  /// Returns the inner flags.
+  pub fn into_inner(self) -> F {
+    self.0
+  }
+}
+
+impl<'de, R: Runtime, F: FlagSet> CommandArg<'de, R> for Flags<F> {
+  /// Deserializes the flag names and folds them into a single [`FlagSet`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let names = Vec::<String>::from_command(command)?;
+    names
+      .iter()
+      .try_fold(F::empty(), |flags, flag| {
+        F::from_name(flag).map(|f| flags.union(f)).ok_or_else(|| {
+          InvokeError::from_anyhow(anyhow::anyhow!("command {name} got unknown flag `{flag}`"))
+        })
+      })
+      .map(Self)
+  }
+}
+
+/// The fields a [`SortSpec`] can sort by.
+///
+/// Only the names accepted by [`SortField::from_name`] are allowed, so commands can safely map them to columns.
+pub trait SortField: Sized {
+  /// Gets the field with the given name.
+  fn from_name(name: &str) -> Option<Self>;
+}
+
+/// The direction of a [`SortSpec`] entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+  /// `asc`, the default when no direction is given.
+  Ascending,
+  /// `desc`.
+  Descending,
+}
+
+/// A command argument that parses sort specifications like `"name:asc,created:desc"`.
+///
+/// Fields are validated against [`SortField`] and directions must be `asc` or `desc`.
+/// An empty string is an empty specification.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::{SortField, SortSpec};
+///
+/// enum Column {
+///   Name,
+///   Created,
+/// }
+///
+/// impl SortField for Column {
+///   fn from_name(name: &str) -> Option<Self> {
+///     match name {
+///       "name" => Some(Self::Name),
+///       "created" => Some(Self::Created),
+///       _ => None,
+///     }
+///   }
+/// }
+///
+/// #[tauri::command]
+/// fn list(sort: SortSpec<Column>) {
+///   println!("sorting by {} fields", sort.0.len());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SortSpec<F: SortField>(pub Vec<(F, SortDirection)>);
+
+impl<F: SortField> SortSpec<F> {
+  /// Returns the fields and their directions, in order.
+  pub fn into_inner(self) -> Vec<(F, SortDirection)> {
+    self.0
+  }
+}
+
+impl<'de, R: Runtime, F: SortField> CommandArg<'de, R> for SortSpec<F> {
+  /// Parses the comma separated `field:direction` entries.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let spec = String::from_command(command)?;
+    if spec.trim().is_empty() {
+      return Ok(Self(Vec::new()));
+    }
+    spec
+      .split(',')
+      .map(|entry| {
+        let (field, direction) = match entry.split_once(':') {
+          Some((field, direction)) => (field.trim(), Some(direction.trim())),
+          None => (entry.trim(), None),
+        };
+        let field = F::from_name(field).ok_or_else(|| {
+          InvokeError::from_anyhow(anyhow::anyhow!(
+            "command {name} got unknown sort field `{field}`"
+          ))
+        })?;
+        let direction = match direction {
+          None | Some("asc") => SortDirection::Ascending,
+          Some("desc") => SortDirection::Descending,
+          Some(direction) => {
+            return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+              "command {name} got invalid sort direction `{direction}`, expected `asc` or `desc`"
+            )))
+          }
+        };
+        Ok((field, direction))
+      })
+      .collect::<Result<_, _>>()
+      .map(Self)
+  }
+}
+
+/// A command argument that parses a log level name, ignoring its case.
+///
+/// Levels are ordered from the most verbose, [`LogLevel::Trace`], to the least verbose, [`LogLevel::Error`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::LogLevel;
+///
+/// #[tauri::command]
+/// fn set_log_level(level: LogLevel) {
+///   println!("logging {level:?} and above");
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+  /// `trace`.
+  Trace,
+  /// `debug`.
+  Debug,
+  /// `info`.
+  Info,
+  /// `warn`.
+  Warn,
+  /// `error`.
+  Error,
+}
+
+impl LogLevel {
+  const NAMES: [(&'static str, Self); 5] = [
+    ("trace", Self::Trace),
+    ("debug", Self::Debug),
+    ("info", Self::Info),
+    ("warn", Self::Warn),
+    ("error", Self::Error),
+  ];
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for LogLevel {
+  /// Parses the level name case-insensitively.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let level = String::from_command(command)?;
+    Self::NAMES
+      .iter()
+      .find(|(n, _)| n.eq_ignore_ascii_case(&level))
+      .map(|(_, level)| *level)
+      .ok_or_else(|| {
+        let valid = Self::NAMES.map(|(n, _)| n).join(", ");
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} got unknown log level `{level}`, expected one of {valid}"
+        ))
+      })
+  }
+}
+
+/// A command argument that reads the bytes payload of the IPC call.
+///
+/// Named arguments can't be read from a bytes payload, so this is the way to receive raw data,
+/// e.g. from `invoke('upload', new Uint8Array([1, 2, 3]))`. Calls with a JSON payload are rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::RawBody;
+///
+/// #[tauri::command]
+/// fn upload(body: RawBody) {
+///   println!("received {} bytes", body.0.len());
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawBody(pub Vec<u8>);
+
+impl RawBody {
+  /// Returns the bytes of the payload.
+  pub fn into_inner(self) -> Vec<u8> {
+    self.0
+  }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for RawBody {
+  /// Copies the bytes of the [`InvokeBody::Raw`] payload.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    match command.message.payload() {
+      InvokeBody::Raw(bytes) => Ok(Self(bytes.clone())),
+      InvokeBody::Json(_) => Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {} expected a bytes payload but the IPC call used a JSON payload",
+        command.name
+      ))),
+    }
+  }
+}
+
+/// The current URL of the webview that invoked the command.
+///
+/// Unlike [`Origin`](super::Origin), this includes the full path, query and fragment of the page.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::WebviewUrl;
+///
+/// #[tauri::command]
+/// fn report(url: WebviewUrl) {
+///   println!("reported from {}", url.0);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebviewUrl(pub Url);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for WebviewUrl {
+  /// Reads the current URL of the invoking webview. Fails if the webview URL is not available.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    command
+      .message
+      .window
+      .window
+      .dispatcher
+      .url()
+      .map(Self)
+      .map_err(|e| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {} could not read the webview URL: {e}",
+          command.name
+        ))
+      })
+  }
+}
+
+/// The label of the window that invoked the command.
+///
+/// Lighter than taking the whole [`Window`](crate::Window) when only the label is needed,
+/// e.g. for logging. The argument does not read the command payload.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::WindowLabel;
+///
+/// #[tauri::command]
+/// fn log(label: WindowLabel, message: String) {
+///   println!("[{}] {message}", label.0);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowLabel(pub String);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for WindowLabel {
+  /// Copies the label of the invoking window.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    Ok(Self(command.message.window.label().to_string()))
+  }
+}
+
+/// The window label patterns the access control list allows for the running command.
+///
+/// Useful to keep secondary authorization decisions, e.g. which windows receive an event,
+/// consistent with the access control list. Empty when the command is not in the access control list.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::{command::AllowedWindows, Manager};
+///
+/// #[tauri::command]
+/// fn broadcast<R: tauri::Runtime>(app: tauri::AppHandle<R>, windows: AllowedWindows) {
+///   for (label, window) in app.windows() {
+///     if windows.matches(&label) {
+///       let _ = window.emit("refresh", ());
+///     }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedWindows(pub Vec<glob::Pattern>);
+
+impl AllowedWindows {
+  /// Whether the window label matches one of the patterns.
+  pub fn matches(&self, label: &str) -> bool {
+    self.0.iter().any(|pattern| pattern.matches(label))
+  }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for AllowedWindows {
+  /// Clones the window patterns of the resolved command.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    Ok(Self(
+      command
+        .acl
+        .as_ref()
+        .map(|acl| acl.windows.clone())
+        .unwrap_or_default(),
+    ))
+  }
+}
+
+/// Where the running command was invoked from and what the access control list resolved for it.
+///
+/// The origin is the one the [`RuntimeAuthority`](super::RuntimeAuthority) checked, computed once when the invoke
+/// was received, so the command can't reach a different conclusion than the authority did.
+/// The argument does not read the command payload.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::InvokeContext;
+///
+/// #[tauri::command]
+/// fn export(context: InvokeContext) -> Result<(), String> {
+///   if !context.is_local() {
+///     return Err(format!("{} can only export from the app", context.window));
+///   }
+///   Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvokeContext {
+  /// The origin the access control list was checked against.
+  pub origin: Origin,
+  /// The label of the window that invoked the command.
+  pub window: String,
+  /// The resolved access control list entry, `None` when the command is not in the access control list.
+  pub resolved: Option<ResolvedCommand>,
+}
+
+impl InvokeContext {
+  /// Whether the command was invoked from the app's own assets.
+  pub fn is_local(&self) -> bool {
+    matches!(self.origin, Origin::Local | Origin::LocalUrl { .. })
+  }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for InvokeContext {
+  /// Copies the origin, window label and resolved access control list entry of the invoke.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    Ok(Self {
+      origin: command.message.origin.clone(),
+      window: command.message.window.label().to_string(),
+      resolved: command.acl.clone(),
+    })
+  }
+}
+
+/// The deadline the frontend set for the invoke, if any.
+///
+/// The deadline is read from the `Tauri-Deadline` header, in milliseconds since the Unix epoch.
+/// Async commands are cancelled when it passes, so this is mostly useful to budget work
+/// or to give up early in synchronous commands.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::Deadline;
+///
+/// #[tauri::command]
+/// async fn search(query: String, deadline: Deadline) -> Vec<String> {
+///   let mut results = Vec::new();
+///   for page in 0.. {
+///     if deadline.is_expired() || page == 10 {
+///       break;
+///     }
+///     results.push(format!("{query} result {page}"));
+///   }
+///   results
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(pub Option<std::time::Instant>);
+
+impl Deadline {
+  /// The time left until the deadline, zero once it passed. `None` if the invoke has no deadline.
+  pub fn remaining(&self) -> Option<std::time::Duration> {
+    self
+      .0
+      .map(|deadline| deadline.saturating_duration_since(std::time::Instant::now()))
+  }
+
+  /// Whether the deadline passed.
+  pub fn is_expired(&self) -> bool {
+    self
+      .remaining()
+      .is_some_and(|remaining| remaining.is_zero())
+  }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for Deadline {
+  /// Reads the deadline header of the invoke, ignoring malformed values.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    Ok(Self(crate::ipc::deadline(command.message.headers())))
+  }
+}
+
+/// The ISO 3166-1 alpha-2 country codes, sorted.
+const COUNTRY_CODES: &[&str] = &[
+  "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
+  "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BL", "BM", "BN", "BO", "BQ", "BR", "BS",
+  "BT", "BV", "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN",
+  "CO", "CR", "CU", "CV", "CW", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE",
+  "EG", "EH", "ER", "ES", "ET", "FI", "FJ", "FK", "FM", "FO", "FR", "GA", "GB", "GD", "GE", "GF",
+  "GG", "GH", "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM",
+  "HN", "HR", "HT", "HU", "ID", "IE", "IL", "IM", "IN", "IO", "IQ", "IR", "IS", "IT", "JE", "JM",
+  "JO", "JP", "KE", "KG", "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC",
+  "LI", "LK", "LR", "LS", "LT", "LU", "LV", "LY", "MA", "MC", "MD", "ME", "MF", "MG", "MH", "MK",
+  "ML", "MM", "MN", "MO", "MP", "MQ", "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA",
+  "NC", "NE", "NF", "NG", "NI", "NL", "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG",
+  "PH", "PK", "PL", "PM", "PN", "PR", "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RS", "RU", "RW",
+  "SA", "SB", "SC", "SD", "SE", "SG", "SH", "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "SS",
+  "ST", "SV", "SX", "SY", "SZ", "TC", "TD", "TF", "TG", "TH", "TJ", "TK", "TL", "TM", "TN", "TO",
+  "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UM", "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI",
+  "VN", "VU", "WF", "WS", "YE", "YT", "ZA", "ZM", "ZW",
+];
+
+/// An ISO 3166-1 alpha-2 country code, e.g. `US` or `FR`.
+///
+/// The code is matched case-insensitively and normalized to uppercase.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::CountryCode;
+///
+/// #[tauri::command]
+/// fn set_region(country: CountryCode) {
+///   println!("region set to {}", country.as_str());
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CountryCode([u8; 2]);
+
+impl CountryCode {
+  /// The uppercase code.
+  pub fn as_str(&self) -> &str {
+    // always two ASCII uppercase letters
+    std::str::from_utf8(&self.0).unwrap()
+  }
+}
+
+impl std::fmt::Display for CountryCode {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl std::str::FromStr for CountryCode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let code = s.trim().to_ascii_uppercase();
+    if COUNTRY_CODES.binary_search(&code.as_str()).is_ok() {
+      let bytes = code.as_bytes();
+      Ok(Self([bytes[0], bytes[1]]))
+    } else {
+      Err(format!("unknown ISO 3166-1 alpha-2 country code `{s}`"))
+    }
+  }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for CountryCode {
+  /// Deserializes the code string and checks it is an assigned ISO 3166-1 alpha-2 code.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let code = String::from_command(command)?;
+    code
+      .parse()
+      .map_err(|e| InvokeError::from_anyhow(anyhow::anyhow!("command {name} got an {e}")))
+  }
+}
+
+/// A geographic coordinate in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LatLng {
+  /// The latitude, from -90 to 90.
+  pub lat: f64,
+  /// The longitude, from -180 to 180.
+  pub lng: f64,
+}
+
+/// The bounding box used to validate [`BoundedLatLng`] arguments.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) to configure the allowed region.
+/// A box whose south-west longitude is greater than its north-east longitude crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoBounds {
+  /// The south-west corner of the box.
+  pub south_west: LatLng,
+  /// The north-east corner of the box.
+  pub north_east: LatLng,
+}
+
+impl GeoBounds {
+  /// Checks if the point is inside the box, edges included.
+  pub fn contains(&self, point: &LatLng) -> bool {
+    let lat = (self.south_west.lat..=self.north_east.lat).contains(&point.lat);
+    let lng = if self.south_west.lng <= self.north_east.lng {
+      (self.south_west.lng..=self.north_east.lng).contains(&point.lng)
+    } else {
+      point.lng >= self.south_west.lng || point.lng <= self.north_east.lng
+    };
+    lat && lng
+  }
+}
+
+/// A [`LatLng`] command argument that must be inside the managed [`GeoBounds`].
+///
+/// Fails if the coordinate is invalid, outside the bounds, or if no bounds are managed.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::BoundedLatLng;
+///
+/// #[tauri::command]
+/// fn check_in(location: BoundedLatLng) {
+///   println!("checked in at {}, {}", location.0.lat, location.0.lng);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundedLatLng(pub LatLng);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for BoundedLatLng {
+  /// Deserializes the coordinate and checks it against the managed [`GeoBounds`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let bounds = command.message.state_ref().try_get::<GeoBounds>();
+    let point = LatLng::from_command(command)?;
+
+    if !(-90.0..=90.0).contains(&point.lat) || !(-180.0..=180.0).contains(&point.lng) {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got an invalid coordinate ({}, {})",
+        point.lat,
+        point.lng
+      )));
+    }
+
+    let bounds = bounds.ok_or_else(|| {
+      InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} requires a bounded coordinate but no GeoBounds are managed"
+      ))
+    })?;
+    if !bounds.contains(&point) {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got coordinate ({}, {}) outside the allowed region",
+        point.lat,
+        point.lng
+      )));
+    }
+
+    Ok(Self(point))
+  }
+}
+
+/// The limits used to validate [`GlobArg`] patterns.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) to override the defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlobLimits {
+  /// Maximum length of the pattern in bytes. Defaults to 256.
+  pub max_length: usize,
+  /// Maximum number of wildcards (`*`, `**`, `?` and `[...]`) in the pattern. Defaults to 16.
+  pub max_wildcards: usize,
+}
+
+impl Default for GlobLimits {
+  fn default() -> Self {
+    Self {
+      max_length: 256,
+      max_wildcards: 16,
+    }
+  }
+}
+
+/// A user supplied glob pattern, compiled with the [`glob`] crate.
+///
+/// Patterns exceeding the managed [`GlobLimits`] (or the default limits) are rejected
+/// to avoid pathological matching.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::GlobArg;
+///
+/// #[tauri::command]
+/// fn search(pattern: GlobArg) {
+///   println!("searching {}", pattern.0.as_str());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GlobArg(pub glob::Pattern);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for GlobArg {
+  /// Deserializes the pattern string, checks the [`GlobLimits`] and compiles it.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let limits = command
+      .message
+      .state_ref()
+      .try_get::<GlobLimits>()
+      .map(|limits| *limits)
+      .unwrap_or_default();
+    let pattern = String::from_command(command)?;
+
+    if pattern.len() > limits.max_length {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got a glob pattern longer than {} bytes",
+        limits.max_length
+      )));
+    }
+    let wildcards = pattern.replace("**", "*").matches(['*', '?', '[']).count();
+    if wildcards > limits.max_wildcards {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got a glob pattern with more than {} wildcards",
+        limits.max_wildcards
+      )));
+    }
+
+    glob::Pattern::new(&pattern).map(Self).map_err(|e| {
+      InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got an invalid glob pattern: {e}"
+      ))
+    })
+  }
+}
+
+/// The MIME types accepted by [`MimeType`] arguments, per command.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage). Commands without an entry accept any type.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use tauri::{command::MimeAllowList, Manager};
+///
+/// tauri::Builder::default().setup(|app| {
+///   app.manage(MimeAllowList::default().allow("set_avatar", ["image/png", "image/*"]));
+///   Ok(())
+/// });
+/// ```
+#[cfg(feature = "mime-type")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mime-type")))]
+#[derive(Debug, Clone, Default)]
+pub struct MimeAllowList(BTreeMap<String, Vec<mime::Mime>>);
+
+#[cfg(feature = "mime-type")]
+impl MimeAllowList {
+  /// Allows the given types for the command. A `*` subtype accepts every subtype, e.g. `image/*`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a type is not a valid MIME type.
+  pub fn allow<I: IntoIterator<Item = S>, S: AsRef<str>>(
+    mut self,
+    command: impl Into<String>,
+    types: I,
+  ) -> Self {
+    self.0.entry(command.into()).or_default().extend(
+      types
+        .into_iter()
+        .map(|t| t.as_ref().parse().expect("invalid MIME type in allow list")),
+    );
+    self
+  }
+
+  /// Whether the command accepts the given type.
+  pub fn is_allowed(&self, command: &str, mime: &mime::Mime) -> bool {
+    self.0.get(command).map_or(true, |allowed| {
+      allowed.iter().any(|a| {
+        a.type_() == mime.type_() && (a.subtype() == mime::STAR || a.subtype() == mime.subtype())
+      })
+    })
+  }
+}
+
+/// A MIME type, parsed with the [`mime`] crate.
+///
+/// Malformed values are rejected, as well as types not allowed for the command by a managed [`MimeAllowList`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::MimeType;
+///
+/// #[tauri::command]
+/// fn upload(content_type: MimeType, data: Vec<u8>) {
+///   if content_type.is_image() {
+///     println!("uploading a {} byte image", data.len());
+///   }
+/// }
+/// ```
+#[cfg(feature = "mime-type")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mime-type")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeType(pub mime::Mime);
+
+#[cfg(feature = "mime-type")]
+impl MimeType {
+  /// Whether this is an `image/*` type.
+  pub fn is_image(&self) -> bool {
+    self.0.type_() == mime::IMAGE
+  }
+
+  /// Whether this is a `text/*` type.
+  pub fn is_text(&self) -> bool {
+    self.0.type_() == mime::TEXT
+  }
+
+  /// Whether this is an `audio/*` type.
+  pub fn is_audio(&self) -> bool {
+    self.0.type_() == mime::AUDIO
+  }
+
+  /// Whether this is a `video/*` type.
+  pub fn is_video(&self) -> bool {
+    self.0.type_() == mime::VIDEO
+  }
+
+  /// The type without its parameters, e.g. `text/plain` for `text/plain; charset=utf-8`.
+  pub fn essence(&self) -> &str {
+    self.0.essence_str()
+  }
+}
+
+#[cfg(feature = "mime-type")]
+impl<'de, R: Runtime> CommandArg<'de, R> for MimeType {
+  /// Deserializes the type string, parses it and checks the [`MimeAllowList`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let allow_list = command.message.state_ref().try_get::<MimeAllowList>();
+    let value = String::from_command(command)?;
+
+    let mime: mime::Mime = value.parse().map_err(|_| {
+      InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got an invalid MIME type `{value}`"
+      ))
+    })?;
+    if let Some(allow_list) = allow_list {
+      if !allow_list.is_allowed(name, &mime) {
+        return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} does not accept the MIME type `{}`",
+          mime.essence_str()
+        )));
+      }
+    }
+
+    Ok(Self(mime))
+  }
+}
+
+/// The keys of the loaded message catalog, used to validate [`MessageKey`] arguments.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) and [`Self::replace`] the keys when another catalog is loaded.
+#[derive(Debug, Default)]
+pub struct MessageCatalog(RwLock<BTreeSet<String>>);
+
+impl MessageCatalog {
+  /// Creates a catalog with the given message keys.
+  pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(keys: I) -> Self {
+    Self(RwLock::new(keys.into_iter().map(Into::into).collect()))
+  }
+
+  /// Replaces the message keys, e.g. after loading another catalog.
+  pub fn replace<I: IntoIterator<Item = S>, S: Into<String>>(&self, keys: I) {
+    *self.0.write().unwrap() = keys.into_iter().map(Into::into).collect();
+  }
+
+  /// Whether the catalog has the message key.
+  pub fn contains(&self, key: &str) -> bool {
+    self.0.read().unwrap().contains(key)
+  }
+
+  /// The keys close to the given key, closest first, at most three.
+  pub fn near_matches(&self, key: &str) -> Vec<String> {
+    let max_distance = (key.chars().count() / 3).max(2);
+    let mut matches: Vec<(usize, &String)> = Vec::new();
+    let keys = self.0.read().unwrap();
+    for candidate in keys.iter() {
+      let distance = edit_distance(key, candidate);
+      if distance <= max_distance {
+        matches.push((distance, candidate));
+      }
+    }
+    matches.sort();
+    matches
+      .into_iter()
+      .take(3)
+      .map(|(_, key)| key.clone())
+      .collect()
+  }
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut row: Vec<usize> = (0..=b.len()).collect();
+  for (i, ca) in a.chars().enumerate() {
+    let mut diagonal = row[0];
+    row[0] = i + 1;
+    for (j, cb) in b.iter().enumerate() {
+      let substitution = diagonal + usize::from(ca != *cb);
+      diagonal = row[j + 1];
+      row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+    }
+  }
+  row[b.len()]
+}
+
+/// A message key that exists in the managed [`MessageCatalog`].
+///
+/// Unknown keys are rejected with the near matches of the catalog, so typos surface at the call
+/// instead of as a missing translation at render time.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::MessageKey;
+///
+/// #[tauri::command]
+/// fn translate(key: MessageKey) -> String {
+///   format!("translation of {}", key.0)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MessageKey(pub String);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for MessageKey {
+  /// Deserializes the key string and checks it against the [`MessageCatalog`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let catalog = command.message.state_ref().try_get::<MessageCatalog>();
+    let key = String::from_command(command)?;
+
+    let catalog = catalog.ok_or_else(|| {
+      InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} requires a message key but no MessageCatalog is managed"
+      ))
+    })?;
+    if catalog.contains(&key) {
+      return Ok(Self(key));
+    }
+
+    let near_matches = catalog.near_matches(&key);
+    Err(InvokeError::from_anyhow(if near_matches.is_empty() {
+      anyhow::anyhow!("command {name} got unknown message key `{key}`")
+    } else {
+      anyhow::anyhow!(
+        "command {name} got unknown message key `{key}`, did you mean {}?",
+        near_matches
+          .iter()
+          .map(|key| format!("`{key}`"))
+          .collect::<Vec<_>>()
+          .join(", ")
+      )
+    }))
+  }
+}
+
+/// A byte count, deserialized from an integer or a human-readable size string.
+///
+/// Strings are a number followed by an optional unit, e.g. `"10MB"`, `"1.5 GiB"` or `"512"`.
+/// Both SI (`kB`, `MB`, `GB`, `TB`, `PB`) and IEC (`KiB`, `MiB`, `GiB`, `TiB`, `PiB`) units are supported,
+/// case-insensitively.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::ByteSize;
+///
+/// #[tauri::command]
+/// fn set_quota(quota: ByteSize) {
+///   println!("quota set to {} bytes", quota.0);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl std::str::FromStr for ByteSize {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    let split = s
+      .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+      .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+      .parse()
+      .map_err(|_| format!("invalid byte size `{s}`"))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+      "" | "b" => 1,
+      "kb" => 1000,
+      "mb" => 1000u64.pow(2),
+      "gb" => 1000u64.pow(3),
+      "tb" => 1000u64.pow(4),
+      "pb" => 1000u64.pow(5),
+      "kib" => 1 << 10,
+      "mib" => 1 << 20,
+      "gib" => 1 << 30,
+      "tib" => 1 << 40,
+      "pib" => 1 << 50,
+      unit => return Err(format!("unknown byte size unit `{unit}`")),
+    };
+    let bytes = number * multiplier as f64;
+    if bytes > u64::MAX as f64 {
+      return Err(format!("byte size `{s}` is too large"));
+    }
+    Ok(Self(bytes.round() as u64))
+  }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+      Bytes(u64),
+      Human(String),
+    }
+
+    match Repr::deserialize(deserializer)? {
+      Repr::Bytes(bytes) => Ok(Self(bytes)),
+      Repr::Human(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+  }
+}
+
+/// A temperature, deserialized from a unit-bearing string or a `{ value, unit }` object
+/// and normalized to degrees Celsius.
+///
+/// Strings are a number followed by a unit, e.g. `"21C"`, `"70 °F"` or `"300K"`.
+/// Units are Celsius (`C`), Fahrenheit (`F`) and Kelvin (`K`), case-insensitively.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::Temperature;
+///
+/// #[tauri::command]
+/// fn set_thermostat(target: Temperature) {
+///   println!("heating to {:.1}°C ({:.1}°F)", target.as_celsius(), target.as_fahrenheit());
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Temperature {
+  celsius: f64,
+}
+
+impl Temperature {
+  /// A temperature in degrees Celsius.
+  pub fn from_celsius(celsius: f64) -> Self {
+    Self { celsius }
+  }
+
+  /// A temperature in degrees Fahrenheit.
+  pub fn from_fahrenheit(fahrenheit: f64) -> Self {
+    Self::from_celsius((fahrenheit - 32.) * 5. / 9.)
+  }
+
+  /// The temperature in degrees Celsius.
+  pub fn as_celsius(&self) -> f64 {
+    self.celsius
+  }
+
+  /// The temperature in degrees Fahrenheit.
+  pub fn as_fahrenheit(&self) -> f64 {
+    self.celsius * 9. / 5. + 32.
+  }
+
+  fn with_unit(value: f64, unit: &str) -> Result<Self, String> {
+    if !value.is_finite() {
+      return Err(format!("invalid temperature `{value}`"));
+    }
+    let unit = unit.trim();
+    match unit.trim_start_matches('°').to_ascii_lowercase().as_str() {
+      "c" | "celsius" => Ok(Self::from_celsius(value)),
+      "f" | "fahrenheit" => Ok(Self::from_fahrenheit(value)),
+      "k" | "kelvin" => Ok(Self::from_celsius(value - 273.15)),
+      "" => Err(format!("missing unit for temperature `{value}`")),
+      _ => Err(format!("unknown temperature unit `{unit}`")),
+    }
+  }
+}
+
+impl std::str::FromStr for Temperature {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    let split = s
+      .trim_end_matches(|c: char| c.is_alphabetic() || c == '°')
+      .len();
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number
+      .trim()
+      .parse()
+      .map_err(|_| format!("invalid temperature `{s}`"))?;
+    Self::with_unit(value, unit)
+  }
+}
+
+impl<'de> Deserialize<'de> for Temperature {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+      Text(String),
+      WithUnit { value: f64, unit: String },
+    }
+
+    match Repr::deserialize(deserializer)? {
+      Repr::Text(s) => s.parse().map_err(serde::de::Error::custom),
+      Repr::WithUnit { value, unit } => {
+        Self::with_unit(value, &unit).map_err(serde::de::Error::custom)
+      }
+    }
+  }
+}
+
+/// An enum with a fallback variant for values it does not know, used by [`WithUnknown`].
+pub trait UnknownVariant {
+  /// The variant used for unrecognized values.
+  fn unknown() -> Self;
+}
+
+/// A command argument that deserializes into the enum `E`, mapping unrecognized variant names
+/// to [`UnknownVariant::unknown`] instead of failing.
+///
+/// Values of a known variant with an invalid content are still rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::{UnknownVariant, WithUnknown};
+///
+/// #[derive(serde::Deserialize)]
+/// enum Theme {
+///   Light,
+///   Dark,
+///   #[serde(skip)]
+///   Unknown,
+/// }
+///
+/// impl UnknownVariant for Theme {
+///   fn unknown() -> Self {
+///     Self::Unknown
+///   }
+/// }
+///
+/// #[tauri::command]
+/// fn set_theme(theme: WithUnknown<Theme>) {
+///   if let Theme::Unknown = theme.into_inner() {
+///     println!("unknown theme, keeping the current one");
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithUnknown<E>(pub E);
+
+impl<E> WithUnknown<E> {
+  /// Returns the inner value.
+  pub fn into_inner(self) -> E {
+    self.0
+  }
+}
+
+/// Gets the variant names of the enum `E` from its [`Deserialize`] implementation.
+fn enum_variants<'de, E: Deserialize<'de>>() -> &'static [&'static str] {
+  use serde::de::{Error, Visitor};
+
+  struct Variants(&'static [&'static str]);
+
+  impl std::fmt::Debug for Variants {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      f.write_str("Variants")
+    }
+  }
+
+  impl std::fmt::Display for Variants {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      f.write_str("enum variants")
+    }
+  }
+
+  impl std::error::Error for Variants {}
+
+  impl Error for Variants {
+    fn custom<T: std::fmt::Display>(_msg: T) -> Self {
+      Self(&[])
+    }
+  }
+
+  /// A deserializer that only records the variants the enum expects.
+  struct VariantsDeserializer;
+
+  impl<'de> serde::Deserializer<'de> for VariantsDeserializer {
+    type Error = Variants;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Variants> {
+      Err(Variants(&[]))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+      self,
+      _name: &'static str,
+      variants: &'static [&'static str],
+      _visitor: V,
+    ) -> Result<V::Value, Variants> {
+      Err(Variants(variants))
+    }
+
+    serde::forward_to_deserialize_any! {
+      bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+      bytes byte_buf option unit unit_struct newtype_struct seq tuple
+      tuple_struct map struct identifier ignored_any
+    }
+  }
+
+  match E::deserialize(VariantsDeserializer) {
+    Err(Variants(variants)) => variants,
+    Ok(_) => &[],
+  }
+}
+
+impl<'de, R: Runtime, E: DeserializeOwned + UnknownVariant> CommandArg<'de, R> for WithUnknown<E> {
+  /// Deserializes the enum, falling back to [`UnknownVariant::unknown`] on unrecognized variant names.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let key = command.key;
+    let value = JsonValue::from_command(command)?;
+
+    let variant = match &value {
+      JsonValue::String(variant) => Some(variant.as_str()),
+      JsonValue::Object(map) if map.len() == 1 => map.keys().next().map(|k| k.as_str()),
+      _ => None,
+    };
+    if let Some(variant) = variant {
+      if !enum_variants::<E>().contains(&variant) {
+        return Ok(Self(E::unknown()));
+      }
+    }
+
+    serde_json::from_value(value)
+      .map(Self)
+      .map_err(|e| crate::Error::InvalidArgs(name, key, e).into())
+  }
+}
+
+/// A command argument that deserializes an array of enum variant names into an ordered set.
+///
+/// Duplicated and unknown variants are rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::UniqueEnumSet;
+///
+/// #[derive(serde::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+/// #[serde(rename_all = "camelCase")]
+/// enum Column {
+///   Name,
+///   Size,
+///   ModifiedAt,
+/// }
+///
+/// #[tauri::command]
+/// fn list_files(columns: UniqueEnumSet<Column>) {
+///   println!("including {} columns", columns.0.len());
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniqueEnumSet<E: Ord>(pub BTreeSet<E>);
+
+impl<E: Ord> UniqueEnumSet<E> {
+  /// Returns the inner set.
+  pub fn into_inner(self) -> BTreeSet<E> {
+    self.0
+  }
+}
+
+impl<'de, R: Runtime, E: DeserializeOwned + Ord> CommandArg<'de, R> for UniqueEnumSet<E> {
+  /// Deserializes every variant of the array, rejecting duplicates.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let key = command.key;
+    let values = Vec::<JsonValue>::from_command(command)?;
+    let mut set = BTreeSet::new();
+    for value in values {
+      let variant = E::deserialize(&value)
+        .map_err(|e| InvokeError::from(crate::Error::InvalidArgs(name, key, e)))?;
+      if !set.insert(variant) {
+        return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} got duplicated value {value} for `{key}`"
+        )));
+      }
+    }
+    Ok(Self(set))
+  }
+}
+
+/// A command argument that derives the operation `Op` from the suffix of the invoked command name,
+/// so a single handler can serve a family of commands like `entity_create` and `entity_update`.
+///
+/// The suffix is the part of the name after its last `_`, deserialized as a unit variant of `Op`.
+/// Unknown suffixes are rejected. The argument does not read the command payload.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::CommandSuffixOp;
+///
+/// #[derive(serde::Deserialize)]
+/// #[serde(rename_all = "snake_case")]
+/// enum Op {
+///   Create,
+///   Update,
+/// }
+///
+/// #[tauri::command]
+/// fn entity(op: CommandSuffixOp<Op>) {
+///   match op.into_inner() {
+///     Op::Create => println!("creating"),
+///     Op::Update => println!("updating"),
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSuffixOp<Op>(pub Op);
+
+impl<Op> CommandSuffixOp<Op> {
+  /// Returns the inner operation.
+  pub fn into_inner(self) -> Op {
+    self.0
+  }
+}
+
+impl<'de, R: Runtime, Op: DeserializeOwned> CommandArg<'de, R> for CommandSuffixOp<Op> {
+  /// Derives the operation from the suffix of the invoked command name.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let invoked = command.message.command();
+    let suffix = invoked
+      .rsplit_once('_')
+      .map(|(_, suffix)| suffix)
+      .ok_or_else(|| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {invoked} has no operation suffix for key {}",
+          command.key
+        ))
+      })?;
+    serde_json::from_value(JsonValue::String(suffix.into()))
+      .map(Self)
+      .map_err(|_| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "unknown operation `{suffix}` in command {invoked}"
+        ))
+      })
+  }
+}
+
+/// A list of `key=value` strings, e.g. the arguments of a CLI bridge, parsed into a map.
+///
+/// The value is everything after the first `=`. Entries without `=` are rejected
+/// and the last entry wins when a key is repeated.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::EnvArgs;
+///
+/// #[tauri::command]
+/// fn run(args: EnvArgs) {
+///   for (key, value) in args.0 {
+///     println!("{key} = {value}");
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvArgs(pub BTreeMap<String, String>);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for EnvArgs {
+  /// Deserializes the list of strings and splits each entry at its first `=`.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let entries = Vec::<String>::from_command(command)?;
+
+    entries
+      .into_iter()
+      .map(|entry| match entry.split_once('=') {
+        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+        None => Err(InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} expected a `key=value` entry, got `{entry}`"
+        ))),
+      })
+      .collect::<Result<_, _>>()
+      .map(Self)
+  }
+}
+
+/// A state machine whose transitions are validated by [`Transition`].
+pub trait StateMachine: DeserializeOwned + Clone + std::fmt::Debug + Send + 'static {
+  /// The transition table: whether the machine can go from this state to the target state.
+  fn can_transition(&self, target: &Self) -> bool;
+}
+
+/// A command argument that deserializes the target state of the state machine `S` and
+/// validates the transition from its current state, read from a managed [`std::sync::Mutex<S>`].
+///
+/// Illegal transitions are rejected, as is every target if the current state is not managed.
+/// The argument does not update the current state.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Mutex;
+/// use tauri::{command::{StateMachine, Transition}, State};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+/// #[serde(rename_all = "camelCase")]
+/// enum Player {
+///   Stopped,
+///   Playing,
+///   Paused,
+/// }
+///
+/// impl StateMachine for Player {
+///   fn can_transition(&self, target: &Self) -> bool {
+///     matches!(
+///       (self, target),
+///       (Player::Stopped, Player::Playing)
+///         | (Player::Playing, Player::Paused | Player::Stopped)
+///         | (Player::Paused, Player::Playing | Player::Stopped)
+///     )
+///   }
+/// }
+///
+/// #[tauri::command]
+/// fn set_player(state: Transition<Player>, player: State<'_, Mutex<Player>>) {
+///   *player.lock().unwrap() = state.to;
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition<S> {
+  /// The current state.
+  pub from: S,
+  /// The target state.
+  pub to: S,
+}
+
+impl<'de, R: Runtime, S: StateMachine> CommandArg<'de, R> for Transition<S> {
+  /// Deserializes the target state and checks the transition from the current state.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let key = command.key;
+    let to = S::from_command(command)?;
+
+    let from = command
+      .message
+      .state_ref()
+      .try_get::<std::sync::Mutex<S>>()
+      .map(|current| current.lock().unwrap().clone())
+      .ok_or_else(|| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} has no managed current state for key {key}"
+        ))
+      })?;
+
+    if !from.can_transition(&to) {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} cannot transition from {from:?} to {to:?}"
+      )));
+    }
+
+    Ok(Self { from, to })
+  }
+}
+
+/// A `ROWS`×`COLS` matrix of numbers deserialized from a flat JSON array in row-major order.
+///
+/// Arrays whose length is not `ROWS * COLS` are rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::Matrix;
+///
+/// #[tauri::command]
+/// fn transform(matrix: Matrix<3, 3>) {
+///   println!("translation: ({}, {})", matrix.get(0, 2), matrix.get(1, 2));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<const ROWS: usize, const COLS: usize>(Vec<f64>);
+
+impl<const ROWS: usize, const COLS: usize> Matrix<ROWS, COLS> {
+  /// The number of rows.
+  pub const ROWS: usize = ROWS;
+  /// The number of columns.
+  pub const COLS: usize = COLS;
+
+  /// The element at the given row and column.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the row or column is out of bounds.
+  pub fn get(&self, row: usize, col: usize) -> f64 {
+    assert!(row < ROWS && col < COLS, "matrix index out of bounds");
+    self.0[row * COLS + col]
+  }
+
+  /// The elements of the given row.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the row is out of bounds.
+  pub fn row(&self, row: usize) -> &[f64] {
+    &self.0[row * COLS..(row + 1) * COLS]
+  }
+
+  /// Iterates over the rows.
+  pub fn rows(&self) -> impl Iterator<Item = &[f64]> {
+    self.0.chunks(COLS.max(1)).take(ROWS)
+  }
+
+  /// The elements in row-major order.
+  pub fn as_slice(&self) -> &[f64] {
+    &self.0
+  }
+}
+
+impl<'de, R: Runtime, const ROWS: usize, const COLS: usize> CommandArg<'de, R>
+  for Matrix<ROWS, COLS>
+{
+  /// Deserializes the flat array and checks its length.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let key = command.key;
+    let elements = Vec::<f64>::from_command(command)?;
+
+    if elements.len() != ROWS * COLS {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} expected {} elements for the {ROWS}x{COLS} matrix {key}, got {}",
+        ROWS * COLS,
+        elements.len()
+      )));
+    }
+
+    Ok(Self(elements))
+  }
+}
+
+/// A string argument that must be allowed by the [`CommandScope<StringScope>`] of the command.
+///
+/// Values denied by the scope or absent from its allow-list are rejected,
+/// as is every value if the command has no scope.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::ScopedString;
+///
+/// #[tauri::command]
+/// fn list(resource_type: ScopedString) {
+///   println!("listing {}", resource_type.0);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopedString(pub String);
+
+impl<'de, R: Runtime> CommandArg<'de, R> for ScopedString {
+  /// Deserializes the string and checks it against the command scope.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let scope = CommandScope::<StringScope>::from_command(CommandItem {
+      name: command.name,
+      key: command.key,
+      message: command.message,
+      acl: command.acl,
+    });
+    let name = command.name;
+    let value = String::from_command(command)?;
+
+    let allowed = scope.is_ok_and(|scope| {
+      !scope.denies().iter().any(|entry| entry.value == value)
+        && scope.allows().iter().any(|entry| entry.value == value)
+    });
+    if allowed {
+      Ok(Self(value))
+    } else {
+      Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} does not allow the value `{value}`"
+      )))
+    }
+  }
+}
+
+/// The options used to validate [`NormalizedStr`] arguments.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) to override the defaults.
+#[cfg(feature = "unicode-normalization")]
+#[cfg_attr(docsrs, doc(cfg(feature = "unicode-normalization")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NormalizationOptions {
+  /// Whether strings mixing characters of several scripts (e.g. latin and cyrillic) are rejected.
+  /// Disabled by default.
+  pub reject_mixed_script: bool,
+}
+
+/// A string argument normalized to the Unicode Normalization Form C (NFC).
+///
+/// If [`NormalizationOptions::reject_mixed_script`] is enabled, strings mixing several scripts,
+/// which are commonly used in homograph attacks, are rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::NormalizedStr;
+///
+/// #[tauri::command]
+/// fn rename(name: NormalizedStr) {
+///   println!("renaming to {}", name.0);
+/// }
+/// ```
+#[cfg(feature = "unicode-normalization")]
+#[cfg_attr(docsrs, doc(cfg(feature = "unicode-normalization")))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NormalizedStr(pub String);
+
+#[cfg(feature = "unicode-normalization")]
+impl<'de, R: Runtime> CommandArg<'de, R> for NormalizedStr {
+  /// Deserializes the string, normalizes it and checks the [`NormalizationOptions`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    use unicode_normalization::UnicodeNormalization;
+    use unicode_security::MixedScript;
+
+    let name = command.name;
+    let options = command
+      .message
+      .state_ref()
+      .try_get::<NormalizationOptions>()
+      .map(|options| *options)
+      .unwrap_or_default();
+    let value = String::from_command(command)?.nfc().collect::<String>();
+
+    if options.reject_mixed_script && !value.is_single_script() {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got `{value}` which mixes several scripts"
+      )));
+    }
+
+    Ok(Self(value))
+  }
+}
+
+/// The limits used to compile [`RegexArg`] patterns.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) to override the defaults.
+#[cfg(feature = "regex")]
+#[cfg_attr(docsrs, doc(cfg(feature = "regex")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexLimits {
+  /// Maximum size in bytes of the compiled regex. Defaults to 1 MiB.
+  pub size_limit: usize,
+}
+
+#[cfg(feature = "regex")]
+impl Default for RegexLimits {
+  fn default() -> Self {
+    Self {
+      size_limit: 1 << 20,
+    }
+  }
+}
+
+/// A user supplied regular expression, compiled with the [`regex`] crate.
+///
+/// The `regex` crate guarantees linear time matching so there is no catastrophic backtracking,
+/// and patterns compiling to a program larger than the managed [`RegexLimits`] are rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::RegexArg;
+///
+/// #[tauri::command]
+/// fn filter(lines: Vec<String>, pattern: RegexArg) -> Vec<String> {
+///   lines.into_iter().filter(|line| pattern.0.is_match(line)).collect()
+/// }
+/// ```
+#[cfg(feature = "regex")]
+#[cfg_attr(docsrs, doc(cfg(feature = "regex")))]
+#[derive(Debug, Clone)]
+pub struct RegexArg(pub regex::Regex);
+
+#[cfg(feature = "regex")]
+impl<'de, R: Runtime> CommandArg<'de, R> for RegexArg {
+  /// Deserializes the pattern string and compiles it within the [`RegexLimits`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let limits = command
+      .message
+      .state_ref()
+      .try_get::<RegexLimits>()
+      .map(|limits| *limits)
+      .unwrap_or_default();
+    let pattern = String::from_command(command)?;
+
+    regex::RegexBuilder::new(&pattern)
+      .size_limit(limits.size_limit)
+      .build()
+      .map(Self)
+      .map_err(|e| match e {
+        regex::Error::CompiledTooBig(limit) => InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} got a regex exceeding the size limit of {limit} bytes"
+        )),
+        e => InvokeError::from_anyhow(anyhow::anyhow!("command {name} got an invalid regex: {e}")),
+      })
+  }
+}
+
+/// The clock used by [`TimeOrNow`] to resolve the `"now"` sentinel.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) to override the system clock, e.g. in tests.
+#[cfg(feature = "time")]
+#[cfg_attr(docsrs, doc(cfg(feature = "time")))]
+#[derive(Clone)]
+pub struct Clock(std::sync::Arc<dyn Fn() -> time::OffsetDateTime + Send + Sync>);
+
+#[cfg(feature = "time")]
+impl Clock {
+  /// Creates a clock reading the current time from the given function.
+  pub fn new<F: Fn() -> time::OffsetDateTime + Send + Sync + 'static>(now: F) -> Self {
+    Self(std::sync::Arc::new(now))
+  }
+
+  /// The current time according to this clock.
+  pub fn now(&self) -> time::OffsetDateTime {
+    (self.0)()
+  }
+}
+
+#[cfg(feature = "time")]
+impl Default for Clock {
+  /// The system clock, in UTC.
+  fn default() -> Self {
+    Self::new(time::OffsetDateTime::now_utc)
+  }
+}
+
+#[cfg(feature = "time")]
+impl std::fmt::Debug for Clock {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("Clock").field(&self.now()).finish()
+  }
+}
+
+/// A point in time given either as an RFC 3339 timestamp or as the `"now"` sentinel,
+/// which resolves to the current time of the managed [`Clock`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::TimeOrNow;
+///
+/// #[tauri::command]
+/// fn schedule(at: TimeOrNow) {
+///   println!("scheduled at {}", at.0);
+/// }
+/// ```
+#[cfg(feature = "time")]
+#[cfg_attr(docsrs, doc(cfg(feature = "time")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeOrNow(pub time::OffsetDateTime);
+
+#[cfg(feature = "time")]
+impl<'de, R: Runtime> CommandArg<'de, R> for TimeOrNow {
+  /// Deserializes the string, resolving `"now"` with the [`Clock`] and parsing other values as RFC 3339.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let value = String::from_command(command)?;
+
+    if value == "now" {
+      let now = match command.message.state_ref().try_get::<Clock>() {
+        Some(clock) => clock.now(),
+        None => Clock::default().now(),
+      };
+      return Ok(Self(now));
+    }
+
+    time::OffsetDateTime::parse(&value, &time::format_description::well_known::Rfc3339)
+      .map(Self)
+      .map_err(|e| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} expected `now` or an RFC 3339 timestamp, got `{value}`: {e}"
+        ))
+      })
+  }
+}
+
+/// The minimum age required by [`Birthdate`]. Defaults to 18.
+///
+/// Manage it with [`Manager::manage`](crate::Manager::manage) to override the default.
+#[cfg(feature = "time")]
+#[cfg_attr(docsrs, doc(cfg(feature = "time")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinimumAge(pub u32);
+
+#[cfg(feature = "time")]
+impl Default for MinimumAge {
+  fn default() -> Self {
+    Self(18)
+  }
+}
+
+/// A birthdate given as an ISO 8601 date, e.g. `2000-01-31`, of someone at least [`MinimumAge`] years old
+/// according to the managed [`Clock`].
+///
+/// Future dates are rejected.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::Birthdate;
+///
+/// #[tauri::command]
+/// fn sign_up(birthdate: Birthdate) {
+///   println!("welcome, born on {}", birthdate.0);
+/// }
+/// ```
+#[cfg(feature = "time")]
+#[cfg_attr(docsrs, doc(cfg(feature = "time")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Birthdate(pub time::Date);
+
+#[cfg(feature = "time")]
+impl<'de, R: Runtime> CommandArg<'de, R> for Birthdate {
+  /// Deserializes the date and checks the age it implies against the [`MinimumAge`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let state = command.message.state_ref();
+    let minimum_age = state
+      .try_get::<MinimumAge>()
+      .map(|age| *age)
+      .unwrap_or_default();
+    let today = match state.try_get::<Clock>() {
+      Some(clock) => clock.now(),
+      None => Clock::default().now(),
+    }
+    .date();
+    let value = String::from_command(command)?;
+
+    let birthdate = time::Date::parse(&value, &time::format_description::well_known::Iso8601::DATE)
+      .map_err(|e| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} expected an ISO 8601 date, got `{value}`: {e}"
+        ))
+      })?;
+
+    if birthdate > today {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} got the birthdate {birthdate} which is in the future"
+      )));
+    }
+
+    let had_birthday = (today.month(), today.day()) >= (birthdate.month(), birthdate.day());
+    let age = today.year() - birthdate.year() - i32::from(!had_birthday);
+    if age < minimum_age.0 as i32 {
+      return Err(InvokeError::from_anyhow(anyhow::anyhow!(
+        "command {name} requires a minimum age of {}",
+        minimum_age.0
+      )));
+    }
+
+    Ok(Self(birthdate))
+  }
+}
+
+/// The key used to sign and verify [`Cursor`]s.
+///
+/// When managed by the app, cursors must carry a valid HMAC-SHA256 signature of their position.
+#[cfg(feature = "cursor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cursor")))]
+#[derive(Clone)]
+pub struct CursorKey(Vec<u8>);
+
+#[cfg(feature = "cursor")]
+impl CursorKey {
+  /// Creates a key from the given secret.
+  pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+    Self(secret.into())
+  }
+
+  fn sign(&self, message: &[u8]) -> [u8; 32] {
+    hmac_sha256(&self.0, message)
+  }
+}
+
+/// Computes the HMAC-SHA256 of the message.
+#[cfg(any(feature = "cursor", feature = "grant"))]
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+  use sha2::{Digest, Sha256};
+
+  let mut block = [0u8; 64];
+  if key.len() > block.len() {
+    block[..32].copy_from_slice(&Sha256::digest(key));
+  } else {
+    block[..key.len()].copy_from_slice(key);
+  }
+  let inner = Sha256::new()
+    .chain_update(block.map(|b| b ^ 0x36))
+    .chain_update(message)
+    .finalize();
+  Sha256::new()
+    .chain_update(block.map(|b| b ^ 0x5c))
+    .chain_update(inner)
+    .finalize()
+    .into()
+}
+
+/// Compares the signatures in constant time.
+#[cfg(any(feature = "cursor", feature = "grant"))]
+fn signature_matches(signature: &[u8], expected: &[u8]) -> bool {
+  signature.len() == expected.len()
+    && signature
+      .iter()
+      .zip(expected)
+      .fold(0, |diff, (a, b)| diff | (a ^ b))
+      == 0
+}
+
+#[cfg(feature = "cursor")]
+impl std::fmt::Debug for CursorKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("CursorKey").finish_non_exhaustive()
+  }
+}
+
+/// A command argument that decodes an opaque pagination cursor into the position `T`.
+///
+/// Cursors are the URL-safe base64 encoding of the JSON position, see [`Cursor::encode`].
+/// If a [`CursorKey`] is managed by the app, the position is followed by a `.` and the base64 encoded signature,
+/// and cursors with a missing or invalid signature are rejected as tampered.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::Cursor;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Position {
+///   id: u64,
+/// }
+///
+/// #[tauri::command]
+/// fn list(cursor: Option<Cursor<Position>>) -> String {
+///   let after = cursor.map(|c| c.0.id).unwrap_or_default();
+///   Cursor::encode(&Position { id: after + 50 }, None).unwrap()
+/// }
+/// ```
+#[cfg(feature = "cursor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cursor")))]
+#[derive(Debug, Clone)]
+pub struct Cursor<T>(pub T);
+
+#[cfg(feature = "cursor")]
+impl<T> Cursor<T> {
+  /// Returns the decoded position.
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+#[cfg(feature = "cursor")]
+impl<T: Serialize> Cursor<T> {
+  /// Encodes the position as a cursor, signed with the key if any.
+  pub fn encode(position: &T, key: Option<&CursorKey>) -> serde_json::Result<String> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    let json = serde_json::to_vec(position)?;
+    let mut cursor = URL_SAFE_NO_PAD.encode(&json);
+    if let Some(key) = key {
+      cursor.push('.');
+      cursor.push_str(&URL_SAFE_NO_PAD.encode(key.sign(&json)));
+    }
+    Ok(cursor)
+  }
+}
+
+#[cfg(feature = "cursor")]
+impl<'de, R: Runtime, T: DeserializeOwned> CommandArg<'de, R> for Cursor<T> {
+  /// Decodes the cursor, verifying its signature if a [`CursorKey`] is managed.
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    let name = command.name;
+    let key = command.message.state_ref().try_get::<CursorKey>();
+    let cursor = String::from_command(command)?;
+    let malformed =
+      || InvokeError::from_anyhow(anyhow::anyhow!("command {name} got a malformed cursor"));
+    let tampered =
+      || InvokeError::from_anyhow(anyhow::anyhow!("command {name} got a tampered cursor"));
+
+    let (position, signature) = match cursor.split_once('.') {
+      Some((position, signature)) => (position, Some(signature)),
+      None => (cursor.as_str(), None),
+    };
+    let json = URL_SAFE_NO_PAD.decode(position).map_err(|_| malformed())?;
+    match (key, signature) {
+      (Some(key), Some(signature)) => {
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| malformed())?;
+        if !signature_matches(&signature, &key.sign(&json)) {
+          return Err(tampered());
+        }
+      }
+      (Some(_), None) => return Err(tampered()),
+      (None, Some(_)) => return Err(malformed()),
+      (None, None) => {}
+    }
+    serde_json::from_slice(&json)
+      .map(Self)
+      .map_err(|_| malformed())
+  }
+}
+
+/// The key used to sign and verify [`Grant`]s, with the grant format version it accepts.
+///
+/// Must be managed by the app for commands taking a [`Grant`].
+#[cfg(feature = "grant")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grant")))]
+#[derive(Clone)]
+pub struct GrantKey {
+  secret: Vec<u8>,
+  version: u32,
+}
+
+#[cfg(feature = "grant")]
+impl GrantKey {
+  /// Creates a key from the given secret, accepting grants of the given version.
+  pub fn new(secret: impl Into<Vec<u8>>, version: u32) -> Self {
+    Self {
+      secret: secret.into(),
+      version,
+    }
+  }
+
+  /// Issues a grant for the claims, valid until `expires_at` and signed with this key.
+  pub fn issue<C: Serialize>(
+    &self,
+    claims: &C,
+    expires_at: std::time::SystemTime,
+  ) -> serde_json::Result<String> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    let expires_at = expires_at
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+    let json = serde_json::to_vec(&serde_json::json!({
+      "version": self.version,
+      "expiresAt": expires_at,
+      "claims": claims,
+    }))?;
+    let payload = URL_SAFE_NO_PAD.encode(json);
+    let signature = URL_SAFE_NO_PAD.encode(hmac_sha256(&self.secret, payload.as_bytes()));
+    Ok(format!("{payload}.{signature}"))
+  }
+}
+
+#[cfg(feature = "grant")]
+impl std::fmt::Debug for GrantKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("GrantKey")
+      .field("version", &self.version)
+      .finish_non_exhaustive()
+  }
+}
+
+/// Why a [`Grant`] was rejected.
+///
+/// Sent to the frontend as `{ "type": string, "data": any }`, see [`crate::ipc::TypedError`].
+#[cfg(feature = "grant")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grant")))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum GrantError {
+  /// The command was invoked without a grant.
+  Missing,
+  /// The grant is not a valid signed grant.
+  Malformed,
+  /// The signature of the grant does not match the [`GrantKey`].
+  InvalidSignature,
+  /// The grant format version is not the one of the [`GrantKey`].
+  #[serde(rename_all = "camelCase")]
+  WrongVersion {
+    /// The version accepted by the key.
+    expected: u32,
+    /// The version of the grant.
+    found: u32,
+  },
+  /// The grant has expired.
+  Expired,
+}
+
+/// A command argument holding the claims `C` of a verified signed grant.
+///
+/// The grant is read from the argument, or from the [`Grant::HEADER`] request header if the argument is missing,
+/// and issued with [`GrantKey::issue`]. Its signature, format version and expiry are checked against the managed [`GrantKey`],
+/// and rejected grants fail with a [`GrantError`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::command::Grant;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Claims {
+///   document: u64,
+/// }
+///
+/// #[tauri::command]
+/// fn delete_document(grant: Grant<Claims>) {
+///   println!("deleting document {}", grant.0.document);
+/// }
+/// ```
+#[cfg(feature = "grant")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grant")))]
+#[derive(Debug, Clone)]
+pub struct Grant<C>(pub C);
+
+#[cfg(feature = "grant")]
+impl<C> Grant<C> {
+  /// The request header the grant is read from when the argument is missing.
+  pub const HEADER: &'static str = "Tauri-Grant";
+
+  /// Returns the claims.
+  pub fn into_inner(self) -> C {
+    self.0
+  }
+}
+
+#[cfg(feature = "grant")]
+impl<C: DeserializeOwned> Grant<C> {
+  /// Verifies the signature, version and expiry of the grant.
+  fn verify(grant: &str, key: &GrantKey) -> Result<Self, GrantError> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    #[derive(Deserialize)]
+    struct Version {
+      version: u32,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Envelope<C> {
+      expires_at: u64,
+      claims: C,
+    }
+
+    let (payload, signature) = grant.split_once('.').ok_or(GrantError::Malformed)?;
+    let signature = URL_SAFE_NO_PAD
+      .decode(signature)
+      .map_err(|_| GrantError::Malformed)?;
+    if !signature_matches(&signature, &hmac_sha256(&key.secret, payload.as_bytes())) {
+      return Err(GrantError::InvalidSignature);
+    }
+    let json = URL_SAFE_NO_PAD
+      .decode(payload)
+      .map_err(|_| GrantError::Malformed)?;
+
+    // check the version before the claims, which may have changed shape
+    let Version { version } = serde_json::from_slice(&json).map_err(|_| GrantError::Malformed)?;
+    if version != key.version {
+      return Err(GrantError::WrongVersion {
+        expected: key.version,
+        found: version,
+      });
+    }
+
+    let envelope: Envelope<C> = serde_json::from_slice(&json).map_err(|_| GrantError::Malformed)?;
+    let now = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+    if envelope.expires_at <= now {
+      return Err(GrantError::Expired);
+    }
+    Ok(Self(envelope.claims))
+  }
+}
+
+#[cfg(feature = "grant")]
+impl<'de, R: Runtime, C: DeserializeOwned> CommandArg<'de, R> for Grant<C> {
+  /// Verifies the grant against the managed [`GrantKey`].
+  fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+    let name = command.name;
+    let key = command
+      .message
+      .state_ref()
+      .try_get::<GrantKey>()
+      .ok_or_else(|| {
+        InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {name} requires a grant but no GrantKey is managed"
+        ))
+      })?;
+    let rejected = |error| InvokeError::from(crate::ipc::TypedError(error));
+
+    let grant = match Option::<String>::from_command(command)? {
+      Some(grant) => grant,
+      None => command
+        .message
+        .headers()
+        .get(Self::HEADER)
+        .ok_or(GrantError::Missing)
+        .and_then(|grant| grant.to_str().map_err(|_| GrantError::Malformed))
+        .map_err(rejected)?
+        .to_string(),
+    };
+    Self::verify(&grant, &key).map_err(rejected)
+  }
+}
+
+/// A command argument that runs several extractors on the same [`CommandItem`] and returns them as a tuple.
+///
+/// The first extraction error short-circuits.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::{command::Extract, AppHandle, Manager, Window};
+///
+/// #[tauri::command]
+/// fn focus(ctx: Extract<(AppHandle, Window)>) {
+///   let Extract((app, window)) = ctx;
+///   println!("{} windows open, focusing {}", app.windows().len(), window.label());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Extract<T>(pub T);
+
+impl<T> Extract<T> {
+  /// Returns the extracted tuple.
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+macro_rules! impl_extract_tuple {
+  ($($ty:ident),+) => {
+    impl<'de, R: Runtime, $($ty: CommandArg<'de, R>),+> CommandArg<'de, R> for Extract<($($ty,)+)> {
+      /// Runs each extractor in order.
+      fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        Ok(Self(($($ty::from_command(command)?,)+)))
+      }
+    }
+  };
+}
+
+impl_extract_tuple!(A);
+impl_extract_tuple!(A, B);
+impl_extract_tuple!(A, B, C);
+impl_extract_tuple!(A, B, C, D);
+impl_extract_tuple!(A, B, C, D, E);
+impl_extract_tuple!(A, B, C, D, E, F);
+impl_extract_tuple!(A, B, C, D, E, F, G);
+impl_extract_tuple!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use http::HeaderMap;
+  use serde_json::json;
+  use tauri_utils::acl::{
+    resolved::{ResolvedCommand, ResolvedScope},
+    Value,
+  };
+
+  use super::*;
+  use crate::{
+    command::{OptionalCommandScope, OptionalGlobalScope, PluginAcl},
+    ipc::InvokeMessage,
+    sealed::ManagerBase,
+    test::{mock_app, mock_builder, mock_context, noop_assets, MockRuntime},
+    App, Manager, WindowBuilder,
+  };
+
+  /// Creates an [`InvokeMessage`] for the given command and payload on a mock window.
+  pub(crate) fn invoke_message(
+    app: &App<MockRuntime>,
+    command: &str,
+    payload: impl Into<InvokeBody>,
+  ) -> InvokeMessage<MockRuntime> {
+    let window = app.get_window("main").unwrap_or_else(|| {
+      WindowBuilder::new(app, "main", Default::default())
+        .build()
+        .unwrap()
+    });
+    InvokeMessage::new(
+      window,
+      app.manager().state(),
+      command.into(),
+      payload.into(),
+      HeaderMap::new(),
+    )
+  }
+
+  /// Runs [`CommandArg::from_command`] for the given key of the message.
+  pub(crate) fn extract<'a, T: CommandArg<'a, MockRuntime>>(
+    message: &'a InvokeMessage<MockRuntime>,
+    key: &'static str,
+  ) -> Result<T, InvokeError> {
+    extract_with_acl(message, key, &None)
+  }
+
+  /// Runs [`CommandArg::from_command`] for the given key of the message with a resolved ACL.
+  pub(crate) fn extract_with_acl<'a, T: CommandArg<'a, MockRuntime>>(
+    message: &'a InvokeMessage<MockRuntime>,
+    key: &'static str,
+    acl: &'a Option<ResolvedCommand>,
+  ) -> Result<T, InvokeError> {
+    T::from_command(CommandItem {
+      name: "test",
+      key,
+      message,
+      acl,
+    })
+  }
+
+  struct Theme;
+
+  impl SettingKey for Theme {
+    const KEY: &'static str = "theme";
+  }
+
+  #[test]
+  fn default_from_state() {
+    let app = mock_app();
+    let store = SettingsStore::new();
+    store.set(Theme::KEY, "dark").unwrap();
+    app.manage(store);
+
+    let message = invoke_message(&app, "test", json!({ "theme": "light" }));
+    let theme = extract::<DefaultFromState<Theme, String>>(&message, "theme").unwrap();
+    assert_eq!(theme.into_inner(), "light");
+
+    let message = invoke_message(&app, "test", json!({}));
+    let theme = extract::<DefaultFromState<Theme, String>>(&message, "theme").unwrap();
+    assert_eq!(theme.into_inner(), "dark");
+  }
+
+  #[test]
+  fn default_from_state_missing() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({}));
+    assert!(extract::<DefaultFromState<Theme, String>>(&message, "theme").is_err());
+  }
+
+  #[derive(Debug, Default, PartialEq, Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  struct SearchOptions {
+    limit: Option<u32>,
+    case_sensitive: bool,
+  }
+
+  #[test]
+  fn or_default() {
+    let app = mock_app();
+
+    // the options are omitted from the payload
+    let message = invoke_message(&app, "test", json!({ "query": "tauri" }));
+    assert_eq!(
+      extract::<OrDefault<SearchOptions>>(&message, "options")
+        .unwrap()
+        .into_inner(),
+      SearchOptions::default()
+    );
+    assert_eq!(extract::<OrDefault<u32>>(&message, "limit").unwrap().0, 0);
+
+    let message = invoke_message(
+      &app,
+      "test",
+      json!({ "options": { "limit": 10, "caseSensitive": true }, "limit": null }),
+    );
+    assert_eq!(
+      extract::<OrDefault<SearchOptions>>(&message, "options")
+        .unwrap()
+        .into_inner(),
+      SearchOptions {
+        limit: Some(10),
+        case_sensitive: true
+      }
+    );
+    assert_eq!(extract::<OrDefault<u32>>(&message, "limit").unwrap().0, 0);
+
+    // invalid values are still rejected
+    let message = invoke_message(&app, "test", json!({ "options": { "limit": "ten" } }));
+    assert!(extract::<OrDefault<SearchOptions>>(&message, "options").is_err());
+
+    // bytes payloads have no keys
+    let message = invoke_message(&app, "test", vec![1, 2, 3]);
+    assert_eq!(
+      extract::<OrDefault<SearchOptions>>(&message, "options")
+        .unwrap()
+        .into_inner(),
+      SearchOptions::default()
+    );
+  }
+
+  struct UserId;
+
+  impl JsonPathKey for UserId {
+    const PATH: &'static str = "payload.user.id";
+  }
+
+  #[test]
+  fn json_path() {
+    let app = mock_app();
+    let message = invoke_message(
+      &app,
+      "test",
+      json!({ "payload": { "user": { "id": 42, "name": "tauri" } } }),
+    );
+    assert_eq!(
+      extract::<JsonPath<UserId, u64>>(&message, "userId")
+        .unwrap()
+        .into_inner(),
+      42
+    );
+
+    let err = extract::<JsonPath<UserId, String>>(&message, "userId").unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .starts_with("invalid args `userId` for command `test`: invalid type: integer `42`"));
+  }
+
+  #[test]
+  fn json_path_missing_segment() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "payload": { "account": {} } }));
+    assert_eq!(
+      extract::<JsonPath<UserId, u64>>(&message, "userId")
+        .unwrap_err()
+        .0,
+      "command test missing key `user` of path `payload.user.id`"
+    );
+
+    let message = invoke_message(&app, "test", json!({ "payload": { "user": [42] } }));
+    assert_eq!(
+      extract::<JsonPath<UserId, u64>>(&message, "userId")
+        .unwrap_err()
+        .0,
+      "command test expected an object at `payload.user` of path `payload.user.id`"
+    );
+
+    let message = invoke_message(&app, "test", json!(null));
+    assert_eq!(
+      extract::<JsonPath<UserId, u64>>(&message, "userId")
+        .unwrap_err()
+        .0,
+      "command test expected an object at `` of path `payload.user.id`"
+    );
+  }
+
+  struct TraceId;
+
+  impl HeaderKey for TraceId {
+    const NAME: &'static str = "X-Trace-Id";
+  }
+
+  fn message_with_headers(
+    app: &crate::App<MockRuntime>,
+    headers: &[(&'static str, &'static str)],
+  ) -> InvokeMessage<MockRuntime> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+      map.append(*name, value.parse().unwrap());
+    }
+    InvokeMessage::new(
+      invoke_message(app, "test", json!({})).window,
+      app.manager().state(),
+      "test".into(),
+      json!({}).into(),
+      map,
+    )
+  }
+
+  #[test]
+  fn header() {
+    let app = mock_app();
+    let message = message_with_headers(&app, &[("x-trace-id", "abc123")]);
+    let trace = extract::<Header<TraceId>>(&message, "trace").unwrap();
+    assert_eq!(trace.into_inner(), "abc123");
+    let trace = extract::<Header<TraceId, Option<String>>>(&message, "trace").unwrap();
+    assert_eq!(trace.0.as_deref(), Some("abc123"));
+  }
+
+  #[test]
+  fn header_missing() {
+    let app = mock_app();
+    let message = message_with_headers(&app, &[]);
+    let err = extract::<Header<TraceId>>(&message, "trace").unwrap_err();
+    assert_eq!(
+      err.0.as_str().unwrap(),
+      "command test missing header X-Trace-Id"
+    );
+
+    let trace = extract::<Header<TraceId, Option<String>>>(&message, "trace").unwrap();
+    assert_eq!(trace.0, None);
+  }
+
+  #[test]
+  fn headers() {
+    let app = mock_app();
+    let message = message_with_headers(
+      &app,
+      &[
+        ("x-trace-id", "abc123"),
+        ("Idempotency-Key", "k1"),
+        ("idempotency-key", "k2"),
+      ],
+    );
+    let headers = extract::<Headers>(&message, "headers").unwrap();
+    assert_eq!(
+      headers.0,
+      BTreeMap::from([
+        ("idempotency-key".to_string(), "k1".to_string()),
+        ("x-trace-id".to_string(), "abc123".to_string()),
+      ])
+    );
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  struct Permissions(u8);
+
+  impl FlagSet for Permissions {
+    fn empty() -> Self {
+      Self(0)
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+      match name {
+        "READ" => Some(Self(1)),
+        "WRITE" => Some(Self(2)),
+        "SHARE" => Some(Self(4)),
+        _ => None,
+      }
+    }
+
+    fn union(self, other: Self) -> Self {
+      Self(self.0 | other.0)
+    }
+  }
+
+  #[test]
+  fn flags() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "permissions": ["READ", "SHARE"] }));
+    let flags = extract::<Flags<Permissions>>(&message, "permissions").unwrap();
+    assert_eq!(flags.into_inner(), Permissions(5));
+
+    let message = invoke_message(&app, "test", json!({ "permissions": [] }));
+    let flags = extract::<Flags<Permissions>>(&message, "permissions").unwrap();
+    assert_eq!(flags.into_inner(), Permissions::empty());
+
+    let message = invoke_message(&app, "test", json!({ "permissions": ["READ", "DELETE"] }));
+    let err = extract::<Flags<Permissions>>(&message, "permissions").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("DELETE"));
+  }
+
+  #[derive(Debug, PartialEq, Eq)]
+  enum Column {
+    Name,
+    Created,
+  }
+
+  impl SortField for Column {
+    fn from_name(name: &str) -> Option<Self> {
+      match name {
+        "name" => Some(Self::Name),
+        "created" => Some(Self::Created),
+        _ => None,
+      }
+    }
+  }
+
+  #[test]
+  fn sort_spec() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "sort": "name:asc, created:desc" }));
+    let sort = extract::<SortSpec<Column>>(&message, "sort").unwrap();
+    assert_eq!(
+      sort.into_inner(),
+      vec![
+        (Column::Name, SortDirection::Ascending),
+        (Column::Created, SortDirection::Descending)
+      ]
+    );
+
+    let message = invoke_message(&app, "test", json!({ "sort": "created" }));
+    let sort = extract::<SortSpec<Column>>(&message, "sort").unwrap();
+    assert_eq!(sort.0, vec![(Column::Created, SortDirection::Ascending)]);
+
+    let message = invoke_message(&app, "test", json!({ "sort": "" }));
+    assert!(extract::<SortSpec<Column>>(&message, "sort")
+      .unwrap()
+      .0
+      .is_empty());
+  }
+
+  #[test]
+  fn sort_spec_unknown_field() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "sort": "name:asc,password:desc" }));
+    let err = extract::<SortSpec<Column>>(&message, "sort").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("`password`"));
+
+    let message = invoke_message(&app, "test", json!({ "sort": "name,,created" }));
+    assert!(extract::<SortSpec<Column>>(&message, "sort").is_err());
+  }
+
+  #[test]
+  fn sort_spec_malformed_direction() {
+    let app = mock_app();
+    for spec in [
+      "name:up",
+      "name:",
+      "name:asc:desc",
+      "created:DESC; DROP TABLE users",
+    ] {
+      let message = invoke_message(&app, "test", json!({ "sort": spec }));
+      let err = extract::<SortSpec<Column>>(&message, "sort").unwrap_err();
+      assert!(err.0.as_str().unwrap().contains("sort direction"), "{spec}");
+    }
+  }
+
+  #[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+  #[serde(rename_all = "camelCase")]
+  enum FileColumn {
+    Name,
+    Size,
+    ModifiedAt,
+  }
+
+  #[test]
+  fn unique_enum_set() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "columns": ["modifiedAt", "name"] }));
+    let columns = extract::<UniqueEnumSet<FileColumn>>(&message, "columns").unwrap();
+    assert_eq!(
+      columns.into_inner().into_iter().collect::<Vec<_>>(),
+      vec![FileColumn::Name, FileColumn::ModifiedAt]
+    );
+
+    let message = invoke_message(&app, "test", json!({ "columns": [] }));
+    let columns = extract::<UniqueEnumSet<FileColumn>>(&message, "columns").unwrap();
+    assert!(columns.0.is_empty());
+  }
+
+  #[test]
+  fn unique_enum_set_duplicate() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "columns": ["size", "name", "size"] }));
+    let err = extract::<UniqueEnumSet<FileColumn>>(&message, "columns").unwrap_err();
+    assert!(err
+      .0
+      .as_str()
+      .unwrap()
+      .contains("duplicated value \"size\""));
+  }
+
+  #[test]
+  fn unique_enum_set_unknown_variant() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "columns": ["name", "owner"] }));
+    let err = extract::<UniqueEnumSet<FileColumn>>(&message, "columns").unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .contains("unknown variant `owner`"));
+  }
+
+  #[test]
+  fn log_level() {
+    let app = mock_app();
+    for (input, expected) in [
+      ("trace", LogLevel::Trace),
+      ("TRACE", LogLevel::Trace),
+      ("debug", LogLevel::Debug),
+      ("Debug", LogLevel::Debug),
+      ("info", LogLevel::Info),
+      ("iNfO", LogLevel::Info),
+      ("warn", LogLevel::Warn),
+      ("WARN", LogLevel::Warn),
+      ("error", LogLevel::Error),
+      ("Error", LogLevel::Error),
+    ] {
+      let message = invoke_message(&app, "test", json!({ "level": input }));
+      assert_eq!(extract::<LogLevel>(&message, "level").unwrap(), expected);
+    }
+    assert!(LogLevel::Trace < LogLevel::Error);
+  }
+
+  #[test]
+  fn log_level_unknown() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "level": "verbose" }));
+    let err = extract::<LogLevel>(&message, "level").unwrap_err();
+    assert_eq!(
+      err.0.as_str().unwrap(),
+      "command test got unknown log level `verbose`, expected one of trace, debug, info, warn, error"
+    );
+  }
+
+  #[test]
+  fn webview_url() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({}));
+    let url: Url = "https://tauri.app/docs/guides?page=2#setup"
+      .parse()
+      .unwrap();
+    message.window().navigate(url.clone());
+    assert_eq!(extract::<WebviewUrl>(&message, "url").unwrap().0, url);
+  }
+
+  #[test]
+  fn window_label() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({}));
+    assert_eq!(extract::<WindowLabel>(&message, "label").unwrap().0, "main");
+
+    let settings = WindowBuilder::new(&app, "settings", Default::default())
+      .build()
+      .unwrap();
+    let message = InvokeMessage::new(
+      settings,
+      app.manager().state(),
+      "test".into(),
+      json!({}).into(),
+      HeaderMap::new(),
+    );
+    assert_eq!(
+      extract::<WindowLabel>(&message, "label").unwrap(),
+      WindowLabel("settings".into())
+    );
+  }
+
+  #[test]
+  fn invoke_context() {
+    use crate::{
+      command::{DecisionRecord, Origin},
+      ipc::{CallbackFn, InvokeResponse},
+      window::InvokeRequest,
+    };
+    use std::sync::{mpsc::channel, Arc, Mutex};
+
+    let decisions = Arc::new(Mutex::new(Vec::<DecisionRecord>::new()));
+    let decisions_ = decisions.clone();
+    let app = mock_builder()
+      .configure_runtime_authority(move |authority| {
+        authority
+          .set_decision_recorder(move |record| decisions_.lock().unwrap().push(record.clone()))
+      })
+      .invoke_handler(|invoke| {
+        let context = InvokeContext::from_command(CommandItem {
+          name: "context",
+          key: "context",
+          message: &invoke.message,
+          acl: &invoke.acl,
+        })
+        .unwrap();
+        crate::command::respond_blocking(invoke.resolver, context.origin);
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let invoke = || {
+      let (tx, rx) = channel();
+      window.clone().on_message(
+        InvokeRequest {
+          cmd: "context".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({}).into(),
+          headers: Default::default(),
+        },
+        Box::new(move |_window, _cmd, response, _callback, _error| {
+          tx.send(response).unwrap();
+        }),
+      );
+      match rx.recv().unwrap() {
+        InvokeResponse::Ok(body) => body.deserialize::<Origin>().unwrap(),
+        InvokeResponse::Err(e) => panic!("unexpected error {:?}", e.0),
+      }
+    };
+
+    let local = invoke();
+    assert!(matches!(local, Origin::LocalUrl { .. }));
+
+    window
+      .clone()
+      .navigate("https://remote.example:8443/page".parse().unwrap());
+    let remote = invoke();
+    assert_eq!(
+      remote,
+      Origin::RemoteUrl {
+        scheme: "https".into(),
+        domain: "remote.example".into(),
+        port: Some(8443),
+      }
+    );
+
+    let seen: Vec<Origin> = decisions
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|record| record.origin.clone())
+      .collect();
+    assert_eq!(seen, vec![local, remote]);
+  }
+
+  #[test]
+  fn allowed_windows() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({}));
+    let patterns = vec![
+      glob::Pattern::new("main").unwrap(),
+      glob::Pattern::new("editor-*").unwrap(),
+    ];
+    let acl = Some(ResolvedCommand {
+      windows: patterns.clone(),
+      scope: None,
+    });
+
+    let windows = extract_with_acl::<AllowedWindows>(&message, "windows", &acl).unwrap();
+    assert_eq!(windows.0, patterns);
+    assert!(windows.matches("main"));
+    assert!(windows.matches("editor-1"));
+    assert!(!windows.matches("settings"));
+
+    let windows = extract::<AllowedWindows>(&message, "windows").unwrap();
+    assert!(windows.0.is_empty());
+  }
+
+  #[test]
+  fn deadline() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({}));
+    let deadline = extract::<Deadline>(&message, "deadline").unwrap();
+    assert_eq!(deadline.remaining(), None);
+    assert!(!deadline.is_expired());
+
+    let deadline = |millis: u128| {
+      let mut headers = HeaderMap::new();
+      headers.insert("Tauri-Deadline", millis.to_string().parse().unwrap());
+      let message = InvokeMessage::new(
+        invoke_message(&app, "test", json!({})).window,
+        app.manager().state(),
+        "test".into(),
+        json!({}).into(),
+        headers,
+      );
+      extract::<Deadline>(&message, "deadline").unwrap()
+    };
+    let now = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap()
+      .as_millis();
+
+    let remaining = deadline(now + 60_000).remaining().unwrap();
+    assert!(remaining > std::time::Duration::from_secs(50));
+    assert!(deadline(now - 1_000).is_expired());
+  }
+
+  #[test]
+  fn country_code() {
+    let app = mock_app();
+    let code = |code: &str| {
+      let message = invoke_message(&app, "test", json!({ "country": code }));
+      extract::<CountryCode>(&message, "country")
+    };
+
+    assert_eq!(code("FR").unwrap().as_str(), "FR");
+    assert_eq!(code("us").unwrap().to_string(), "US");
+    assert_eq!(code("Gb").unwrap(), code("GB").unwrap());
+
+    for invalid in ["XX", "USA", "U", ""] {
+      let err = code(invalid).unwrap_err();
+      assert_eq!(
+        err.0.as_str().unwrap(),
+        format!("command test got an unknown ISO 3166-1 alpha-2 country code `{invalid}`")
+      );
+    }
+  }
+
+  #[test]
+  fn bounded_lat_lng() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "at": { "lat": 48.85, "lng": 2.35 } }));
+    // no bounds managed
+    assert!(extract::<BoundedLatLng>(&message, "at").is_err());
+
+    app.manage(GeoBounds {
+      south_west: LatLng {
+        lat: 41.3,
+        lng: -5.2,
+      },
+      north_east: LatLng {
+        lat: 51.1,
+        lng: 9.6,
+      },
+    });
+    let paris = extract::<BoundedLatLng>(&message, "at").unwrap();
+    assert_eq!(
+      paris.0,
+      LatLng {
+        lat: 48.85,
+        lng: 2.35
+      }
+    );
+
+    let message = invoke_message(&app, "test", json!({ "at": { "lat": 52.52, "lng": 13.4 } }));
+    let err = extract::<BoundedLatLng>(&message, "at").unwrap_err();
+    assert!(err
+      .0
+      .as_str()
+      .unwrap()
+      .contains("outside the allowed region"));
+
+    let message = invoke_message(&app, "test", json!({ "at": { "lat": 95.0, "lng": 2.0 } }));
+    assert!(extract::<BoundedLatLng>(&message, "at").is_err());
+  }
+
+  #[test]
+  fn geo_bounds_across_antimeridian() {
+    let pacific = GeoBounds {
+      south_west: LatLng {
+        lat: -30.0,
+        lng: 170.0,
+      },
+      north_east: LatLng {
+        lat: 30.0,
+        lng: -170.0,
+      },
+    };
+    assert!(pacific.contains(&LatLng {
+      lat: 0.0,
+      lng: 179.0
+    }));
+    assert!(pacific.contains(&LatLng {
+      lat: 0.0,
+      lng: -175.0
+    }));
+    assert!(!pacific.contains(&LatLng { lat: 0.0, lng: 0.0 }));
+  }
+
+  #[test]
+  fn glob_arg() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "pattern": "src/**/*.rs" }));
+    let pattern = extract::<GlobArg>(&message, "pattern").unwrap();
+    assert!(pattern.0.matches("src/command/args.rs"));
+
+    let message = invoke_message(&app, "test", json!({ "pattern": "src/[a-" }));
+    let err = extract::<GlobArg>(&message, "pattern").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("invalid glob pattern"));
+  }
+
+  #[test]
+  fn glob_arg_limits() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "pattern": "a".repeat(257) }));
+    let err = extract::<GlobArg>(&message, "pattern").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("longer than 256 bytes"));
+
+    app.manage(GlobLimits {
+      max_length: 64,
+      max_wildcards: 3,
+    });
+    let message = invoke_message(&app, "test", json!({ "pattern": "*a*b*c*" }));
+    let err = extract::<GlobArg>(&message, "pattern").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("more than 3 wildcards"));
+
+    let message = invoke_message(&app, "test", json!({ "pattern": "**/*.txt" }));
+    assert!(extract::<GlobArg>(&message, "pattern").is_ok());
+  }
+
+  #[cfg(feature = "mime-type")]
+  #[test]
+  fn mime_type() {
+    let app = mock_app();
+    let message = invoke_message(
+      &app,
+      "test",
+      json!({ "contentType": "image/png; charset=binary" }),
+    );
+    let mime = extract::<MimeType>(&message, "contentType").unwrap();
+    assert!(mime.is_image());
+    assert!(!mime.is_text());
+    assert_eq!(mime.essence(), "image/png");
+
+    let message = invoke_message(&app, "test", json!({ "contentType": "image" }));
+    let err = extract::<MimeType>(&message, "contentType").unwrap_err();
+    assert!(err
+      .0
+      .as_str()
+      .unwrap()
+      .contains("invalid MIME type `image`"));
+  }
+
+  #[cfg(feature = "mime-type")]
+  #[test]
+  fn mime_type_allow_list() {
+    let app = mock_app();
+    app.manage(
+      MimeAllowList::default()
+        .allow("test", ["image/*", "application/pdf"])
+        .allow("other", ["text/plain"]),
+    );
+
+    for allowed in ["image/webp", "application/pdf"] {
+      let message = invoke_message(&app, "test", json!({ "contentType": allowed }));
+      assert!(extract::<MimeType>(&message, "contentType").is_ok());
+    }
+
+    let message = invoke_message(&app, "test", json!({ "contentType": "text/html" }));
+    let err = extract::<MimeType>(&message, "contentType").unwrap_err();
+    assert!(err
+      .0
+      .as_str()
+      .unwrap()
+      .contains("does not accept the MIME type `text/html`"));
+  }
+
+  #[test]
+  fn message_key() {
+    let app = mock_app();
+    let key = |key: &str| {
+      let message = invoke_message(&app, "test", json!({ "key": key }));
+      extract::<MessageKey>(&message, "key").map_err(|e| e.0.as_str().unwrap().to_string())
+    };
+
+    assert!(key("greeting.hello")
+      .unwrap_err()
+      .contains("no MessageCatalog is managed"));
+
+    app.manage(MessageCatalog::new([
+      "greeting.hello",
+      "greeting.goodbye",
+      "menu.file.open",
+    ]));
+    assert_eq!(
+      key("greeting.hello").unwrap(),
+      MessageKey("greeting.hello".into())
+    );
+    assert_eq!(
+      key("checkout.total").unwrap_err(),
+      "command test got unknown message key `checkout.total`"
+    );
+  }
+
+  #[test]
+  fn message_key_near_matches() {
+    let app = mock_app();
+    app.manage(MessageCatalog::new([
+      "greeting.hello",
+      "greeting.help",
+      "menu.file.open",
+    ]));
+    let message = invoke_message(&app, "test", json!({ "key": "greeting.helo" }));
+    let err = extract::<MessageKey>(&message, "key").unwrap_err();
+    assert_eq!(
+      err.0.as_str().unwrap(),
+      "command test got unknown message key `greeting.helo`, did you mean `greeting.hello`, `greeting.help`?"
+    );
+
+    app.state::<MessageCatalog>().replace(["greeting.helo"]);
+    assert!(extract::<MessageKey>(&message, "key").is_ok());
+  }
+
+  #[test]
+  fn byte_size() {
+    let app = mock_app();
+    let size = |value: JsonValue| {
+      let message = invoke_message(&app, "test", json!({ "size": value }));
+      extract::<ByteSize>(&message, "size").map(|size| size.0)
+    };
+
+    assert_eq!(size(json!("10MB")).unwrap(), 10_000_000);
+    assert_eq!(size(json!("1GiB")).unwrap(), 1_073_741_824);
+    assert_eq!(size(json!("1.5 kib")).unwrap(), 1536);
+    assert_eq!(size(json!(4096)).unwrap(), 4096);
+    assert_eq!(size(json!("512")).unwrap(), 512);
+    assert!(size(json!("ten megabytes")).is_err());
+    assert!(size(json!("10XB")).is_err());
+    assert!(size(json!(-1)).is_err());
+  }
+
+  #[test]
+  fn temperature() {
+    let app = mock_app();
+    let temperature = |value: JsonValue| {
+      let message = invoke_message(&app, "test", json!({ "temperature": value }));
+      extract::<Temperature>(&message, "temperature")
+    };
+
+    let celsius = temperature(json!("21C")).unwrap();
+    assert_eq!(celsius.as_celsius(), 21.);
+    assert!((celsius.as_fahrenheit() - 69.8).abs() < 1e-9);
+    assert_eq!(temperature(json!("-4.5 °c")).unwrap().as_celsius(), -4.5);
+    assert_eq!(
+      temperature(json!({ "value": 21, "unit": "celsius" })).unwrap(),
+      celsius
+    );
+    assert!((temperature(json!("300K")).unwrap().as_celsius() - 26.85).abs() < 1e-9);
+  }
+
+  #[test]
+  fn temperature_fahrenheit() {
+    let app = mock_app();
+    let temperature = |value: JsonValue| {
+      let message = invoke_message(&app, "test", json!({ "temperature": value }));
+      extract::<Temperature>(&message, "temperature").unwrap()
+    };
+
+    let fahrenheit = temperature(json!("212F"));
+    assert_eq!(fahrenheit.as_celsius(), 100.);
+    assert_eq!(fahrenheit.as_fahrenheit(), 212.);
+    assert_eq!(
+      temperature(json!({ "value": 32, "unit": "°F" })).as_celsius(),
+      0.
+    );
+  }
+
+  #[test]
+  fn temperature_invalid() {
+    let app = mock_app();
+    let temperature = |value: JsonValue| {
+      let message = invoke_message(&app, "test", json!({ "temperature": value }));
+      extract::<Temperature>(&message, "temperature")
+    };
+
+    for value in [
+      json!("21X"),
+      json!({ "value": 21, "unit": "rankine" }),
+      json!("21"),
+      json!("warm"),
+      json!("C"),
+      json!(21),
+    ] {
+      assert!(temperature(value.clone()).is_err(), "{value} was accepted");
+    }
+  }
+
+  #[derive(Debug, PartialEq, Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  enum Shape {
+    Circle,
+    Square {
+      size: u32,
+    },
+    #[serde(skip)]
+    Unknown,
+  }
+
+  impl UnknownVariant for Shape {
+    fn unknown() -> Self {
+      Self::Unknown
+    }
+  }
+
+  #[test]
+  fn with_unknown() {
+    let app = mock_app();
+    let shape = |value: JsonValue| {
+      let message = invoke_message(&app, "test", json!({ "shape": value }));
+      extract::<WithUnknown<Shape>>(&message, "shape").map(WithUnknown::into_inner)
+    };
+
+    assert_eq!(shape(json!("circle")).unwrap(), Shape::Circle);
+    assert_eq!(
+      shape(json!({ "square": { "size": 2 } })).unwrap(),
+      Shape::Square { size: 2 }
+    );
+    assert_eq!(shape(json!("hexagon")).unwrap(), Shape::Unknown);
+    assert_eq!(
+      shape(json!({ "star": { "points": 5 } })).unwrap(),
+      Shape::Unknown
+    );
+    // the skipped fallback variant is not accepted by name
+    assert_eq!(shape(json!("unknown")).unwrap(), Shape::Unknown);
+    // known variants with an invalid content are still rejected
+    assert!(shape(json!({ "square": { "size": "big" } })).is_err());
+    assert!(shape(json!(3)).is_err());
+  }
+
+  #[derive(Debug, PartialEq, Eq, Deserialize)]
+  #[serde(rename_all = "snake_case")]
+  enum Op {
+    Create,
+    Update,
+  }
+
+  #[test]
+  fn command_suffix_op() {
+    let app = mock_app();
+    let op = |command: &str| {
+      let message = invoke_message(&app, command, json!({}));
+      extract::<CommandSuffixOp<Op>>(&message, "op").map(CommandSuffixOp::into_inner)
+    };
+
+    assert_eq!(op("entity_create").unwrap(), Op::Create);
+    assert_eq!(op("plugin:store|entity_update").unwrap(), Op::Update);
+    assert!(op("entity_delete").is_err());
+    assert!(op("entity").is_err());
+  }
+
+  #[test]
+  fn env_args() {
+    let app = mock_app();
+    let env_args = |value: JsonValue| {
+      let message = invoke_message(&app, "test", json!({ "args": value }));
+      extract::<EnvArgs>(&message, "args").map(|args| args.0)
+    };
+
+    assert_eq!(
+      env_args(json!(["mode=release", "query=a=b", "empty="])).unwrap(),
+      [("empty", ""), ("mode", "release"), ("query", "a=b")]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    );
+
+    let err = env_args(json!(["mode=release", "verbose"])).unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("`verbose`"));
+
+    assert_eq!(
+      env_args(json!(["mode=debug", "mode=release"])).unwrap()["mode"],
+      "release"
+    );
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  enum Door {
+    Open,
+    Closed,
+    Locked,
+  }
+
+  impl StateMachine for Door {
+    fn can_transition(&self, target: &Self) -> bool {
+      matches!(
+        (self, target),
+        (Door::Open, Door::Closed)
+          | (Door::Closed, Door::Open | Door::Locked)
+          | (Door::Locked, Door::Closed)
+      )
+    }
+  }
+
+  #[test]
+  fn transition() {
+    let app = mock_app();
+    let transition = |target: &str| {
+      let message = invoke_message(&app, "test", json!({ "door": target }));
+      extract::<Transition<Door>>(&message, "door")
+    };
+
+    assert!(transition("open").is_err());
+
+    app.manage(std::sync::Mutex::new(Door::Closed));
+    assert_eq!(
+      transition("locked").unwrap(),
+      Transition {
+        from: Door::Closed,
+        to: Door::Locked
+      }
+    );
+
+    *app.state::<std::sync::Mutex<Door>>().lock().unwrap() = Door::Open;
+    let err = transition("locked").unwrap_err();
+    assert!(err
+      .0
+      .as_str()
+      .unwrap()
+      .contains("cannot transition from Open to Locked"));
+  }
+
+  #[test]
+  fn matrix() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "m": [1, 2, 3, 4, 5, 6.5] }));
+
+    let matrix = extract::<Matrix<2, 3>>(&message, "m").unwrap();
+    assert_eq!(matrix.get(1, 2), 6.5);
+    assert_eq!(matrix.row(0), &[1.0, 2.0, 3.0]);
+    assert_eq!(matrix.rows().count(), 2);
+
+    let err = extract::<Matrix<3, 3>>(&message, "m").unwrap_err();
+    assert!(err
+      .0
+      .as_str()
+      .unwrap()
+      .contains("expected 9 elements for the 3x3 matrix m, got 6"));
+  }
+
+  fn string_entry(value: &str) -> Value {
+    Value::Map(
+      [("value".to_string(), Value::from(value.to_string()))]
+        .into_iter()
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn raw_body_with_command_scope() {
+    use crate::ipc::Request;
+
+    fn upload(request: Request<'_>, scope: CommandScope<'_, StringScope>) -> Result<usize, String> {
+      let name = request
+        .headers()
+        .get("file-name")
+        .and_then(|name| name.to_str().ok())
+        .ok_or("missing file name")?;
+      if !scope.permits(name) {
+        return Err(format!("{name} is not allowed"));
+      }
+      let bytes = request.raw_body().ok_or("expected a bytes payload")?;
+      Ok(bytes.len())
+    }
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority
+          .install_plugin(PluginAcl {
+            command_scope: [(
+              100,
+              ResolvedScope {
+                allow: vec![string_entry("avatar.png")],
+                deny: Vec::new(),
+              },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+          })
+          .unwrap();
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let acl = Some(ResolvedCommand {
+      windows: Vec::new(),
+      scope: Some(100),
+    });
+    let invoke = |name: &str, payload: InvokeBody| {
+      let mut headers = HeaderMap::new();
+      headers.insert("file-name", name.parse().unwrap());
+      let message = InvokeMessage::new(
+        invoke_message(&app, "upload", json!({})).window,
+        app.manager().state(),
+        "upload".into(),
+        payload,
+        headers,
+      );
+      upload(
+        extract_with_acl(&message, "request", &acl).unwrap(),
+        extract_with_acl(&message, "scope", &acl).unwrap(),
+      )
+    };
+
+    assert_eq!(invoke("avatar.png", vec![1, 2, 3].into()), Ok(3));
+    assert!(invoke("passwd", vec![1, 2, 3].into()).is_err());
+    assert!(invoke("avatar.png", json!({ "bytes": [1, 2, 3] }).into()).is_err());
+  }
+
+  #[test]
+  fn optional_command_scope() {
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority
+          .install_plugin(PluginAcl {
+            command_scope: [(
+              100,
+              ResolvedScope {
+                allow: vec![string_entry("avatar.png")],
+                deny: Vec::new(),
+              },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+          })
+          .unwrap();
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let message = invoke_message(&app, "upload", json!({}));
+
+    let scoped = Some(ResolvedCommand {
+      windows: Vec::new(),
+      scope: Some(100),
+    });
+    let scope =
+      extract_with_acl::<OptionalCommandScope<'_, StringScope>>(&message, "scope", &scoped)
+        .unwrap()
+        .into_inner()
+        .expect("the command has a scope");
+    assert!(scope.permits("avatar.png"));
+    assert!(!scope.permits("passwd"));
+
+    let unscoped = Some(ResolvedCommand {
+      windows: Vec::new(),
+      scope: None,
+    });
+    for acl in [unscoped, None] {
+      assert!(
+        extract_with_acl::<OptionalCommandScope<'_, StringScope>>(&message, "scope", &acl)
+          .unwrap()
+          .is_none()
+      );
+    }
+    assert!(extract_with_acl::<CommandScope<'_, StringScope>>(&message, "scope", &None).is_err());
+  }
+
+  #[test]
+  fn optional_global_scope() {
+    use crate::command::RuntimeAuthorityBuilder;
+
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({}));
+    assert!(
+      extract::<OptionalGlobalScope<'_, StringScope>>(&message, "scope")
+        .unwrap()
+        .is_none()
+    );
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        *authority = RuntimeAuthorityBuilder::new()
+          .with_global_scope(ResolvedScope {
+            allow: vec![string_entry("avatar.png")],
+            deny: Vec::new(),
+          })
+          .build()
+          .unwrap();
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let message = invoke_message(&app, "test", json!({}));
+    let scope = extract::<OptionalGlobalScope<'_, StringScope>>(&message, "scope")
+      .unwrap()
+      .into_inner()
+      .expect("the global scope has entries");
+    assert_eq!(scope.allows().len(), 1);
+    assert!(scope.denies().is_empty());
+  }
+
+  #[test]
+  fn raw_body() {
+    use crate::{
+      ipc::{CallbackFn, Response},
+      test::get_ipc_response,
+      window::InvokeRequest,
+    };
+
+    let app = mock_builder()
+      .invoke_handler(|invoke| {
+        let body = RawBody::from_command(CommandItem {
+          name: "upload",
+          key: "body",
+          message: &invoke.message,
+          acl: &invoke.acl,
+        });
+        invoke
+          .resolver
+          .respond(body.map(|body| Response::new(body.into_inner())));
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let upload = |body: InvokeBody| {
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: "upload".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body,
+          headers: Default::default(),
+        },
+      )
+    };
+
+    let bytes = vec![0, 1, 2, 254, 255];
+    match upload(bytes.clone().into()).unwrap() {
+      InvokeBody::Raw(response) => assert_eq!(response, bytes),
+      body => panic!("expected a bytes response, got {body:?}"),
+    }
+
+    let err = upload(json!({ "body": [1, 2, 3] }).into()).unwrap_err();
+    assert!(err.as_str().unwrap().contains("expected a bytes payload"));
+  }
+
+  #[test]
+  fn scoped_string() {
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority
+          .install_plugin(PluginAcl {
+            command_scope: [(
+              100,
+              ResolvedScope {
+                allow: vec![string_entry("photo"), string_entry("video")],
+                deny: vec![string_entry("video")],
+              },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+          })
+          .unwrap();
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let acl = Some(ResolvedCommand {
+      windows: Vec::new(),
+      scope: Some(100),
+    });
+    let resource = |value: &str| {
+      let message = invoke_message(&app, "test", json!({ "resource": value }));
+      extract_with_acl::<ScopedString>(&message, "resource", &acl).map(|s| s.0)
+    };
+
+    assert_eq!(resource("photo").unwrap(), "photo");
+    // denied
+    assert!(resource("video").is_err());
+    // absent from the allow-list
+    let err = resource("audio").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("`audio`"));
+
+    // commands without a scope allow nothing
+    let message = invoke_message(&app, "test", json!({ "resource": "photo" }));
+    assert!(extract::<ScopedString>(&message, "resource").is_err());
+  }
+
+  #[test]
+  fn scope_accesses_recorded() {
+    use crate::{
+      command::{ScopeAccess, ScopeAccessKind},
+      test::take_scope_accesses,
+    };
+
+    let app = |record: bool| {
+      let scope = ResolvedScope {
+        allow: vec![string_entry("photo")],
+        deny: vec![string_entry("video")],
+      };
+      mock_builder()
+        .configure_runtime_authority(move |authority| {
+          if record {
+            authority.record_scope_accesses(true);
+          }
+          authority
+            .install_plugin(PluginAcl {
+              command_scope: [(100, scope)].into_iter().collect(),
+              ..Default::default()
+            })
+            .unwrap();
+        })
+        .build(mock_context(noop_assets()))
+        .unwrap()
+    };
+    let acl = Some(ResolvedCommand {
+      windows: Vec::new(),
+      scope: Some(100),
+    });
+
+    // not recorded by default
+    let unrecorded = app(false);
+    let message = invoke_message(&unrecorded, "test", json!({ "resource": "photo" }));
+    extract_with_acl::<ScopedString>(&message, "resource", &acl).unwrap();
+    assert!(take_scope_accesses(&unrecorded).is_empty());
+
+    let app = app(true);
+    let message = invoke_message(&app, "test", json!({ "resource": "photo" }));
+    extract_with_acl::<ScopedString>(&message, "resource", &acl).unwrap();
+
+    assert_eq!(
+      take_scope_accesses(&app),
+      vec![
+        ScopeAccess {
+          scope: 100,
+          kind: ScopeAccessKind::Denies,
+          values: vec![string_entry("video")],
+        },
+        ScopeAccess {
+          scope: 100,
+          kind: ScopeAccessKind::Allows,
+          values: vec![string_entry("photo")],
+        },
+      ]
+    );
+    assert!(take_scope_accesses(&app).is_empty());
+  }
+
+  #[test]
+  fn extract_tuple() {
+    let app = mock_app();
+    let store = SettingsStore::new();
+    store.set(Theme::KEY, "dark").unwrap();
+    app.manage(store);
+    let message = invoke_message(&app, "test", json!({}));
+
+    let Extract((window, handle, settings)) = extract::<
+      Extract<(
+        crate::Window<MockRuntime>,
+        crate::AppHandle<MockRuntime>,
+        crate::State<'_, SettingsStore>,
+      )>,
+    >(&message, "ctx")
+    .unwrap();
+    assert_eq!(window.label(), "main");
+    assert!(handle.get_window("main").is_some());
+    assert_eq!(settings.get(Theme::KEY), Some(json!("dark")));
+
+    // the first error short-circuits
+    let err =
+      extract::<Extract<(crate::Window<MockRuntime>, GlobArg, DroppedFiles)>>(&message, "ctx")
+        .unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .contains("missing required key ctx"));
+  }
+
+  #[cfg(feature = "unicode-normalization")]
+  #[test]
+  fn normalized_str() {
+    let app = mock_app();
+    let normalized = |value: &str| {
+      let message = invoke_message(&app, "test", json!({ "name": value }));
+      extract::<NormalizedStr>(&message, "name").map(|s| s.0)
+    };
+
+    // "e" followed by a combining acute accent
+    assert_eq!(normalized("caf\u{0065}\u{0301}").unwrap(), "caf\u{00e9}");
+    // latin "p" and "a" with a cyrillic "а" (U+0430) are accepted by default
+    assert!(normalized("p\u{0430}ypal").is_ok());
+
+    app.manage(NormalizationOptions {
+      reject_mixed_script: true,
+    });
+    let err = normalized("p\u{0430}ypal").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("mixes several scripts"));
+    assert_eq!(normalized("paypal").unwrap(), "paypal");
+    assert!(normalized("\u{043f}\u{0440}\u{0438}\u{0432}\u{0435}\u{0442}").is_ok());
+  }
+
+  #[cfg(feature = "regex")]
+  #[test]
+  fn regex_arg() {
+    let app = mock_app();
+    let regex = |pattern: &str| {
+      let message = invoke_message(&app, "test", json!({ "pattern": pattern }));
+      extract::<RegexArg>(&message, "pattern").map(|r| r.0)
+    };
+
+    assert!(regex(r"^\d{3}-\d{4}$").unwrap().is_match("555-1234"));
+
+    let err = regex("(unclosed").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("invalid regex"));
+
+    app.manage(RegexLimits { size_limit: 1024 });
+    let err = regex(r"\w{1000}").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("size limit"));
+  }
+
+  #[cfg(feature = "time")]
+  #[test]
+  fn time_or_now() {
+    use time::macros::datetime;
+
+    let app = mock_app();
+    app.manage(Clock::new(|| datetime!(2024-01-02 03:04:05 UTC)));
+    let at = |value: &str| {
+      let message = invoke_message(&app, "test", json!({ "at": value }));
+      extract::<TimeOrNow>(&message, "at").map(|t| t.0)
+    };
+
+    assert_eq!(at("now").unwrap(), datetime!(2024-01-02 03:04:05 UTC));
+    assert_eq!(
+      at("2023-06-07T08:09:10+02:00").unwrap(),
+      datetime!(2023-06-07 08:09:10 +2)
+    );
+
+    let err = at("tomorrow").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("RFC 3339"));
+  }
+
+  #[test]
+  fn notification_permission() {
+    let app = mock_app();
+    // no JSON key is needed
+    let message = invoke_message(&app, "notify", json!({}));
+    assert_eq!(
+      extract::<NotificationPermission>(&message, "permission").unwrap(),
+      NotificationPermission::Default
+    );
+
+    app.manage(NotificationPermissionSource::new(|| {
+      NotificationPermission::Denied
+    }));
+    assert_eq!(
+      extract::<NotificationPermission>(&message, "permission").unwrap(),
+      NotificationPermission::Denied
+    );
+  }
+
+  #[cfg(feature = "time")]
+  #[test]
+  fn birthdate() {
+    use time::macros::{date, datetime};
+
+    let app = mock_app();
+    app.manage(Clock::new(|| datetime!(2024-06-15 12:00 UTC)));
+    let birthdate = |value: &str| {
+      let message = invoke_message(&app, "test", json!({ "birthdate": value }));
+      extract::<Birthdate>(&message, "birthdate").map(|b| b.0)
+    };
+
+    assert_eq!(birthdate("2006-06-15").unwrap(), date!(2006 - 06 - 15));
+
+    let err = birthdate("2006-06-16").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("minimum age of 18"));
+
+    let err = birthdate("2024-06-16").unwrap_err();
+    assert!(err.0.as_str().unwrap().contains("in the future"));
+
+    app.manage(MinimumAge(13));
+    assert!(birthdate("2010-01-01").is_ok());
+  }
+
+  #[test]
+  fn dropped_files() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({}));
+    assert!(extract::<DroppedFiles>(&message, "files")
+      .unwrap()
+      .0
+      .is_empty());
+
+    let paths = vec![PathBuf::from("/tmp/a.txt"), PathBuf::from("/tmp/b.png")];
+    app
+      .manager()
+      .window
+      .set_dropped_files("main", paths.clone());
+    assert_eq!(extract::<DroppedFiles>(&message, "files").unwrap().0, paths);
+    // the drop is handed to a single command
+    assert!(extract::<DroppedFiles>(&message, "files")
+      .unwrap()
+      .0
+      .is_empty());
+  }
+
+  #[cfg(feature = "grant")]
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  struct Claims {
+    document: u64,
+  }
+
+  #[cfg(feature = "grant")]
+  fn grant_result(
+    app: &crate::App<MockRuntime>,
+    grant: &str,
+  ) -> Result<Grant<Claims>, crate::ipc::InvokeError> {
+    let message = invoke_message(app, "test", json!({ "grant": grant }));
+    extract::<Grant<Claims>>(&message, "grant")
+  }
+
+  #[cfg(feature = "grant")]
+  fn in_one_hour() -> std::time::SystemTime {
+    std::time::SystemTime::now() + std::time::Duration::from_secs(60 * 60)
+  }
+
+  #[cfg(feature = "grant")]
+  #[test]
+  fn grant() {
+    let app = mock_app();
+    let key = GrantKey::new("secret", 1);
+    app.manage(key.clone());
+
+    let grant = key.issue(&Claims { document: 7 }, in_one_hour()).unwrap();
+    assert_eq!(
+      grant_result(&app, &grant).unwrap().into_inner(),
+      Claims { document: 7 }
+    );
+
+    // read from the header when the argument is missing
+    let mut headers = HeaderMap::new();
+    headers.insert(Grant::<Claims>::HEADER, grant.parse().unwrap());
+    let message = InvokeMessage::new(
+      invoke_message(&app, "test", json!({})).window,
+      app.manager().state(),
+      "test".into(),
+      json!({}).into(),
+      headers,
+    );
+    assert_eq!(
+      extract::<Grant<Claims>>(&message, "grant").unwrap().0,
+      Claims { document: 7 }
+    );
+
+    let message = invoke_message(&app, "test", json!({}));
+    assert_eq!(
+      extract::<Grant<Claims>>(&message, "grant").unwrap_err().0,
+      json!({ "type": "missing", "data": null })
+    );
+  }
+
+  #[cfg(feature = "grant")]
+  #[test]
+  fn grant_expired() {
+    let app = mock_app();
+    let key = GrantKey::new("secret", 1);
+    app.manage(key.clone());
+
+    let expired = std::time::SystemTime::now() - std::time::Duration::from_secs(1);
+    let grant = key.issue(&Claims { document: 7 }, expired).unwrap();
+    assert_eq!(
+      grant_result(&app, &grant).unwrap_err().0,
+      json!({ "type": "expired", "data": null })
+    );
+  }
+
+  #[cfg(feature = "grant")]
+  #[test]
+  fn grant_invalid_signature() {
+    let app = mock_app();
+    app.manage(GrantKey::new("secret", 1));
+
+    let forged = GrantKey::new("other secret", 1)
+      .issue(&Claims { document: 7 }, in_one_hour())
+      .unwrap();
+    assert_eq!(
+      grant_result(&app, &forged).unwrap_err().0,
+      json!({ "type": "invalidSignature", "data": null })
+    );
+
+    for malformed in ["", "no signature", "a.not base64!"] {
+      assert_eq!(
+        grant_result(&app, malformed).unwrap_err().0,
+        json!({ "type": "malformed", "data": null })
+      );
+    }
+  }
+
+  #[cfg(feature = "grant")]
+  #[test]
+  fn grant_wrong_version() {
+    let app = mock_app();
+    app.manage(GrantKey::new("secret", 2));
+
+    let grant = GrantKey::new("secret", 1)
+      .issue(&Claims { document: 7 }, in_one_hour())
+      .unwrap();
+    assert_eq!(
+      grant_result(&app, &grant).unwrap_err().0,
+      json!({ "type": "wrongVersion", "data": { "expected": 2, "found": 1 } })
+    );
+  }
+
+  #[cfg(feature = "cursor")]
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  struct Position {
+    id: u64,
+    created: String,
+  }
+
+  #[cfg(feature = "cursor")]
+  #[test]
+  fn cursor() {
+    let app = mock_app();
+    let position = Position {
+      id: 42,
+      created: "2023-11-01".into(),
+    };
+    let cursor = Cursor::encode(&position, None).unwrap();
+    let message = invoke_message(&app, "test", json!({ "cursor": cursor }));
+    assert_eq!(
+      extract::<Cursor<Position>>(&message, "cursor")
+        .unwrap()
+        .into_inner(),
+      position
+    );
+  }
+
+  #[cfg(feature = "cursor")]
+  #[test]
+  fn cursor_malformed() {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    let app = mock_app();
+    for cursor in [
+      "not base64!".to_string(),
+      URL_SAFE_NO_PAD.encode("not json"),
+      URL_SAFE_NO_PAD.encode(r#"{"id":"42"}"#),
+    ] {
+      let message = invoke_message(&app, "test", json!({ "cursor": cursor }));
+      let err = extract::<Cursor<Position>>(&message, "cursor").unwrap_err();
+      assert_eq!(err.0, json!("command test got a malformed cursor"));
+    }
+  }
+
+  #[cfg(feature = "cursor")]
+  #[test]
+  fn cursor_tampered() {
+    let app = mock_app();
+    let key = CursorKey::new("secret");
+    app.manage(key.clone());
+    let position = Position {
+      id: 42,
+      created: "2023-11-01".into(),
+    };
+    let extract_cursor = |cursor: String| {
+      let message = invoke_message(&app, "test", json!({ "cursor": cursor }));
+      extract::<Cursor<Position>>(&message, "cursor").map(Cursor::into_inner)
+    };
+
+    let signed = Cursor::encode(&position, Some(&key)).unwrap();
+    assert_eq!(extract_cursor(signed.clone()).unwrap(), position);
+
+    let forged = Cursor::encode(
+      &Position {
+        id: 1,
+        created: "2023-11-01".into(),
+      },
+      None,
+    )
+    .unwrap();
+    let (_, signature) = signed.split_once('.').unwrap();
+    for cursor in [
+      format!("{forged}.{signature}"),
+      forged,
+      Cursor::encode(&position, Some(&CursorKey::new("other"))).unwrap(),
+    ] {
+      let err = extract_cursor(cursor).unwrap_err();
+      assert_eq!(err.0, json!("command test got a tampered cursor"));
+    }
+  }
+
+  #[cfg(feature = "cursor")]
+  #[test]
+  fn cursor_hmac() {
+    // RFC 4231 test case 2
+    let signature = CursorKey::new("Jefe").sign(b"what do ya want for nothing?");
+    assert_eq!(
+      signature
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>(),
+      "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+  }
+}
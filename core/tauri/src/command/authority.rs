@@ -2,10 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-use std::collections::BTreeMap;
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+#[cfg(feature = "metrics")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
-use serde::de::DeserializeOwned;
+use futures_util::Future;
+use http::{header::CONTENT_TYPE, HeaderMap};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use state::TypeMap;
 
 use tauri_utils::acl::{
@@ -13,416 +23,5216 @@ use tauri_utils::acl::{
   ExecutionContext,
 };
 
-use crate::{ipc::InvokeError, Runtime};
+use crate::{
+  ipc::{InvokeBody, InvokeError},
+  Runtime,
+};
 
-use super::{CommandArg, CommandItem};
+use super::{
+  cache::{IdempotencyCache, ResponseCache},
+  trie::CommandTrie,
+  CommandArg, CommandItem,
+};
 
 /// The runtime authority used to authorize IPC execution based on the Access Control List.
 pub struct RuntimeAuthority {
   allowed_commands: BTreeMap<CommandKey, ResolvedCommand>,
   denied_commands: BTreeMap<CommandKey, ResolvedCommand>,
+  allowed_index: CommandTrie<CommandKey>,
+  denied_index: CommandTrie<CommandKey>,
   scope_manager: ScopeManager,
+  step_up_commands: BTreeSet<String>,
+  step_up_freshness: Duration,
+  focus_commands: BTreeSet<String>,
+  companions: BTreeMap<String, BTreeSet<String>>,
+  pub(crate) response_cache: ResponseCache,
+  pub(crate) idempotency_cache: IdempotencyCache,
+  max_payload_depth: usize,
+  max_response_bytes: BTreeMap<String, usize>,
+  query_fallback_commands: BTreeSet<String>,
+  key_normalization_commands: BTreeSet<String>,
+  strict_field_commands: BTreeSet<String>,
+  features: FeatureRegistry,
+  required_features: BTreeMap<String, String>,
+  deny_reasons: BTreeMap<CommandKey, String>,
+  toggles: Option<CachedToggles>,
+  origin_matcher: Option<Box<dyn OriginMatcher>>,
+  rate_limiter: Option<Box<dyn RateLimiter>>,
+  decision_recorder: Option<DecisionRecorder>,
+  #[cfg(feature = "metrics")]
+  decision_counts: Mutex<BTreeMap<String, DecisionCounts>>,
+  payload_transforms: Vec<PayloadTransform>,
+  payload_codecs: BTreeMap<String, Box<dyn PayloadCodec>>,
+  interceptors: Vec<Box<dyn CommandInterceptor>>,
+  installed_plugins: BTreeMap<PluginId, InstalledPlugin>,
+  next_plugin_id: u64,
+  command_schemas: BTreeMap<String, CommandSchema>,
+  #[cfg(any(test, feature = "test"))]
+  test_handlers: BTreeMap<String, TestHandler>,
+  #[cfg(any(test, feature = "test"))]
+  record_argument_accesses: bool,
+  #[cfg(any(test, feature = "test"))]
+  argument_accesses: Mutex<Vec<ArgumentAccess>>,
 }
 
-/// The origin trying to access the IPC.
-pub enum Origin {
-  /// Local app origin.
-  Local,
-  /// Remote origin.
-  Remote {
-    /// Remote origin domain.
-    domain: String,
-  },
+/// A transform added with [`RuntimeAuthority::add_payload_transform`].
+type PayloadTransform = Box<dyn Fn(InvokeBody) -> Result<InvokeBody, InvokeError> + Send + Sync>;
+
+/// A command handler registered with [`RuntimeAuthority::register_test_handler`].
+#[cfg(any(test, feature = "test"))]
+type TestHandler = Box<dyn Fn(InvokeBody) -> Result<InvokeBody, InvokeError> + Send + Sync>;
+
+/// The schema of a command, used to describe the IPC to clients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSchema {
+  /// The command name.
+  pub name: String,
+  /// The names of the command arguments.
+  pub args: Vec<String>,
 }
 
-impl Origin {
-  fn matches(&self, context: &ExecutionContext) -> bool {
-    match (self, context) {
-      (Self::Local, ExecutionContext::Local) => true,
-      (
-        Self::Remote { domain },
-        ExecutionContext::Remote {
-          domain: domain_pattern,
-        },
-      ) => domain_pattern.matches(domain),
-      _ => false,
-    }
-  }
+/// Default duration a step-up authentication is considered fresh.
+const DEFAULT_STEP_UP_FRESHNESS: Duration = Duration::from_secs(5 * 60);
+
+/// Default maximum nesting depth of a command argument.
+const DEFAULT_MAX_PAYLOAD_DEPTH: usize = 128;
+
+/// Errors that can happen when resolving access to a command.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum AccessError {
+  /// The command is not allowed for the given window and origin.
+  #[error("command not allowed")]
+  NotAllowed,
+  /// The command requires a step-up authentication within the configured freshness window.
+  #[error("command requires a recent step-up authentication")]
+  StepUpRequired,
+  /// The command requires a feature that is not enabled in the [`FeatureRegistry`].
+  #[error("command requires the `{0}` feature which is not enabled")]
+  FeatureUnavailable(String),
+  /// The command is turned off in the [`ToggleStore`].
+  #[error("command is toggled off")]
+  ToggledOff,
+  /// The command is explicitly denied for the given origin.
+  #[error("command denied")]
+  Denied,
+  /// The command is explicitly denied by an entry with a reason set with [`RuntimeAuthority::set_deny_reason`].
+  #[error("{0}")]
+  DeniedWithReason(String),
+  /// The command is allowed, but not for the given origin.
+  #[error("command not allowed for this origin")]
+  OriginMismatch,
+  /// The command is allowed for the given origin, but not for the given window.
+  #[error("command not allowed for this window")]
+  WindowNotAllowed,
+  /// The command is not in the access control list.
+  #[error("command not found in the access control list")]
+  CommandNotFound,
+  /// The command requires the invoking window to be focused.
+  #[error("command requires a focused window")]
+  WindowNotFocused,
+  /// The command exceeded the rate allowed by the [`RateLimiter`].
+  #[error("too many requests")]
+  TooManyRequests,
 }
 
-impl RuntimeAuthority {
-  pub(crate) fn new(acl: Resolved) -> Self {
-    let command_cache = acl
-      .command_scope
-      .keys()
-      .map(|key| (*key, <TypeMap![Send + Sync]>::new()))
-      .collect();
-    Self {
-      allowed_commands: acl.allowed_commands,
-      denied_commands: acl.denied_commands,
-      scope_manager: ScopeManager {
-        command_scope: acl.command_scope,
-        global_scope: acl.global_scope,
-        command_cache,
-        global_scope_cache: Default::default(),
-      },
+impl AccessError {
+  /// Maps the detailed access control list errors of [`RuntimeAuthority::resolve_access_detailed`] to [`Self::NotAllowed`].
+  ///
+  /// [`Self::Denied`] is kept since explicitly denied commands are rejected even when the IPC
+  /// dispatches them without an access control list entry, like [`Self::DeniedWithReason`].
+  pub(crate) fn coarse(self) -> Self {
+    match self {
+      Self::OriginMismatch | Self::WindowNotAllowed | Self::CommandNotFound => Self::NotAllowed,
+      e => e,
     }
   }
+}
 
-  /// Checks if the given IPC execution is allowed and returns the [`ResolvedCommand`] if it is.
-  pub fn resolve_access(
-    &self,
-    command: &str,
-    window: &str,
-    origin: Origin,
-  ) -> Option<&ResolvedCommand> {
-    if self
-      .denied_commands
-      .keys()
-      .any(|cmd| cmd.name == command && origin.matches(&cmd.context))
-    {
-      None
-    } else {
-      self
-        .allowed_commands
-        .iter()
-        .find(|(cmd, _)| cmd.name == command && origin.matches(&cmd.context))
-        .map(|(_cmd, allowed)| allowed)
-        .filter(|allowed| allowed.windows.iter().any(|w| w.matches(window)))
+/// The outcome of an access decision.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DecisionOutcome {
+  /// The command was allowed.
+  Allowed,
+  /// The command was denied.
+  Denied(AccessError),
+}
+
+impl<T> From<&Result<T, AccessError>> for DecisionOutcome {
+  fn from(result: &Result<T, AccessError>) -> Self {
+    match result {
+      Ok(_) => Self::Allowed,
+      Err(e) => Self::Denied(e.clone()),
     }
   }
 }
 
-#[derive(Debug)]
-struct ScopeValue<T: Debug + DeserializeOwned + Send + Sync + 'static> {
-  allow: Vec<T>,
-  deny: Vec<T>,
+/// An access decision of an invoke of the IPC,
+/// captured by the recorder set with [`RuntimeAuthority::set_decision_recorder`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecisionRecord {
+  /// The invoked command.
+  pub command: String,
+  /// The label of the window invoking the command.
+  pub window: String,
+  /// The origin invoking the command.
+  pub origin: Origin,
+  /// The decision.
+  pub outcome: DecisionOutcome,
 }
 
-/// Access scope for a command that can be retrieved directly in the command function.
-#[derive(Debug)]
-pub struct CommandScope<'a, T: Debug + DeserializeOwned + Send + Sync + 'static>(&'a ScopeValue<T>);
+/// A recorded decision that differs when replayed with [`RuntimeAuthority::replay`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Divergence {
+  /// The recorded decision.
+  pub record: DecisionRecord,
+  /// The outcome of the replayed decision.
+  pub replayed: DecisionOutcome,
+}
 
-impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandScope<'a, T> {
-  /// What this access scope allows.
-  pub fn allows(&self) -> &Vec<T> {
-    &self.0.allow
-  }
+/// A recorder set with [`RuntimeAuthority::set_decision_recorder`].
+type DecisionRecorder = Box<dyn Fn(&DecisionRecord) + Send + Sync>;
 
-  /// What this access scope denies.
-  pub fn denies(&self) -> &Vec<T> {
-    &self.0.deny
-  }
+/// A central store of runtime toggles, consulted by [`RuntimeAuthority::resolve_access`] for every command.
+///
+/// See [`RuntimeAuthority::set_toggle_store`].
+pub trait ToggleStore: Send + Sync {
+  /// Whether the given command is turned on.
+  fn is_on(&self, command: &str) -> bool;
 }
 
-impl<'a, R: Runtime, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandArg<'a, R>
-  for CommandScope<'a, T>
-{
-  /// Grabs the [`ResolvedScope`] from the [`CommandItem`] and returns the associated [`CommandScope`].
-  fn from_command(command: CommandItem<'a, R>) -> Result<Self, InvokeError> {
-    command
-      .acl
-      .as_ref()
-      .and_then(|resolved| resolved.scope)
-      .and_then(|scope_id| {
-        command
-          .message
-          .window
-          .manager
-          .runtime_authority
-          .scope_manager
-          .get_command_scope_typed(&scope_id)
-          .map(CommandScope)
-      })
-      .ok_or_else(|| InvokeError::from_anyhow(anyhow::anyhow!("scope not found")))
+/// A [`ToggleStore`] whose answers are cached for a fixed duration.
+struct CachedToggles {
+  store: Box<dyn ToggleStore>,
+  ttl: Duration,
+  entries: Mutex<BTreeMap<String, (Instant, bool)>>,
+}
+
+impl CachedToggles {
+  fn is_on(&self, command: &str) -> bool {
+    let mut entries = self.entries.lock().unwrap();
+    match entries.get(command) {
+      Some((checked_at, on)) if checked_at.elapsed() < self.ttl => *on,
+      _ => {
+        let on = self.store.is_on(command);
+        entries.insert(command.to_string(), (Instant::now(), on));
+        on
+      }
+    }
   }
 }
 
-/// Global access scope that can be retrieved directly in the command function.
-#[derive(Debug)]
-pub struct GlobalScope<'a, T: Debug + DeserializeOwned + Send + Sync + 'static>(&'a ScopeValue<T>);
+/// Throttles how often commands can be invoked, consulted by the IPC for the invokes it dispatches.
+///
+/// See [`RuntimeAuthority::set_rate_limiter`].
+pub trait RateLimiter: Send + Sync {
+  /// Whether the call is within the allowed rate.
+  ///
+  /// Only called for calls passing every other check, so the limiter can count each call it allows.
+  fn check(&self, command: &str, window: &str, origin: &Origin) -> bool;
+}
 
-impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> GlobalScope<'a, T> {
-  /// What this access scope allows.
-  pub fn allows(&self) -> &Vec<T> {
-    &self.0.allow
-  }
+/// Validates the entries of the scopes read as `T`, e.g. to reject paths escaping a directory.
+///
+/// See [`RuntimeAuthority::set_scope_entry_validator`].
+pub trait ScopeEntryValidator<T>: Send + Sync {
+  /// Checks a deserialized allow or deny entry, returning why it is rejected.
+  fn validate(&self, entry: &T) -> Result<(), String>;
+}
 
-  /// What this access scope denies.
-  pub fn denies(&self) -> &Vec<T> {
-    &self.0.deny
+impl<T, F: Fn(&T) -> Result<(), String> + Send + Sync> ScopeEntryValidator<T> for F {
+  fn validate(&self, entry: &T) -> Result<(), String> {
+    self(entry)
   }
 }
 
-impl<'a, R: Runtime, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandArg<'a, R>
-  for GlobalScope<'a, T>
-{
-  /// Grabs the [`ResolvedScope`] from the [`CommandItem`] and returns the associated [`GlobalScope`].
-  fn from_command(command: CommandItem<'a, R>) -> Result<Self, InvokeError> {
-    let scope = command
-      .message
-      .window
-      .manager
-      .runtime_authority
-      .scope_manager
-      .get_global_scope_typed();
-    Ok(GlobalScope(scope))
+/// Decodes the raw payload of a binary content type, e.g. MessagePack or CBOR,
+/// into the JSON value the command arguments are read from.
+///
+/// See [`RuntimeAuthority::register_payload_codec`].
+pub trait PayloadCodec: Send + Sync {
+  /// Decodes the bytes sent by the frontend, returning why they are invalid.
+  fn decode(&self, bytes: &[u8]) -> Result<JsonValue, String>;
+}
+
+impl<F: Fn(&[u8]) -> Result<JsonValue, String> + Send + Sync> PayloadCodec for F {
+  fn decode(&self, bytes: &[u8]) -> Result<JsonValue, String> {
+    self(bytes)
   }
 }
 
-#[derive(Debug)]
-pub struct ScopeManager {
-  command_scope: BTreeMap<ScopeKey, ResolvedScope>,
-  global_scope: ResolvedScope,
-  command_cache: BTreeMap<ScopeKey, TypeMap![Send + Sync]>,
-  global_scope_cache: TypeMap![Send + Sync],
+/// Cross-cutting logic run before every command passing the access control list, e.g. logging or refreshing credentials.
+///
+/// See [`RuntimeAuthority::add_command_interceptor`].
+pub trait CommandInterceptor: Send + Sync {
+  /// Called before the arguments of the command are deserialized.
+  ///
+  /// Returning an error rejects the command without running it nor the remaining interceptors.
+  fn intercept(&self, command: &str, window: &str, origin: &Origin) -> Result<(), InvokeError>;
 }
 
-impl ScopeManager {
-  fn get_global_scope_typed<T: Send + Sync + DeserializeOwned + Debug + 'static>(
-    &self,
-  ) -> &ScopeValue<T> {
-    match self.global_scope_cache.try_get() {
-      Some(cached) => cached,
-      None => {
-        let mut allow: Vec<T> = Vec::new();
-        let mut deny: Vec<T> = Vec::new();
+/// Custom matching of origins against the execution context of the access control list entries.
+///
+/// See [`RuntimeAuthority::set_origin_matcher`].
+pub trait OriginMatcher: Send + Sync {
+  /// Whether the origin matches the execution context of an entry.
+  ///
+  /// Returns `None` to fall back to the built-in glob matching.
+  fn matches(&self, origin: &Origin, context: &ExecutionContext) -> Option<bool>;
+}
 
-        for allowed in &self.global_scope.allow {
-          allow.push(allowed.deserialize().unwrap());
-        }
-        for denied in &self.global_scope.deny {
-          deny.push(denied.deserialize().unwrap());
-        }
+/// The registry of the features enabled at runtime, used by [`RuntimeAuthority::require_feature`].
+///
+/// This is a cheap handle to the registry of the runtime authority: clone it with
+/// [`RuntimeAuthority::feature_registry`] to toggle features while the app is running.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureRegistry(Arc<RwLock<BTreeSet<String>>>);
 
-        let scope = ScopeValue { allow, deny };
-        let _ = self.global_scope_cache.set(scope);
-        self.global_scope_cache.get()
-      }
-    }
+impl FeatureRegistry {
+  /// Enables the given feature.
+  pub fn enable(&self, feature: impl Into<String>) {
+    self.0.write().unwrap().insert(feature.into());
   }
 
-  fn get_command_scope_typed<T: Send + Sync + DeserializeOwned + Debug + 'static>(
-    &self,
-    key: &ScopeKey,
-  ) -> Option<&ScopeValue<T>> {
-    let cache = self.command_cache.get(key).unwrap();
-    match cache.try_get() {
-      cached @ Some(_) => cached,
-      None => match self.command_scope.get(key).map(|r| {
-        let mut allow: Vec<T> = Vec::new();
-        let mut deny: Vec<T> = Vec::new();
-
-        for allowed in &r.allow {
-          allow.push(allowed.deserialize().unwrap());
-        }
-        for denied in &r.deny {
-          deny.push(denied.deserialize().unwrap());
-        }
+  /// Disables the given feature.
+  pub fn disable(&self, feature: &str) {
+    self.0.write().unwrap().remove(feature);
+  }
 
-        ScopeValue { allow, deny }
-      }) {
-        None => None,
-        Some(value) => {
-          let _ = cache.set(value);
-          cache.try_get()
-        }
-      },
+  /// Checks if the given feature is enabled.
+  pub fn is_enabled(&self, feature: &str) -> bool {
+    self.0.read().unwrap().contains(feature)
+  }
+}
+
+/// A potential inconsistency of the access control list, reported by [`RuntimeAuthority::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AclWarning {
+  /// A command is allowed but its companion command is not.
+  MissingCompanion {
+    /// The allowed command.
+    command: String,
+    /// The companion command that is not allowed.
+    companion: String,
+    /// The execution context the command is allowed in.
+    context: ExecutionContext,
+  },
+}
+
+impl std::fmt::Display for AclWarning {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::MissingCompanion {
+        command, companion, ..
+      } => write!(
+        f,
+        "command {command} is allowed but its companion command {companion} is not"
+      ),
     }
   }
 }
 
-#[cfg(test)]
-mod tests {
-  use glob::Pattern;
-  use tauri_utils::acl::{
-    resolved::{CommandKey, Resolved, ResolvedCommand},
-    ExecutionContext,
-  };
+/// Errors that can happen when aliasing a scope key.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ScopeAliasError {
+  /// The alias would create a cycle.
+  #[error("aliasing scope {alias} to scope {target} creates a cycle")]
+  Cycle {
+    /// The alias scope key.
+    alias: ScopeKey,
+    /// The target scope key.
+    target: ScopeKey,
+  },
+}
 
-  use crate::command::Origin;
+/// Errors that can happen when reading a scope as a typed value.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ScopeError {
+  /// The command scope does not exist.
+  #[error("scope {0} not found")]
+  NotFound(ScopeKey),
+  /// A scope entry does not deserialize into the requested type.
+  #[error(
+    "{} entry {entry} does not deserialize into {type_name}",
+    scope.map_or_else(|| "global scope".to_string(), |key| format!("scope {key}"))
+  )]
+  InvalidEntry {
+    /// The command scope key, or `None` for the global scope.
+    scope: Option<ScopeKey>,
+    /// The entry serialized as JSON.
+    entry: String,
+    /// The name of the requested type.
+    type_name: &'static str,
+  },
+  /// A scope entry was rejected by the [`ScopeEntryValidator`] of its type.
+  #[error(
+    "{} entry {entry} is rejected: {reason}",
+    scope.map_or_else(|| "global scope".to_string(), |key| format!("scope {key}"))
+  )]
+  RejectedEntry {
+    /// The command scope key, or `None` for the global scope.
+    scope: Option<ScopeKey>,
+    /// The entry serialized as JSON.
+    entry: String,
+    /// Why the validator rejected the entry.
+    reason: String,
+  },
+  /// The command scope was already requested as a different type.
+  #[error("scope {scope} requested as {requested} but it was first requested as {first}")]
+  TypeMismatch {
+    /// The command scope key.
+    scope: ScopeKey,
+    /// The name of the type requested first.
+    first: &'static str,
+    /// The name of the conflicting type.
+    requested: &'static str,
+  },
+}
 
-  use super::RuntimeAuthority;
+/// The access control list of a plugin, installed at runtime with [`RuntimeAuthority::install_plugin`].
+#[derive(Debug, Default)]
+pub struct PluginAcl {
+  /// The commands that are allowed.
+  pub allowed_commands: BTreeMap<CommandKey, ResolvedCommand>,
+  /// The commands that are denied.
+  pub denied_commands: BTreeMap<CommandKey, ResolvedCommand>,
+  /// The scopes referenced by the commands.
+  pub command_scope: BTreeMap<ScopeKey, ResolvedScope>,
+}
 
-  #[test]
-  fn window_glob_pattern_matches() {
-    let command = CommandKey {
-      name: "my-command".into(),
-      context: ExecutionContext::Local,
-    };
-    let window = "main-*";
+/// A command added to a [`RuntimeAuthorityBuilder`], with its window patterns not compiled yet.
+#[derive(Debug)]
+struct CommandEntry {
+  key: CommandKey,
+  windows: Vec<String>,
+  scope: Option<ScopeKey>,
+}
 
-    let resolved_cmd = ResolvedCommand {
-      windows: vec![Pattern::new(window).unwrap()],
-      scope: None,
-    };
-    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
-      .into_iter()
-      .collect();
+/// Errors that can happen when building a [`RuntimeAuthority`] with [`RuntimeAuthorityBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum AuthorityBuildError {
+  /// The command is both allowed and denied for the same execution context.
+  #[error("command {0} is both allowed and denied for the same context")]
+  AllowDenyConflict(String),
+  /// Some window or domain patterns are invalid.
+  #[error("invalid patterns: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+  InvalidPatterns(Vec<PatternError>),
+  /// A command references a scope that is not registered.
+  #[error("command {command} references the unknown scope {scope}")]
+  UnknownScope {
+    /// The command referencing the scope.
+    command: String,
+    /// The key of the missing scope.
+    scope: ScopeKey,
+  },
+}
 
-    let authority = RuntimeAuthority::new(Resolved {
-      allowed_commands,
-      denied_commands: Default::default(),
-      command_scope: Default::default(),
-      global_scope: Default::default(),
-    });
+/// Builds a [`RuntimeAuthority`] command by command instead of from a resolved access control list.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::{command::RuntimeAuthorityBuilder, utils::acl::ExecutionContext};
+///
+/// let authority = RuntimeAuthorityBuilder::new()
+///   .allow_command("read", ExecutionContext::Local, ["main", "settings-*"])
+///   .deny_command("delete", ExecutionContext::Local, ["*"])
+///   .build()
+///   .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct RuntimeAuthorityBuilder {
+  allowed_commands: Vec<CommandEntry>,
+  denied_commands: Vec<CommandEntry>,
+  command_scope: BTreeMap<ScopeKey, ResolvedScope>,
+  global_scope: ResolvedScope,
+}
 
-    assert_eq!(
-      authority.resolve_access(
-        &command.name,
-        &window.replace('*', "something"),
-        Origin::Local
-      ),
-      Some(&resolved_cmd)
-    );
+impl RuntimeAuthorityBuilder {
+  /// Creates a builder without any command.
+  pub fn new() -> Self {
+    Self::default()
   }
 
-  #[test]
-  fn remote_domain_matches() {
-    let domain = "tauri.app";
-    let command = CommandKey {
-      name: "my-command".into(),
-      context: ExecutionContext::Remote {
-        domain: Pattern::new(domain).unwrap(),
-      },
-    };
-    let window = "main";
+  /// Allows the command for the given context on the windows matching one of the glob patterns.
+  #[must_use]
+  pub fn allow_command<I: IntoIterator<Item = impl Into<String>>>(
+    mut self,
+    name: impl Into<String>,
+    context: ExecutionContext,
+    windows: I,
+  ) -> Self {
+    self
+      .allowed_commands
+      .push(Self::entry(name, context, windows, None));
+    self
+  }
 
-    let resolved_cmd = ResolvedCommand {
-      windows: vec![Pattern::new(window).unwrap()],
-      scope: None,
-    };
-    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
-      .into_iter()
-      .collect();
+  /// Allows the command like [`Self::allow_command`], with the command scope registered under the given key.
+  #[must_use]
+  pub fn allow_scoped_command<I: IntoIterator<Item = impl Into<String>>>(
+    mut self,
+    name: impl Into<String>,
+    context: ExecutionContext,
+    windows: I,
+    scope: ScopeKey,
+  ) -> Self {
+    self
+      .allowed_commands
+      .push(Self::entry(name, context, windows, Some(scope)));
+    self
+  }
 
-    let authority = RuntimeAuthority::new(Resolved {
-      allowed_commands,
-      denied_commands: Default::default(),
-      command_scope: Default::default(),
-      global_scope: Default::default(),
-    });
+  /// Denies the command for the given context on the windows matching one of the glob patterns.
+  #[must_use]
+  pub fn deny_command<I: IntoIterator<Item = impl Into<String>>>(
+    mut self,
+    name: impl Into<String>,
+    context: ExecutionContext,
+    windows: I,
+  ) -> Self {
+    self
+      .denied_commands
+      .push(Self::entry(name, context, windows, None));
+    self
+  }
 
-    assert_eq!(
-      authority.resolve_access(
-        &command.name,
-        window,
-        Origin::Remote {
-          domain: domain.into()
-        }
-      ),
-      Some(&resolved_cmd)
-    );
+  /// Registers a command scope, referenced by [`Self::allow_scoped_command`].
+  #[must_use]
+  pub fn with_command_scope(mut self, key: ScopeKey, scope: ResolvedScope) -> Self {
+    self.command_scope.insert(key, scope);
+    self
   }
 
-  #[test]
-  fn remote_domain_glob_pattern_matches() {
-    let domain = "tauri.*";
-    let command = CommandKey {
-      name: "my-command".into(),
-      context: ExecutionContext::Remote {
-        domain: Pattern::new(domain).unwrap(),
+  /// Sets the global scope.
+  #[must_use]
+  pub fn with_global_scope(mut self, scope: ResolvedScope) -> Self {
+    self.global_scope = scope;
+    self
+  }
+
+  fn entry<I: IntoIterator<Item = impl Into<String>>>(
+    name: impl Into<String>,
+    context: ExecutionContext,
+    windows: I,
+    scope: Option<ScopeKey>,
+  ) -> CommandEntry {
+    CommandEntry {
+      key: CommandKey {
+        name: name.into(),
+        context,
       },
-    };
-    let window = "main";
+      windows: windows.into_iter().map(Into::into).collect(),
+      scope,
+    }
+  }
 
-    let resolved_cmd = ResolvedCommand {
-      windows: vec![Pattern::new(window).unwrap()],
-      scope: None,
+  /// Builds the authority.
+  ///
+  /// Fails if a command is both allowed and denied for the same context, if a window pattern is invalid
+  /// or if a command scope was not registered with [`Self::with_command_scope`].
+  pub fn build(self) -> Result<RuntimeAuthority, AuthorityBuildError> {
+    if let Some(conflict) = self.allowed_commands.iter().find(|allowed| {
+      self
+        .denied_commands
+        .iter()
+        .any(|denied| denied.key == allowed.key)
+    }) {
+      return Err(AuthorityBuildError::AllowDenyConflict(
+        conflict.key.name.clone(),
+      ));
+    }
+
+    let mut errors = Vec::new();
+    let mut compile = |entries: Vec<CommandEntry>| {
+      entries
+        .into_iter()
+        .map(|entry| {
+          let windows = entry
+            .windows
+            .into_iter()
+            .filter_map(|window| match glob::Pattern::new(&window) {
+              Ok(pattern) => Some(pattern),
+              Err(e) => {
+                errors.push(PatternError {
+                  command: entry.key.name.clone(),
+                  pattern: window,
+                  reason: e.msg.to_string(),
+                });
+                None
+              }
+            })
+            .collect();
+          (
+            entry.key,
+            ResolvedCommand {
+              windows,
+              scope: entry.scope,
+            },
+          )
+        })
+        .collect::<BTreeMap<_, _>>()
     };
-    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
-      .into_iter()
-      .collect();
+    let allowed_commands = compile(self.allowed_commands);
+    let denied_commands = compile(self.denied_commands);
+    if !errors.is_empty() {
+      return Err(AuthorityBuildError::InvalidPatterns(errors));
+    }
 
-    let authority = RuntimeAuthority::new(Resolved {
+    RuntimeAuthority::try_new(Resolved {
       allowed_commands,
-      denied_commands: Default::default(),
-      command_scope: Default::default(),
-      global_scope: Default::default(),
-    });
-
-    assert_eq!(
-      authority.resolve_access(
-        &command.name,
-        window,
-        Origin::Remote {
-          domain: domain.replace('*', "studio")
-        }
-      ),
-      Some(&resolved_cmd)
-    );
+      denied_commands,
+      command_scope: self.command_scope,
+      global_scope: self.global_scope,
+    })
   }
+}
 
-  #[test]
+/// Identifier of a plugin installed with [`RuntimeAuthority::install_plugin`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PluginId(u64);
+
+/// What an installed plugin added to the authority.
+#[derive(Debug)]
+struct InstalledPlugin {
+  allowed_commands: Vec<CommandKey>,
+  denied_commands: Vec<CommandKey>,
+  scopes: Vec<ScopeKey>,
+}
+
+/// Errors that can happen when installing a plugin [`PluginAcl`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum InstallError {
+  /// A command of the plugin is already registered.
+  #[error("command {0} is already registered")]
+  CommandConflict(String),
+  /// A scope of the plugin is already registered.
+  #[error("scope {0} is already registered")]
+  ScopeConflict(ScopeKey),
+}
+
+/// Errors that can happen when merging an access control list with [`RuntimeAuthority::merge`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum MergeError {
+  /// Both access control lists define the scope.
+  #[error("scope {0} is defined by both access control lists")]
+  ScopeConflict(ScopeKey),
+  /// Both access control lists allow the command for the same context with different scopes.
+  #[error("command {0} is allowed with different scopes by both access control lists")]
+  CommandScopeConflict(String),
+}
+
+/// A window or domain pattern of the ACL that can never match as intended.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid pattern `{pattern}` for command {command}: {reason}")]
+pub struct PatternError {
+  /// The command the pattern belongs to.
+  pub command: String,
+  /// The pattern as written.
+  pub pattern: String,
+  /// Why the pattern is invalid.
+  pub reason: String,
+}
+
+/// Checks a glob pattern for compilation errors and for stray `]` characters,
+/// which `glob` compiles to a literal that no window label or domain can contain.
+fn pattern_issue(pattern: &str) -> Option<String> {
+  if let Err(e) = glob::Pattern::new(pattern) {
+    return Some(e.msg.to_string());
+  }
+  let chars = pattern.chars().collect::<Vec<_>>();
+  let mut i = 0;
+  while i < chars.len() {
+    match chars[i] {
+      '[' => {
+        // skip the character class, whose closing bracket glob already validated
+        i += 1;
+        if chars.get(i) == Some(&'!') {
+          i += 1;
+        }
+        if chars.get(i) == Some(&']') {
+          i += 1;
+        }
+        while i < chars.len() && chars[i] != ']' {
+          i += 1;
+        }
+      }
+      ']' => return Some(format!("unmatched `]` at position {i}")),
+      _ => {}
+    }
+    i += 1;
+  }
+  None
+}
+
+/// The principal trying to access the IPC.
+///
+/// The IPC uses the principal of the invoking window, see [`crate::Window::set_principal`].
+#[derive(Debug, Default, Clone)]
+pub struct Principal {
+  /// The last time this principal completed a step-up authentication.
+  pub last_step_up: Option<Instant>,
+}
+
+/// The origin trying to access the IPC.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Origin {
+  /// Local app origin.
+  Local,
+  /// Local app origin with the URL that made the call.
+  LocalUrl {
+    /// Local origin URL, as `scheme://host[:port]`.
+    url: String,
+  },
+  /// Remote origin.
+  Remote {
+    /// Remote origin domain.
+    domain: String,
+  },
+  /// Remote origin with its full URL information.
+  RemoteUrl {
+    /// Remote origin scheme.
+    scheme: String,
+    /// Remote origin domain.
+    domain: String,
+    /// Remote origin port, if known.
+    port: Option<u16>,
+  },
+}
+
+impl Origin {
+  fn matches(&self, context: &ExecutionContext) -> bool {
+    // domains are case-insensitive
+    let domain_matches = |pattern: &glob::Pattern, domain: &str| {
+      pattern.matches_with(
+        domain,
+        glob::MatchOptions {
+          case_sensitive: false,
+          ..Default::default()
+        },
+      )
+    };
+    match (self, context) {
+      // a local context without a URL pattern allows any local origin
+      (Self::Local | Self::LocalUrl { .. }, ExecutionContext::Local) => true,
+      (Self::LocalUrl { url }, ExecutionContext::LocalUrl { url: url_pattern }) => {
+        url_pattern.matches(&url.to_lowercase())
+      }
+      (
+        Self::Remote { domain } | Self::RemoteUrl { domain, .. },
+        ExecutionContext::Remote {
+          domain: domain_pattern,
+        },
+      ) => domain_matches(domain_pattern, domain),
+      (
+        Self::RemoteUrl {
+          scheme,
+          domain,
+          port,
+        },
+        ExecutionContext::RemoteUrl {
+          scheme: scheme_pattern,
+          domain: domain_pattern,
+          port: port_pattern,
+        },
+      ) => {
+        scheme_pattern.as_ref().map_or(true, |s| s == scheme)
+          && port_pattern.map_or(true, |p| Some(p) == *port)
+          && domain_matches(domain_pattern, domain)
+      }
+      _ => false,
+    }
+  }
+}
+
+impl RuntimeAuthority {
+  /// Creates the authority after validating every window and domain pattern of the ACL.
+  ///
+  /// All invalid patterns are reported instead of being kept as patterns that never match.
+  /// Commands referencing a scope missing from the ACL are rejected as well,
+  /// instead of failing every invoke that reads the scope.
+  pub(crate) fn try_new(acl: Resolved) -> Result<Self, AuthorityBuildError> {
+    let mut errors = Vec::new();
+    for (key, command) in acl.allowed_commands.iter().chain(&acl.denied_commands) {
+      for pattern in command.windows.iter().chain(key.context.domain()) {
+        if let Some(reason) = pattern_issue(pattern.as_str()) {
+          errors.push(PatternError {
+            command: key.name.clone(),
+            pattern: pattern.as_str().to_string(),
+            reason,
+          });
+        }
+      }
+    }
+
+    if !errors.is_empty() {
+      return Err(AuthorityBuildError::InvalidPatterns(errors));
+    }
+    if let Some(error) = Self::unknown_scope(&acl) {
+      return Err(error);
+    }
+    Ok(Self::from_resolved(acl))
+  }
+
+  /// Finds the first command referencing a scope missing from the ACL.
+  fn unknown_scope(acl: &Resolved) -> Option<AuthorityBuildError> {
+    acl
+      .allowed_commands
+      .iter()
+      .chain(&acl.denied_commands)
+      .find_map(|(key, command)| match command.scope {
+        Some(scope) if !acl.command_scope.contains_key(&scope) => {
+          Some(AuthorityBuildError::UnknownScope {
+            command: key.name.clone(),
+            scope,
+          })
+        }
+        _ => None,
+      })
+  }
+
+  /// Creates the authority from a resolved access control list, to write authorization tests
+  /// without going through the IPC.
+  ///
+  /// Unlike the authority of the app, window and domain patterns are not validated.
+  ///
+  /// # Panics
+  ///
+  /// With debug assertions enabled, panics if a command references a scope missing from the ACL.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use tauri::{
+  ///   command::{AccessError, Origin, Principal, RuntimeAuthority},
+  ///   utils::acl::{
+  ///     resolved::{CommandKey, Resolved, ResolvedCommand},
+  ///     ExecutionContext,
+  ///   },
+  /// };
+  ///
+  /// let key = CommandKey {
+  ///   name: "plugin:fs:read".into(),
+  ///   context: ExecutionContext::Local,
+  /// };
+  /// let command = ResolvedCommand {
+  ///   windows: vec![glob::Pattern::new("main").unwrap()],
+  ///   scope: None,
+  /// };
+  /// let authority = RuntimeAuthority::new(Resolved {
+  ///   allowed_commands: [(key, command.clone())].into_iter().collect(),
+  ///   denied_commands: Default::default(),
+  ///   command_scope: Default::default(),
+  ///   global_scope: Default::default(),
+  /// });
+  ///
+  /// assert_eq!(
+  ///   authority.resolve_access("plugin:fs:read", "main", &Origin::Local),
+  ///   Some(&command)
+  /// );
+  /// assert_eq!(
+  ///   authority.resolve_access_detailed("plugin:fs:read", "other", &Origin::Local),
+  ///   Err(AccessError::WindowNotAllowed)
+  /// );
+  /// ```
+  #[cfg(any(test, feature = "test"))]
+  #[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+  pub fn new(acl: Resolved) -> Self {
+    if cfg!(debug_assertions) {
+      if let Some(error) = Self::unknown_scope(&acl) {
+        panic!("{error}");
+      }
+    }
+    Self::from_resolved(acl)
+  }
+
+  fn from_resolved(acl: Resolved) -> Self {
+    let mut authority = Self {
+      allowed_commands: acl.allowed_commands,
+      denied_commands: acl.denied_commands,
+      allowed_index: Default::default(),
+      denied_index: Default::default(),
+      scope_manager: ScopeManager::new(acl.command_scope, acl.global_scope),
+      step_up_commands: Default::default(),
+      step_up_freshness: DEFAULT_STEP_UP_FRESHNESS,
+      focus_commands: Default::default(),
+      companions: Default::default(),
+      response_cache: Default::default(),
+      idempotency_cache: Default::default(),
+      max_payload_depth: DEFAULT_MAX_PAYLOAD_DEPTH,
+      max_response_bytes: Default::default(),
+      query_fallback_commands: Default::default(),
+      key_normalization_commands: Default::default(),
+      strict_field_commands: Default::default(),
+      features: Default::default(),
+      required_features: Default::default(),
+      deny_reasons: Default::default(),
+      toggles: None,
+      origin_matcher: None,
+      rate_limiter: None,
+      decision_recorder: None,
+      #[cfg(feature = "metrics")]
+      decision_counts: Default::default(),
+      payload_transforms: Vec::new(),
+      payload_codecs: Default::default(),
+      interceptors: Vec::new(),
+      installed_plugins: Default::default(),
+      next_plugin_id: 0,
+      command_schemas: Default::default(),
+      #[cfg(any(test, feature = "test"))]
+      test_handlers: Default::default(),
+      #[cfg(any(test, feature = "test"))]
+      record_argument_accesses: false,
+      #[cfg(any(test, feature = "test"))]
+      argument_accesses: Default::default(),
+    };
+    authority.index_commands();
+    authority
+  }
+
+  /// Rebuilds the prefix trees used to resolve commands, after the access control list changed.
+  fn index_commands(&mut self) {
+    let index = |commands: &BTreeMap<CommandKey, ResolvedCommand>| {
+      let mut index = CommandTrie::default();
+      for key in commands.keys() {
+        index.insert(&key.name, key.clone());
+      }
+      index
+    };
+    self.allowed_index = index(&self.allowed_commands);
+    self.denied_index = index(&self.denied_commands);
+  }
+
+  /// Iterates over the allowed commands of the access control list, including the ones of installed plugins.
+  pub fn allowed_commands_iter(&self) -> impl Iterator<Item = (&CommandKey, &ResolvedCommand)> {
+    self.allowed_commands.iter()
+  }
+
+  /// Iterates over the denied commands of the access control list, including the ones of installed plugins.
+  pub fn denied_commands_iter(&self) -> impl Iterator<Item = (&CommandKey, &ResolvedCommand)> {
+    self.denied_commands.iter()
+  }
+
+  /// Adds all commands and scopes of the plugin as a unit.
+  ///
+  /// The whole bundle is validated first, so nothing is added if any command or scope is already registered.
+  pub fn install_plugin(&mut self, bundle: PluginAcl) -> Result<PluginId, InstallError> {
+    for key in bundle
+      .allowed_commands
+      .keys()
+      .chain(bundle.denied_commands.keys())
+    {
+      if self.allowed_commands.contains_key(key) || self.denied_commands.contains_key(key) {
+        return Err(InstallError::CommandConflict(key.name.clone()));
+      }
+    }
+    for key in bundle.command_scope.keys() {
+      if self.scope_manager.command_scope.contains_key(key) {
+        return Err(InstallError::ScopeConflict(*key));
+      }
+    }
+
+    let installed = InstalledPlugin {
+      allowed_commands: bundle.allowed_commands.keys().cloned().collect(),
+      denied_commands: bundle.denied_commands.keys().cloned().collect(),
+      scopes: bundle.command_scope.keys().copied().collect(),
+    };
+    self.allowed_commands.extend(bundle.allowed_commands);
+    self.denied_commands.extend(bundle.denied_commands);
+    self.index_commands();
+    for (key, scope) in bundle.command_scope {
+      self.scope_manager.command_scope.insert(key, scope);
+      self
+        .scope_manager
+        .command_cache
+        .get_mut()
+        .unwrap()
+        .insert(key, <TypeMap![Send + Sync]>::new());
+    }
+
+    let id = PluginId(self.next_plugin_id);
+    self.next_plugin_id += 1;
+    self.installed_plugins.insert(id, installed);
+    Ok(id)
+  }
+
+  /// Removes exactly the commands and scopes added by [`Self::install_plugin`].
+  ///
+  /// Returns `false` if the plugin is not installed.
+  pub fn uninstall_plugin(&mut self, id: PluginId) -> bool {
+    let Some(installed) = self.installed_plugins.remove(&id) else {
+      return false;
+    };
+    for key in &installed.allowed_commands {
+      self.allowed_commands.remove(key);
+    }
+    for key in &installed.denied_commands {
+      self.denied_commands.remove(key);
+    }
+    self.index_commands();
+    let mut session_grants = self.scope_manager.session_grants.lock().unwrap();
+    for key in &installed.scopes {
+      self.scope_manager.command_scope.remove(key);
+      self
+        .scope_manager
+        .command_cache
+        .get_mut()
+        .unwrap()
+        .remove(key);
+      session_grants.remove(key);
+    }
+    true
+  }
+
+  /// Folds another resolved access control list, e.g. the one of a plugin, into this authority.
+  ///
+  /// Window patterns of commands present on both sides are combined. Denied commands take precedence
+  /// over allowed ones regardless of the side they come from, and the global scopes are concatenated.
+  ///
+  /// Nothing is merged if both sides define the same command scope key,
+  /// or allow the same command with different scopes.
+  pub fn merge(&mut self, other: Resolved) -> Result<(), MergeError> {
+    for key in other.command_scope.keys() {
+      if self.scope_manager.command_scope.contains_key(key) {
+        return Err(MergeError::ScopeConflict(*key));
+      }
+    }
+    for (key, command) in &other.allowed_commands {
+      if let Some(existing) = self.allowed_commands.get(key) {
+        if existing.scope.is_some() && command.scope.is_some() && existing.scope != command.scope {
+          return Err(MergeError::CommandScopeConflict(key.name.clone()));
+        }
+      }
+    }
+
+    let merge_commands = |commands: &mut BTreeMap<CommandKey, ResolvedCommand>,
+                          other: BTreeMap<CommandKey, ResolvedCommand>| {
+      for (key, command) in other {
+        match commands.get_mut(&key) {
+          Some(existing) => {
+            for window in command.windows {
+              if !existing.windows.contains(&window) {
+                existing.windows.push(window);
+              }
+            }
+            existing.scope = existing.scope.or(command.scope);
+          }
+          None => {
+            commands.insert(key, command);
+          }
+        }
+      }
+    };
+    merge_commands(&mut self.allowed_commands, other.allowed_commands);
+    merge_commands(&mut self.denied_commands, other.denied_commands);
+    self.index_commands();
+
+    for (key, scope) in other.command_scope {
+      self.scope_manager.command_scope.insert(key, scope);
+      self
+        .scope_manager
+        .command_cache
+        .get_mut()
+        .unwrap()
+        .insert(key, <TypeMap![Send + Sync]>::new());
+    }
+    let global_scope = &mut self.scope_manager.global_scope;
+    global_scope.allow.extend(other.global_scope.allow);
+    global_scope.deny.extend(other.global_scope.deny);
+    *self.scope_manager.global_scope_cache.get_mut().unwrap() = <TypeMap![Send + Sync]>::new();
+
+    Ok(())
+  }
+
+  /// Marks the given command as requiring a recent step-up authentication.
+  ///
+  /// See [`Self::set_step_up_freshness`] for how recent the step-up must be.
+  pub fn require_step_up(&mut self, command: impl Into<String>) {
+    self.step_up_commands.insert(command.into());
+  }
+
+  /// Sets how long a step-up authentication is considered fresh. Defaults to 5 minutes.
+  pub fn set_step_up_freshness(&mut self, freshness: Duration) {
+    self.step_up_freshness = freshness;
+  }
+
+  /// Marks the given command as requiring the invoking window to be focused,
+  /// e.g. to prevent background clickjacking on security-sensitive commands.
+  ///
+  /// Invokes from an unfocused window are rejected with [`AccessError::WindowNotFocused`],
+  /// after the rejections of the access control list.
+  pub fn require_focus(&mut self, command: impl Into<String>) {
+    self.focus_commands.insert(command.into());
+  }
+
+  pub(crate) fn requires_focus(&self, command: &str) -> bool {
+    self.focus_commands.contains(command)
+  }
+
+  /// Declares that the given command is only meaningful if its companion command is also allowed,
+  /// e.g. `begin_transaction` requires `commit_transaction`. Checked by [`Self::validate`].
+  pub fn require_companion(&mut self, command: impl Into<String>, companion: impl Into<String>) {
+    self
+      .companions
+      .entry(command.into())
+      .or_default()
+      .insert(companion.into());
+  }
+
+  /// Checks the access control list for inconsistent policies.
+  ///
+  /// Reports [`AclWarning::MissingCompanion`] when a command is allowed in an execution context
+  /// where one of its companions declared with [`Self::require_companion`] is not.
+  pub fn validate(&self) -> Vec<AclWarning> {
+    let allowed = |name: &str, context: &ExecutionContext| {
+      let key = CommandKey {
+        name: name.into(),
+        context: context.clone(),
+      };
+      self.allowed_commands.contains_key(&key) && !self.denied_commands.contains_key(&key)
+    };
+    let mut warnings = Vec::new();
+    for key in self.allowed_commands.keys() {
+      for companion in self.companions.get(&key.name).into_iter().flatten() {
+        if !allowed(companion, &key.context) {
+          warnings.push(AclWarning::MissingCompanion {
+            command: key.name.clone(),
+            companion: companion.clone(),
+            context: key.context.clone(),
+          });
+        }
+      }
+    }
+    warnings
+  }
+
+  /// Returns a compact, human-readable dump of the effective access control list and runtime requirements,
+  /// e.g. to include in crash reports.
+  ///
+  /// The format is meant for diagnostics only and may change at any time.
+  pub fn debug_snapshot(&self) -> String {
+    use std::fmt::Write;
+
+    let context = |context: &ExecutionContext| match context {
+      ExecutionContext::Local => "local".to_string(),
+      ExecutionContext::LocalUrl { url } => format!("local({url})"),
+      ExecutionContext::Remote { domain } => format!("remote({domain})"),
+      ExecutionContext::RemoteUrl {
+        scheme,
+        domain,
+        port,
+      } => format!(
+        "remote({}{domain}{})",
+        scheme
+          .as_ref()
+          .map(|s| format!("{s}://"))
+          .unwrap_or_default(),
+        port.map(|p| format!(":{p}")).unwrap_or_default()
+      ),
+    };
+    let commands =
+      |out: &mut String, title: &str, commands: &BTreeMap<CommandKey, ResolvedCommand>| {
+        let _ = writeln!(out, "{title}:");
+        for (key, command) in commands {
+          let windows = command
+            .windows
+            .iter()
+            .map(|w| w.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+          let scope = command
+            .scope
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "-".into());
+          let _ = writeln!(
+            out,
+            "  {} {} windows=[{windows}] scope={scope}",
+            key.name,
+            context(&key.context)
+          );
+        }
+      };
+    let list = |out: &mut String, title: &str, items: Vec<String>| {
+      if !items.is_empty() {
+        let _ = writeln!(out, "{title}: {}", items.join(", "));
+      }
+    };
+
+    let mut out = String::new();
+    commands(&mut out, "allowed", &self.allowed_commands);
+    commands(&mut out, "denied", &self.denied_commands);
+    list(
+      &mut out,
+      "step-up",
+      self.step_up_commands.iter().cloned().collect(),
+    );
+    list(
+      &mut out,
+      "focus",
+      self.focus_commands.iter().cloned().collect(),
+    );
+    list(
+      &mut out,
+      "required features",
+      self
+        .required_features
+        .iter()
+        .map(|(command, feature)| format!("{command}={feature}"))
+        .collect(),
+    );
+    list(
+      &mut out,
+      "enabled features",
+      self.features.0.read().unwrap().iter().cloned().collect(),
+    );
+    if let Some(toggles) = &self.toggles {
+      list(
+        &mut out,
+        "toggles",
+        toggles
+          .entries
+          .lock()
+          .unwrap()
+          .iter()
+          .map(|(command, (_, on))| format!("{command}={}", if *on { "on" } else { "off" }))
+          .collect(),
+      );
+    }
+    out
+  }
+
+  /// Sets the reason sent to the frontend when the given deny entry rejects a command,
+  /// instead of the generic rejection, see [`AccessError::DeniedWithReason`].
+  pub fn set_deny_reason(&mut self, key: CommandKey, reason: impl Into<String>) {
+    self.deny_reasons.insert(key, reason.into());
+  }
+
+  /// Marks the given command as requiring a feature enabled in the [`FeatureRegistry`].
+  pub fn require_feature(&mut self, command: impl Into<String>, feature: impl Into<String>) {
+    self
+      .required_features
+      .insert(command.into(), feature.into());
+  }
+
+  /// Sets the store of runtime toggles consulted for every command.
+  ///
+  /// Commands turned off in the store are denied with [`AccessError::ToggledOff`].
+  /// The answer of the store for a command is cached for the given duration.
+  pub fn set_toggle_store<S: ToggleStore + 'static>(&mut self, store: S, ttl: Duration) {
+    self.toggles = Some(CachedToggles {
+      store: Box::new(store),
+      ttl,
+      entries: Default::default(),
+    });
+  }
+
+  /// Sets the matcher consulted before the built-in glob matching when checking the origin of a command.
+  pub fn set_origin_matcher<M: OriginMatcher + 'static>(&mut self, matcher: M) {
+    self.origin_matcher = Some(Box::new(matcher));
+  }
+
+  /// Sets the limiter throttling the commands, see [`RateLimiter`].
+  ///
+  /// Invokes over the rate are denied with [`AccessError::TooManyRequests`].
+  /// Access queries such as [`Self::resolve_access`] don't consult the limiter.
+  pub fn set_rate_limiter<L: RateLimiter + 'static>(&mut self, limiter: L) {
+    self.rate_limiter = Some(Box::new(limiter));
+  }
+
+  fn origin_matches(&self, origin: &Origin, context: &ExecutionContext) -> bool {
+    self
+      .origin_matcher
+      .as_ref()
+      .and_then(|matcher| matcher.matches(origin, context))
+      .unwrap_or_else(|| origin.matches(context))
+  }
+
+  /// Returns a handle to the registry of the features enabled at runtime.
+  pub fn feature_registry(&self) -> FeatureRegistry {
+    self.features.clone()
+  }
+
+  /// Marks the given command as cacheable.
+  ///
+  /// Its serialized response is reused for invokes with identical JSON arguments for the given `ttl`,
+  /// without running the command again. Invokes using a raw payload are never cached.
+  ///
+  /// Responses are only reused for the same window and origin, after the access control list allowed the invoke.
+  pub fn cache_command(&mut self, command: impl Into<String>, ttl: Duration) {
+    self.response_cache.enable(command.into(), ttl);
+  }
+
+  /// Marks the given command as idempotent.
+  ///
+  /// Invokes carrying an `Idempotency-Key` header already seen within the given `ttl` return the result
+  /// of the first execution instead of running the command again.
+  /// Retries arriving while the first execution is running wait for its result,
+  /// and are rejected if it runs for longer than the `ttl`.
+  ///
+  /// Keys are scoped to the window and origin, and only checked after the access control list allowed the invoke.
+  pub fn idempotent_command(&mut self, command: impl Into<String>, ttl: Duration) {
+    self.idempotency_cache.enable(command.into(), ttl);
+  }
+
+  /// Sets how deep arrays and objects can be nested in a command argument. Defaults to 128.
+  ///
+  /// Deeper arguments are rejected before being deserialized.
+  pub fn set_max_payload_depth(&mut self, max_depth: usize) {
+    self.max_payload_depth = max_depth;
+  }
+
+  pub(crate) fn max_payload_depth(&self) -> usize {
+    self.max_payload_depth
+  }
+
+  /// Sets the maximum size in bytes of the serialized response of the given command.
+  ///
+  /// Larger responses are not sent and the command is rejected with [`crate::Error::ResponseTooLarge`] instead.
+  pub fn set_max_response_bytes(&mut self, command: impl Into<String>, max_bytes: usize) {
+    self.max_response_bytes.insert(command.into(), max_bytes);
+  }
+
+  pub(crate) fn max_response_bytes(&self, command: &str) -> Option<usize> {
+    self.max_response_bytes.get(command).copied()
+  }
+
+  /// Adds a transform applied to the payload of every command before it reaches the command handler,
+  /// e.g. to decompress or decrypt it.
+  ///
+  /// Transforms are applied in the order they were added, each receiving the output of the previous one.
+  /// The first error rejects the command without running the remaining transforms.
+  pub fn add_payload_transform<F>(&mut self, transform: F)
+  where
+    F: Fn(InvokeBody) -> Result<InvokeBody, InvokeError> + Send + Sync + 'static,
+  {
+    self.payload_transforms.push(Box::new(transform));
+  }
+
+  pub(crate) fn transform_payload(&self, payload: InvokeBody) -> Result<InvokeBody, InvokeError> {
+    self
+      .payload_transforms
+      .iter()
+      .try_fold(payload, |payload, transform| transform(payload))
+  }
+
+  /// Registers the codec decoding raw payloads sent with the given content type, e.g. `application/msgpack`.
+  ///
+  /// The payload is decoded after the payload transforms, so its arguments are deserialized like a JSON payload.
+  /// Raw payloads of any other content type are left untouched.
+  pub fn register_payload_codec<C: PayloadCodec + 'static>(
+    &mut self,
+    content_type: impl Into<String>,
+    codec: C,
+  ) {
+    self
+      .payload_codecs
+      .insert(content_type.into(), Box::new(codec));
+  }
+
+  pub(crate) fn has_payload_codec(&self, content_type: &str) -> bool {
+    self.payload_codecs.contains_key(content_type)
+  }
+
+  pub(crate) fn decode_payload(
+    &self,
+    headers: &HeaderMap,
+    payload: InvokeBody,
+  ) -> Result<InvokeBody, InvokeError> {
+    let content_type = headers
+      .get(CONTENT_TYPE)
+      .and_then(|h| h.to_str().ok())
+      .and_then(|h| h.parse::<mime::Mime>().ok());
+    match (payload, content_type) {
+      (InvokeBody::Raw(bytes), Some(content_type)) => {
+        match self.payload_codecs.get(content_type.essence_str()) {
+          Some(codec) => codec.decode(&bytes).map(Into::into).map_err(|e| {
+            InvokeError::from(format!(
+              "failed to decode the {} payload: {e}",
+              content_type.essence_str()
+            ))
+          }),
+          None => Ok(InvokeBody::Raw(bytes)),
+        }
+      }
+      (payload, _) => Ok(payload),
+    }
+  }
+
+  /// Adds an interceptor run before every command passing the access control list, see [`CommandInterceptor`].
+  ///
+  /// Interceptors run in the order they were added, after every access check and before the payload transforms.
+  pub fn add_command_interceptor<I: CommandInterceptor + 'static>(&mut self, interceptor: I) {
+    self.interceptors.push(Box::new(interceptor));
+  }
+
+  pub(crate) fn intercept(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Result<(), InvokeError> {
+    self
+      .interceptors
+      .iter()
+      .try_for_each(|interceptor| interceptor.intercept(command, window, origin))
+  }
+
+  /// Returns the hit and miss counters of the typed scope caches.
+  #[cfg(feature = "metrics")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+  pub fn scope_cache_metrics(&self) -> ScopeCacheMetrics {
+    self.scope_manager.cache_metrics()
+  }
+
+  /// Drops the deserialized command and global scopes, see [`ScopeManager::clear_cache`].
+  pub fn clear_scope_cache(&self) {
+    self.scope_manager.clear_cache();
+  }
+
+  /// Sets the validator run on every allow and deny entry of the scopes read as `T`, see [`ScopeEntryValidator`].
+  ///
+  /// Reading a scope with a rejected entry fails with [`ScopeError::RejectedEntry`].
+  /// Scopes already deserialized are dropped so their entries are validated too.
+  pub fn set_scope_entry_validator<T: 'static, V: ScopeEntryValidator<T> + 'static>(
+    &mut self,
+    validator: V,
+  ) {
+    let validator: Box<dyn ScopeEntryValidator<T>> = Box::new(validator);
+    self
+      .scope_manager
+      .entry_validators
+      .insert(TypeId::of::<T>(), Box::new(validator));
+    self.scope_manager.clear_cache();
+  }
+
+  /// Deserializes the command scope as `T` ahead of its first access, see [`ScopeManager::prewarm`].
+  pub fn prewarm_scope<T: Send + Sync + DeserializeOwned + Debug + 'static>(
+    &self,
+    key: ScopeKey,
+  ) -> Result<(), ScopeError> {
+    self.scope_manager.prewarm::<T>(key)
+  }
+
+  /// Allows the arguments of the given command to fall back to the query parameters of the invoking URL
+  /// when they are missing from the payload. Only applies to remote origins.
+  ///
+  /// The query parameters go through the same depth, unknown field and `null` checks as the payload.
+  pub fn allow_query_fallback(&mut self, command: impl Into<String>) {
+    self.query_fallback_commands.insert(command.into());
+  }
+
+  pub(crate) fn query_fallback_enabled(&self, command: &str) -> bool {
+    self.query_fallback_commands.contains(command)
+  }
+
+  /// Lets the arguments of the given command match payload keys of the other case convention
+  /// when the exact key is missing, e.g. `my_param` also matches `myParam` and the other way around.
+  pub fn normalize_argument_keys(&mut self, command: impl Into<String>) {
+    self.key_normalization_commands.insert(command.into());
+  }
+
+  pub(crate) fn argument_key_normalization_enabled(&self, command: &str) -> bool {
+    self.key_normalization_commands.contains(command)
+  }
+
+  /// Rejects struct arguments of the given command whose payload object has keys
+  /// beyond the declared fields, like serde's `deny_unknown_fields`.
+  pub fn deny_unknown_argument_fields(&mut self, command: impl Into<String>) {
+    self.strict_field_commands.insert(command.into());
+  }
+
+  pub(crate) fn unknown_argument_fields_denied(&self, command: &str) -> bool {
+    self.strict_field_commands.contains(command)
+  }
+
+  /// Makes the `alias` scope key resolve to the same scope as `target`, sharing its cache entry.
+  ///
+  /// Any scope previously stored for `alias` is shadowed. Returns an error if the alias creates a cycle.
+  pub fn alias_scope(&mut self, alias: ScopeKey, target: ScopeKey) -> Result<(), ScopeAliasError> {
+    self.scope_manager.alias(alias, target)
+  }
+
+  /// Sets the hook asked for consent when [`CommandScope::is_allowed_with_consent`] rejects a path.
+  ///
+  /// When the hook grants access, the path is added to the command scope allow-list for the rest of the session.
+  /// Denied paths never reach the hook.
+  pub fn set_scope_consent_hook<F, Fut>(&mut self, hook: F)
+  where
+    F: Fn(PathBuf) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = bool> + Send + 'static,
+  {
+    self.scope_manager.consent_hook = Some(Box::new(move |path| Box::pin(hook(path))));
+  }
+
+  /// Sets the sink recording every path scope decision, see [`ScopeDecision`].
+  pub fn set_scope_audit_sink<F: Fn(&ScopeDecision) + Send + Sync + 'static>(&mut self, sink: F) {
+    self.scope_manager.audit_sink = Some(Box::new(sink));
+  }
+
+  /// Sets the recorder capturing the access decision of every invoke of the IPC, see [`DecisionRecord`].
+  ///
+  /// Queries such as [`Self::resolve_access`] are not recorded.
+  pub fn set_decision_recorder<F: Fn(&DecisionRecord) + Send + Sync + 'static>(
+    &mut self,
+    recorder: F,
+  ) {
+    self.decision_recorder = Some(Box::new(recorder));
+  }
+
+  /// Replays recorded decisions against this authority and reports those with a different outcome.
+  ///
+  /// Step-up requirements depend on the principal at the time of the call so they are not replayed:
+  /// decisions are replayed with a fresh step-up and recorded [`AccessError::StepUpRequired`] denials
+  /// only differ when the replayed invoke would not be dispatched.
+  /// Likewise the [`RateLimiter`] is not consulted for recorded [`AccessError::TooManyRequests`] denials.
+  pub fn replay(&self, records: &[DecisionRecord]) -> Vec<Divergence> {
+    let principal = Principal {
+      last_step_up: Some(Instant::now()),
+    };
+    records
+      .iter()
+      .filter_map(|record| {
+        let result = self.decide(&record.command, &record.window, &record.origin, &principal);
+        let diverged = match &record.outcome {
+          DecisionOutcome::Denied(AccessError::StepUpRequired | AccessError::TooManyRequests) => {
+            match &result {
+              Ok(_) => false,
+              Err(e) => !dispatched_without_entry(&record.command, e),
+            }
+          }
+          recorded => recorded != &DecisionOutcome::from(&result),
+        };
+        diverged.then(|| Divergence {
+          record: record.clone(),
+          replayed: DecisionOutcome::from(&result),
+        })
+      })
+      .collect()
+  }
+
+  /// Checks if the given IPC execution is allowed and returns the [`ResolvedCommand`] if it is.
+  ///
+  /// See [`Self::resolve_access_detailed`] for the reason of a denial.
+  pub fn resolve_access(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Option<&ResolvedCommand> {
+    self.resolve_access_detailed(command, window, origin).ok()
+  }
+
+  /// Same as [`Self::resolve_access`], but takes the origin by value.
+  #[deprecated(
+    since = "2.0.0-alpha.22",
+    note = "use `resolve_access`, which takes the origin by reference"
+  )]
+  pub fn resolve_access_owned(
+    &self,
+    command: &str,
+    window: &str,
+    origin: Origin,
+  ) -> Option<&ResolvedCommand> {
+    self.resolve_access(command, window, &origin)
+  }
+
+  /// Same as [`Self::resolve_access`], but tells why the access control list denied the command:
+  /// [`AccessError::Denied`], [`AccessError::OriginMismatch`], [`AccessError::WindowNotAllowed`]
+  /// or [`AccessError::CommandNotFound`].
+  ///
+  /// Commands marked with [`Self::require_step_up`] are denied, see [`Self::resolve_access_for_principal`].
+  pub fn resolve_access_detailed(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Result<&ResolvedCommand, AccessError> {
+    self.resolve_access_for_principal(command, window, origin, &Principal::default())
+  }
+
+  /// Same as [`Self::resolve_access_detailed`], but lets commands marked with [`Self::require_step_up`]
+  /// through when the principal completed a step-up authentication recently enough.
+  ///
+  /// The IPC uses the principal of the invoking window, see [`crate::Window::set_principal`].
+  pub fn resolve_access_for_principal(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+    principal: &Principal,
+  ) -> Result<&ResolvedCommand, AccessError> {
+    self.decide(command, window, origin, principal)
+  }
+
+  /// Authorizes an invoke of the IPC with the principal of the invoking window,
+  /// consulting the [`RateLimiter`] for the invokes the IPC dispatches and counting and recording the decision.
+  pub(crate) fn authorize_invoke(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+    principal: &Principal,
+  ) -> Result<&ResolvedCommand, AccessError> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::trace_span!(
+      "ipc::authorization",
+      command,
+      window,
+      origin = ?origin,
+      outcome = tracing::field::Empty
+    )
+    .entered();
+
+    let result = self.resolve_access_for_principal(command, window, origin, principal);
+    let dispatched = match &result {
+      Ok(_) => true,
+      Err(e) => dispatched_without_entry(command, e),
+    };
+    let result = match &self.rate_limiter {
+      Some(limiter) if dispatched && !limiter.check(command, window, origin) => {
+        Err(AccessError::TooManyRequests)
+      }
+      _ => result,
+    };
+    self.count_decision(command, &result);
+    self.record_decision(command, window, origin, &result);
+
+    #[cfg(feature = "tracing")]
+    match &result {
+      Ok(_) => span.record("outcome", "allowed"),
+      Err(e) => span.record(
+        "outcome",
+        tracing::field::display(format_args!("denied: {e}")),
+      ),
+    };
+
+    result
+  }
+
+  fn count_decision(&self, command: &str, result: &Result<&ResolvedCommand, AccessError>) {
+    #[cfg(feature = "metrics")]
+    {
+      // label with the matching access control list entry so the webview can't grow the metrics
+      let label = self
+        .allowed_index
+        .matches(command)
+        .into_iter()
+        .chain(self.denied_index.matches(command))
+        .min_by_key(|key| key.name != command)
+        .map_or(UNKNOWN_COMMAND_LABEL, |key| key.name.as_str());
+      let mut counts = self.decision_counts.lock().unwrap();
+      let counts = counts.entry(label.to_string()).or_default();
+      match result {
+        Ok(_) => counts.allowed += 1,
+        Err(AccessError::CommandNotFound) => counts.not_found += 1,
+        Err(_) => counts.denied += 1,
+      }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (command, result);
+  }
+
+  /// Renders the access decision counters and the scope cache metrics in the Prometheus text exposition format.
+  ///
+  /// Decisions are labeled with the access control list entry matching the command, e.g. `plugin:*:read`
+  /// for commands allowed by that pattern. Commands missing from the access control list share the `<unknown>` label.
+  #[cfg(feature = "prometheus")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "prometheus")))]
+  pub fn render_prometheus(&self) -> String {
+    use std::fmt::Write;
+
+    let escape = |label: &str| {
+      label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+    };
+
+    let mut out = String::new();
+    out.push_str(
+      "# HELP tauri_ipc_decisions_total Access decisions of the IPC by command and outcome.\n",
+    );
+    out.push_str("# TYPE tauri_ipc_decisions_total counter\n");
+    for (command, counts) in self.decision_counts.lock().unwrap().iter() {
+      let command = escape(command);
+      for (outcome, count) in [
+        ("allowed", counts.allowed),
+        ("denied", counts.denied),
+        ("not_found", counts.not_found),
+      ] {
+        let _ = writeln!(
+          out,
+          "tauri_ipc_decisions_total{{command=\"{command}\",outcome=\"{outcome}\"}} {count}"
+        );
+      }
+    }
+
+    let cache = self.scope_cache_metrics();
+    for (name, help, value) in [
+      (
+        "tauri_scope_cache_hits_total",
+        "Scope accesses served from the cache.",
+        cache.hits,
+      ),
+      (
+        "tauri_scope_cache_misses_total",
+        "Scope accesses that had to deserialize the scope.",
+        cache.misses,
+      ),
+    ] {
+      let _ = writeln!(out, "# HELP {name} {help}");
+      let _ = writeln!(out, "# TYPE {name} counter");
+      let _ = writeln!(out, "{name} {value}");
+    }
+    out
+  }
+
+  fn record_decision(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+    result: &Result<&ResolvedCommand, AccessError>,
+  ) {
+    if let Some(recorder) = &self.decision_recorder {
+      recorder(&DecisionRecord {
+        command: command.into(),
+        window: window.into(),
+        origin: origin.clone(),
+        outcome: result.into(),
+      });
+    }
+  }
+
+  /// Checks the access control list, the runtime toggles, the required features and the step-up requirement.
+  ///
+  /// The runtime requirements also apply to the commands the IPC dispatches without an access control list entry.
+  fn decide(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+    principal: &Principal,
+  ) -> Result<&ResolvedCommand, AccessError> {
+    let resolved = self.resolve_acl(command, window, origin);
+    if let Err(e) = &resolved {
+      if !dispatched_without_entry(command, e) {
+        return resolved;
+      }
+    }
+
+    if let Some(toggles) = &self.toggles {
+      if !toggles.is_on(command) {
+        return Err(AccessError::ToggledOff);
+      }
+    }
+
+    if let Some(feature) = self.required_features.get(command) {
+      if !self.features.is_enabled(feature) {
+        return Err(AccessError::FeatureUnavailable(feature.clone()));
+      }
+    }
+
+    if self.step_up_commands.contains(command) {
+      let fresh = principal
+        .last_step_up
+        .is_some_and(|at| at.elapsed() <= self.step_up_freshness);
+      if !fresh {
+        return Err(AccessError::StepUpRequired);
+      }
+    }
+
+    resolved
+  }
+
+  /// Checks the allowed and denied commands of the access control list.
+  fn resolve_acl(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Result<&ResolvedCommand, AccessError> {
+    if let Some(denied) = self
+      .denied_index
+      .matches(command)
+      .into_iter()
+      .find(|cmd| self.origin_matches(origin, &cmd.context))
+    {
+      return Err(match self.deny_reasons.get(denied) {
+        Some(reason) => AccessError::DeniedWithReason(reason.clone()),
+        None => AccessError::Denied,
+      });
+    }
+
+    let mut entries: Vec<&CommandKey> = self.allowed_index.matches(command).into_iter().collect();
+    if entries.is_empty() {
+      return Err(AccessError::CommandNotFound);
+    }
+    // exact names take precedence over wildcard patterns
+    entries.sort_by_key(|cmd| cmd.name != command);
+
+    // every entry matching the origin is tried before denying the window
+    let mut candidates = entries
+      .into_iter()
+      .filter(|cmd| self.origin_matches(origin, &cmd.context))
+      .filter_map(|cmd| self.allowed_commands.get(cmd))
+      .peekable();
+    if candidates.peek().is_none() {
+      return Err(AccessError::OriginMismatch);
+    }
+    candidates
+      .find(|allowed| allowed.windows.iter().any(|w| w.matches(window)))
+      .ok_or(AccessError::WindowNotAllowed)
+  }
+
+  /// Sets whether the reads of the command scope entries are recorded, see [`ScopeAccess`].
+  ///
+  /// Disabled by default since the records are kept until [`crate::test::take_scope_accesses`] is called.
+  #[cfg(any(test, feature = "test"))]
+  #[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+  pub fn record_scope_accesses(&mut self, enabled: bool) {
+    self.scope_manager.record_accesses = enabled;
+  }
+
+  /// Returns and clears the command scope reads recorded so far, see [`ScopeAccess`].
+  #[cfg(any(test, feature = "test"))]
+  pub(crate) fn take_scope_accesses(&self) -> Vec<ScopeAccess> {
+    std::mem::take(&mut *self.scope_manager.scope_accesses.lock().unwrap())
+  }
+
+  /// Sets whether the command arguments resolved from invoke payloads are recorded, see [`ArgumentAccess`].
+  ///
+  /// Disabled by default since the records are kept until [`crate::test::take_argument_accesses`] is called.
+  #[cfg(any(test, feature = "test"))]
+  #[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+  pub fn record_argument_accesses(&mut self, enabled: bool) {
+    self.record_argument_accesses = enabled;
+  }
+
+  #[cfg(any(test, feature = "test"))]
+  pub(crate) fn record_argument_access(&self, command: &str, key: &str) {
+    if !self.record_argument_accesses {
+      return;
+    }
+    self.argument_accesses.lock().unwrap().push(ArgumentAccess {
+      command: command.into(),
+      key: key.into(),
+    });
+  }
+
+  /// Returns and clears the command arguments read so far, see [`ArgumentAccess`].
+  #[cfg(any(test, feature = "test"))]
+  pub(crate) fn take_argument_accesses(&self) -> Vec<ArgumentAccess> {
+    std::mem::take(&mut *self.argument_accesses.lock().unwrap())
+  }
+
+  /// Registers a handler for the given command, executed by [`Self::dispatch_for_test`].
+  #[cfg(any(test, feature = "test"))]
+  #[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+  pub fn register_test_handler<F>(&mut self, command: impl Into<String>, handler: F)
+  where
+    F: Fn(InvokeBody) -> Result<InvokeBody, InvokeError> + Send + Sync + 'static,
+  {
+    self.test_handlers.insert(command.into(), Box::new(handler));
+  }
+
+  /// Runs the authorization of an invoke and its test handler without a window,
+  /// returning what the frontend would receive.
+  ///
+  /// Like the IPC, the access control list is enforced for plugin commands.
+  #[cfg(any(test, feature = "test"))]
+  #[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+  pub fn dispatch_for_test(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+    payload: InvokeBody,
+  ) -> Result<InvokeBody, InvokeError> {
+    match self
+      .resolve_access_detailed(command, window, origin)
+      .map_err(AccessError::coarse)
+    {
+      Ok(_) => (),
+      Err(AccessError::Denied) => return Err("NOT ALLOWED".into()),
+      Err(AccessError::NotAllowed) if command.starts_with("plugin:") => {
+        return Err("NOT ALLOWED".into())
+      }
+      Err(AccessError::NotAllowed) => (),
+      Err(e) => return Err(e.to_string().into()),
+    }
+
+    let handler = self
+      .test_handlers
+      .get(command)
+      .ok_or_else(|| InvokeError::from(format!("Command {command} not found")))?;
+    handler(payload)
+  }
+
+  /// Registers the schema of a command, exposed to clients with [`Self::schema_for_origin`].
+  pub fn register_command_schema(&mut self, schema: CommandSchema) {
+    self.command_schemas.insert(schema.name.clone(), schema);
+  }
+
+  /// Returns the schemas of the registered commands the given window and origin are allowed to call.
+  ///
+  /// Commands requiring a step-up authentication are included since they are callable after one.
+  pub fn schema_for_origin(&self, window: &str, origin: &Origin) -> Vec<CommandSchema> {
+    self
+      .command_schemas
+      .values()
+      .filter(|schema| self.resolve_acl(&schema.name, window, origin).is_ok())
+      .cloned()
+      .collect()
+  }
+}
+
+/// Whether the command belongs to a plugin, which the IPC only dispatches when the access control list allows it.
+fn is_plugin_command(command: &str) -> bool {
+  command.starts_with("plugin:") && command != crate::ipc::channel::FETCH_CHANNEL_DATA_COMMAND
+}
+
+/// Whether the IPC dispatches the command even though the access control list did not allow it,
+/// which is the case of app commands.
+fn dispatched_without_entry(command: &str, error: &AccessError) -> bool {
+  !is_plugin_command(command) && error.clone().coarse() == AccessError::NotAllowed
+}
+
+/// The read-only queries of an authority, to write tooling generic over the authority implementation.
+///
+/// The queries don't consult the [`RateLimiter`] and don't count or record the decisions,
+/// only the invokes of the IPC do.
+///
+/// Implemented by [`RuntimeAuthority`], the trait is object safe so it can be used as `&dyn Authority`.
+pub trait Authority {
+  /// Checks if the given IPC execution is allowed and returns the [`ResolvedCommand`] if it is.
+  ///
+  /// See [`RuntimeAuthority::resolve_access`].
+  fn resolve_access(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Option<&ResolvedCommand>;
+
+  /// Same as [`Self::resolve_access`], but tells why the access control list denied the command.
+  ///
+  /// See [`RuntimeAuthority::resolve_access_detailed`].
+  fn resolve_access_detailed(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Result<&ResolvedCommand, AccessError>;
+
+  /// Whether the given IPC execution is allowed.
+  fn is_allowed(&self, command: &str, window: &str, origin: &Origin) -> bool {
+    self.resolve_access(command, window, origin).is_some()
+  }
+
+  /// Iterates over the allowed commands of the access control list.
+  fn allowed_commands(&self) -> Box<dyn Iterator<Item = (&CommandKey, &ResolvedCommand)> + '_>;
+
+  /// Iterates over the denied commands of the access control list.
+  fn denied_commands(&self) -> Box<dyn Iterator<Item = (&CommandKey, &ResolvedCommand)> + '_>;
+
+  /// Returns the names of the allowed commands the access control list lets the window and origin call, sorted.
+  ///
+  /// Runtime requirements such as step-up authentication or feature toggles are not checked.
+  fn allowed_commands_for_window(&self, window: &str, origin: &Origin) -> Vec<&str>;
+
+  /// Returns the schemas of the registered commands the given window and origin are allowed to call.
+  ///
+  /// See [`RuntimeAuthority::schema_for_origin`].
+  fn schema_for_origin(&self, window: &str, origin: &Origin) -> Vec<CommandSchema>;
+
+  /// Checks the access control list for likely mistakes.
+  ///
+  /// See [`RuntimeAuthority::validate`].
+  fn validate(&self) -> Vec<AclWarning>;
+}
+
+impl Authority for RuntimeAuthority {
+  fn resolve_access(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Option<&ResolvedCommand> {
+    RuntimeAuthority::resolve_access(self, command, window, origin)
+  }
+
+  fn resolve_access_detailed(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+  ) -> Result<&ResolvedCommand, AccessError> {
+    RuntimeAuthority::resolve_access_detailed(self, command, window, origin)
+  }
+
+  fn allowed_commands(&self) -> Box<dyn Iterator<Item = (&CommandKey, &ResolvedCommand)> + '_> {
+    Box::new(self.allowed_commands_iter())
+  }
+
+  fn denied_commands(&self) -> Box<dyn Iterator<Item = (&CommandKey, &ResolvedCommand)> + '_> {
+    Box::new(self.denied_commands_iter())
+  }
+
+  fn allowed_commands_for_window(&self, window: &str, origin: &Origin) -> Vec<&str> {
+    self
+      .allowed_commands
+      .keys()
+      .map(|key| key.name.as_str())
+      .filter(|name| self.resolve_acl(name, window, origin).is_ok())
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect()
+  }
+
+  fn schema_for_origin(&self, window: &str, origin: &Origin) -> Vec<CommandSchema> {
+    RuntimeAuthority::schema_for_origin(self, window, origin)
+  }
+
+  fn validate(&self) -> Vec<AclWarning> {
+    RuntimeAuthority::validate(self)
+  }
+}
+
+#[derive(Debug)]
+struct ScopeValue<T: Debug + DeserializeOwned + Send + Sync + 'static> {
+  allow: Vec<T>,
+  deny: Vec<T>,
+}
+
+/// Shared with the scope cache of the [`RuntimeAuthority`], or owned by a scope built with `from_values`.
+#[derive(Debug)]
+enum ScopeRef<T> {
+  Shared(Arc<T>),
+  Owned(Box<T>),
+}
+
+impl<T> std::ops::Deref for ScopeRef<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    match self {
+      Self::Shared(value) => value,
+      Self::Owned(value) => value,
+    }
+  }
+}
+
+/// Access scope for a command that can be retrieved directly in the command function.
+#[derive(Debug)]
+pub struct CommandScope<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> {
+  value: ScopeRef<ScopeValue<T>>,
+  /// The key of the scope and its manager, `None` for scopes built with [`Self::from_values`].
+  source: Option<(ScopeKey, &'a ScopeManager)>,
+}
+
+impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandScope<'a, T> {
+  /// Creates a scope from the given entries instead of the access control list,
+  /// e.g. for scopes computed at runtime or to unit test the logic consuming a scope.
+  ///
+  /// The scope has no session grants, consent hook nor audit sink of the [`RuntimeAuthority`]
+  /// and its accesses are not recorded.
+  pub fn from_values(allow: Vec<T>, deny: Vec<T>) -> Self {
+    Self {
+      value: ScopeRef::Owned(Box::new(ScopeValue { allow, deny })),
+      source: None,
+    }
+  }
+
+  /// What this access scope allows.
+  pub fn allows(&self) -> &Vec<T> {
+    #[cfg(any(test, feature = "test"))]
+    if let Some((key, manager)) = self.source {
+      manager.record_access(key, ScopeAccessKind::Allows);
+    }
+    &self.value.allow
+  }
+
+  /// What this access scope denies.
+  pub fn denies(&self) -> &Vec<T> {
+    #[cfg(any(test, feature = "test"))]
+    if let Some((key, manager)) = self.source {
+      manager.record_access(key, ScopeAccessKind::Denies);
+    }
+    &self.value.deny
+  }
+
+  /// Checks if the query is permitted by this scope, using [`ScopeMatch`] to match the entries.
+  ///
+  /// Returns `false` if any deny entry matches, otherwise `true` only if some allow entry matches.
+  pub fn permits<Q: ?Sized>(&self, query: &Q) -> bool
+  where
+    T: ScopeMatch<Q>,
+  {
+    self.permits_with(query, T::matches)
+  }
+
+  /// Same as [`Self::permits`], but matches the entries with the given closure.
+  pub fn permits_with<Q: ?Sized, F: Fn(&T, &Q) -> bool>(&self, query: &Q, matches: F) -> bool {
+    !self.denies().iter().any(|entry| matches(entry, query))
+      && self.allows().iter().any(|entry| matches(entry, query))
+  }
+}
+
+/// Defines how a scope entry matches a query, see [`CommandScope::permits`].
+pub trait ScopeMatch<Q: ?Sized> {
+  /// Whether this entry matches the query.
+  fn matches(&self, query: &Q) -> bool;
+}
+
+/// A path entry of a command scope.
+///
+/// A path is in scope if it is equal to or nested under the entry path.
+/// Paths are compared lexically, so paths with `..` components are never in scope.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PathScope {
+  /// The path of the entry.
+  pub path: PathBuf,
+}
+
+impl PathScope {
+  fn contains(&self, path: &Path) -> bool {
+    !escapes(path) && path.starts_with(&self.path)
+  }
+}
+
+/// Whether the path has `..` components, which could escape a lexically matched scope entry.
+fn escapes(path: &Path) -> bool {
+  path
+    .components()
+    .any(|component| component == Component::ParentDir)
+}
+
+/// A string entry of a command scope, used to validate [`ScopedString`](super::ScopedString).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StringScope {
+  /// The string value.
+  pub value: String,
+}
+
+impl ScopeMatch<str> for StringScope {
+  fn matches(&self, query: &str) -> bool {
+    self.value == query
+  }
+}
+
+/// A scope decision recorded by the sink set with [`RuntimeAuthority::set_scope_audit_sink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeDecision {
+  /// The command scope the path was checked against, or `None` for the global scope.
+  pub scope: Option<ScopeKey>,
+  /// The checked path.
+  pub path: PathBuf,
+  /// Whether the path was allowed.
+  pub allowed: bool,
+}
+
+impl<'a> CommandScope<'a, PathScope> {
+  /// Checks the scope entries and the session grants, without asking for consent nor recording the decision.
+  fn matches_path(&self, path: &Path) -> bool {
+    !self.denies_path(path) && self.grants_path(path)
+  }
+
+  /// Whether a deny entry matches the path.
+  ///
+  /// Reads the entries directly so the checks are not recorded as scope accesses of the command.
+  fn denies_path(&self, path: &Path) -> bool {
+    escapes(path) || self.value.deny.iter().any(|entry| entry.contains(path))
+  }
+
+  /// Whether an allow entry or a session grant matches the path.
+  fn grants_path(&self, path: &Path) -> bool {
+    self.value.allow.iter().any(|entry| entry.contains(path))
+      || self.source.is_some_and(|(key, manager)| {
+        manager
+          .session_grants
+          .lock()
+          .unwrap()
+          .get(&key)
+          .is_some_and(|granted| granted.iter().any(|p| path.starts_with(p)))
+      })
+  }
+
+  fn record(&self, path: &Path, allowed: bool) {
+    if let Some((key, manager)) = self.source {
+      manager.record(Some(key), path, allowed);
+    }
+  }
+
+  /// Checks if the path is allowed by this scope or was granted during this session, without asking for consent.
+  ///
+  /// See [`Self::is_allowed_with_consent`] to ask the consent hook for the paths this scope does not allow.
+  pub fn is_allowed(&self, path: &Path) -> bool {
+    let allowed = self.matches_path(path);
+    self.record(path, allowed);
+    allowed
+  }
+
+  /// Same as [`Self::is_allowed`], but if the path is not allowed nor explicitly denied, the consent hook set with
+  /// [`RuntimeAuthority::set_scope_consent_hook`] is asked to grant access for the rest of the session.
+  pub async fn is_allowed_with_consent(&self, path: &Path) -> bool {
+    let allowed = self.check_with_consent(path).await;
+    self.record(path, allowed);
+    allowed
+  }
+
+  async fn check_with_consent(&self, path: &Path) -> bool {
+    if self.denies_path(path) {
+      return false;
+    }
+    if self.grants_path(path) {
+      return true;
+    }
+    let Some((key, manager)) = self.source else {
+      return false;
+    };
+    let Some(hook) = &manager.consent_hook else {
+      return false;
+    };
+    let granted = hook(path.to_path_buf()).await;
+    if granted {
+      manager
+        .session_grants
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_default()
+        .push(path.to_path_buf());
+    }
+    granted
+  }
+}
+
+impl<'a, R: Runtime, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandArg<'a, R>
+  for CommandScope<'a, T>
+{
+  /// Grabs the [`ResolvedScope`] from the [`CommandItem`] and returns the associated [`CommandScope`].
+  fn from_command(command: CommandItem<'a, R>) -> Result<Self, InvokeError> {
+    let scope_id = command
+      .acl
+      .as_ref()
+      .and_then(|resolved| resolved.scope)
+      .ok_or_else(|| InvokeError::from_anyhow(anyhow::anyhow!("scope not found")))?;
+    command
+      .message
+      .window
+      .manager
+      .runtime_authority
+      .scope_manager
+      .command_scope(scope_id)
+      .map_err(InvokeError::from_error)
+  }
+}
+
+/// A [`CommandScope`] for commands that can run without a scope configured.
+///
+/// `Option<CommandScope<T>>` cannot be a command argument because of the implementation for deserializable types.
+#[derive(Debug)]
+pub struct OptionalCommandScope<'a, T: Debug + DeserializeOwned + Send + Sync + 'static>(
+  pub Option<CommandScope<'a, T>>,
+);
+
+impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> OptionalCommandScope<'a, T> {
+  /// Returns the scope, or `None` if the command has no scope configured.
+  pub fn into_inner(self) -> Option<CommandScope<'a, T>> {
+    self.0
+  }
+}
+
+impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> std::ops::Deref
+  for OptionalCommandScope<'a, T>
+{
+  type Target = Option<CommandScope<'a, T>>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl<'a, R: Runtime, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandArg<'a, R>
+  for OptionalCommandScope<'a, T>
+{
+  /// Returns `None` if the command has no scope configured, the [`CommandScope`] otherwise.
+  fn from_command(command: CommandItem<'a, R>) -> Result<Self, InvokeError> {
+    match command.acl.as_ref().and_then(|resolved| resolved.scope) {
+      Some(_) => CommandScope::from_command(command).map(|scope| Self(Some(scope))),
+      None => Ok(Self(None)),
+    }
+  }
+}
+
+/// Global access scope that can be retrieved directly in the command function.
+#[derive(Debug)]
+pub struct GlobalScope<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> {
+  value: ScopeRef<ScopeValue<T>>,
+  /// `None` for scopes built with [`Self::from_values`].
+  manager: Option<&'a ScopeManager>,
+}
+
+impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> GlobalScope<'a, T> {
+  /// Creates a global scope from the given entries instead of the access control list,
+  /// see [`CommandScope::from_values`].
+  pub fn from_values(allow: Vec<T>, deny: Vec<T>) -> Self {
+    Self {
+      value: ScopeRef::Owned(Box::new(ScopeValue { allow, deny })),
+      manager: None,
+    }
+  }
+
+  /// What this access scope allows.
+  pub fn allows(&self) -> &Vec<T> {
+    &self.value.allow
+  }
+
+  /// What this access scope denies.
+  pub fn denies(&self) -> &Vec<T> {
+    &self.value.deny
+  }
+}
+
+impl<'a> GlobalScope<'a, PathScope> {
+  /// Checks if the path is allowed by this scope.
+  pub fn is_allowed(&self, path: &Path) -> bool {
+    let allowed = !self.denies().iter().any(|entry| entry.contains(path))
+      && self.allows().iter().any(|entry| entry.contains(path));
+    if let Some(manager) = self.manager {
+      manager.record(None, path, allowed);
+    }
+    allowed
+  }
+}
+
+impl<'a, R: Runtime, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandArg<'a, R>
+  for GlobalScope<'a, T>
+{
+  /// Grabs the [`ResolvedScope`] from the [`CommandItem`] and returns the associated [`GlobalScope`].
+  fn from_command(command: CommandItem<'a, R>) -> Result<Self, InvokeError> {
+    let manager = &command
+      .message
+      .window
+      .manager
+      .runtime_authority
+      .scope_manager;
+    Ok(GlobalScope {
+      value: ScopeRef::Shared(
+        manager
+          .get_global_scope_typed()
+          .map_err(InvokeError::from_error)?,
+      ),
+      manager: Some(manager),
+    })
+  }
+}
+
+/// A [`GlobalScope`] for commands that can run without a global scope configured.
+#[derive(Debug)]
+pub struct OptionalGlobalScope<'a, T: Debug + DeserializeOwned + Send + Sync + 'static>(
+  pub Option<GlobalScope<'a, T>>,
+);
+
+impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> OptionalGlobalScope<'a, T> {
+  /// Returns the scope, or `None` if the global scope has no entries.
+  pub fn into_inner(self) -> Option<GlobalScope<'a, T>> {
+    self.0
+  }
+}
+
+impl<'a, T: Debug + DeserializeOwned + Send + Sync + 'static> std::ops::Deref
+  for OptionalGlobalScope<'a, T>
+{
+  type Target = Option<GlobalScope<'a, T>>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl<'a, R: Runtime, T: Debug + DeserializeOwned + Send + Sync + 'static> CommandArg<'a, R>
+  for OptionalGlobalScope<'a, T>
+{
+  /// Returns `None` if the global scope has no entries, the [`GlobalScope`] otherwise.
+  fn from_command(command: CommandItem<'a, R>) -> Result<Self, InvokeError> {
+    let global_scope = &command
+      .message
+      .window
+      .manager
+      .runtime_authority
+      .scope_manager
+      .global_scope;
+    if global_scope.allow.is_empty() && global_scope.deny.is_empty() {
+      Ok(Self(None))
+    } else {
+      GlobalScope::from_command(command).map(|scope| Self(Some(scope)))
+    }
+  }
+}
+
+/// The hook asked for consent when a path is not allowed by a command scope.
+type ScopeConsentHook =
+  Box<dyn Fn(PathBuf) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync>;
+
+/// The sink recording every scope decision.
+type ScopeAuditSink = Box<dyn Fn(&ScopeDecision) + Send + Sync>;
+
+pub struct ScopeManager {
+  command_scope: BTreeMap<ScopeKey, ResolvedScope>,
+  global_scope: ResolvedScope,
+  /// The `Arc<ScopeValue<T>>` of each command scope, cleared at runtime by [`Self::clear_cache`].
+  command_cache: RwLock<BTreeMap<ScopeKey, TypeMap![Send + Sync]>>,
+  global_scope_cache: RwLock<TypeMap![Send + Sync]>,
+  aliases: BTreeMap<ScopeKey, ScopeKey>,
+  consent_hook: Option<ScopeConsentHook>,
+  session_grants: Mutex<BTreeMap<ScopeKey, Vec<PathBuf>>>,
+  audit_sink: Option<ScopeAuditSink>,
+  scope_types: Mutex<BTreeMap<ScopeKey, (TypeId, &'static str)>>,
+  /// The `Box<dyn ScopeEntryValidator<T>>` of each entry type.
+  entry_validators: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+  #[cfg(feature = "metrics")]
+  cache_hits: AtomicU64,
+  #[cfg(feature = "metrics")]
+  cache_misses: AtomicU64,
+  #[cfg(any(test, feature = "test"))]
+  record_accesses: bool,
+  #[cfg(any(test, feature = "test"))]
+  scope_accesses: Mutex<Vec<ScopeAccess>>,
+}
+
+/// Which entries of a command scope were read, see [`ScopeAccess`].
+#[cfg(any(test, feature = "test"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeAccessKind {
+  /// [`CommandScope::allows`] was called.
+  Allows,
+  /// [`CommandScope::denies`] was called.
+  Denies,
+}
+
+/// A read of the entries of a command scope, recorded for tests
+/// once enabled with [`RuntimeAuthority::record_scope_accesses`].
+///
+/// Retrieve them with [`crate::test::take_scope_accesses`].
+#[cfg(any(test, feature = "test"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeAccess {
+  /// The key of the command scope.
+  pub scope: ScopeKey,
+  /// Which entries were read.
+  pub kind: ScopeAccessKind,
+  /// The entries as configured in the access control list.
+  pub values: Vec<tauri_utils::acl::Value>,
+}
+
+/// A command argument resolved from the invoke payload, recorded for tests
+/// once enabled with [`RuntimeAuthority::record_argument_accesses`].
+///
+/// Only keys present in the payload are recorded, so comparing them with the keys sent by the
+/// frontend finds arguments that are sent but never read. Retrieve them with [`crate::test::take_argument_accesses`].
+#[cfg(any(test, feature = "test"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentAccess {
+  /// The name of the command.
+  pub command: String,
+  /// The key of the argument.
+  pub key: String,
+}
+
+/// Label used for the metrics of commands that are not part of the access control list.
+#[cfg(feature = "metrics")]
+const UNKNOWN_COMMAND_LABEL: &str = "<unknown>";
+
+/// Outcome counters of the access decisions of a command.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone, Copy)]
+struct DecisionCounts {
+  allowed: u64,
+  denied: u64,
+  not_found: u64,
+}
+
+/// Hit and miss counters of the typed scope caches.
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScopeCacheMetrics {
+  /// Number of scope accesses served from the cache.
+  pub hits: u64,
+  /// Number of scope accesses that had to deserialize the scope.
+  pub misses: u64,
+}
+
+impl Debug for ScopeManager {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ScopeManager")
+      .field("command_scope", &self.command_scope)
+      .field("global_scope", &self.global_scope)
+      .field("command_cache", &self.command_cache)
+      .field("global_scope_cache", &self.global_scope_cache)
+      .field("aliases", &self.aliases)
+      .field("session_grants", &self.session_grants)
+      .finish_non_exhaustive()
+  }
+}
+
+impl ScopeManager {
+  fn new(command_scope: BTreeMap<ScopeKey, ResolvedScope>, global_scope: ResolvedScope) -> Self {
+    let command_cache = command_scope
+      .keys()
+      .map(|key| (*key, <TypeMap![Send + Sync]>::new()))
+      .collect::<BTreeMap<_, _>>()
+      .into();
+    Self {
+      command_scope,
+      global_scope,
+      command_cache,
+      global_scope_cache: Default::default(),
+      aliases: Default::default(),
+      consent_hook: None,
+      session_grants: Default::default(),
+      audit_sink: None,
+      scope_types: Default::default(),
+      entry_validators: Default::default(),
+      #[cfg(feature = "metrics")]
+      cache_hits: Default::default(),
+      #[cfg(feature = "metrics")]
+      cache_misses: Default::default(),
+      #[cfg(any(test, feature = "test"))]
+      record_accesses: false,
+      #[cfg(any(test, feature = "test"))]
+      scope_accesses: Default::default(),
+    }
+  }
+
+  #[cfg(any(test, feature = "test"))]
+  fn record_access(&self, scope: ScopeKey, kind: ScopeAccessKind) {
+    if !self.record_accesses {
+      return;
+    }
+    let values = self
+      .command_scope
+      .get(&scope)
+      .map(|resolved| match kind {
+        ScopeAccessKind::Allows => resolved.allow.clone(),
+        ScopeAccessKind::Denies => resolved.deny.clone(),
+      })
+      .unwrap_or_default();
+    self.scope_accesses.lock().unwrap().push(ScopeAccess {
+      scope,
+      kind,
+      values,
+    });
+  }
+
+  /// Records the scope decision in the audit sink, if any.
+  fn record(&self, scope: Option<ScopeKey>, path: &Path, allowed: bool) {
+    if let Some(sink) = &self.audit_sink {
+      sink(&ScopeDecision {
+        scope,
+        path: path.to_path_buf(),
+        allowed,
+      });
+    }
+  }
+
+  /// Returns the hit and miss counters of the typed scope caches.
+  #[cfg(feature = "metrics")]
+  pub fn cache_metrics(&self) -> ScopeCacheMetrics {
+    ScopeCacheMetrics {
+      hits: self.cache_hits.load(Ordering::Relaxed),
+      misses: self.cache_misses.load(Ordering::Relaxed),
+    }
+  }
+
+  /// Drops every deserialized scope so the next access deserializes it again,
+  /// e.g. after the access control list changed.
+  ///
+  /// The type each scope was first requested as is forgotten too.
+  /// Scopes already handed to running commands are kept alive until they are dropped.
+  pub fn clear_cache(&self) {
+    for cache in self.command_cache.write().unwrap().values_mut() {
+      *cache = <TypeMap![Send + Sync]>::new();
+    }
+    *self.global_scope_cache.write().unwrap() = <TypeMap![Send + Sync]>::new();
+    self.scope_types.lock().unwrap().clear();
+  }
+
+  /// Deserializes the command scope as `T` ahead of its first access.
+  ///
+  /// Each scope has its own cache, so prewarming a scope leaves the cached values of the other scopes untouched.
+  pub fn prewarm<T: Send + Sync + DeserializeOwned + Debug + 'static>(
+    &self,
+    key: ScopeKey,
+  ) -> Result<(), ScopeError> {
+    self.get_command_scope_typed::<T>(&key).map(|_| ())
+  }
+
+  fn record_cache_access(&self, hit: bool) {
+    #[cfg(feature = "metrics")]
+    if hit {
+      self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    } else {
+      self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = hit;
+  }
+
+  fn command_scope<T: Send + Sync + DeserializeOwned + Debug + 'static>(
+    &self,
+    key: ScopeKey,
+  ) -> Result<CommandScope<'_, T>, ScopeError> {
+    let key = self.canonical_key(key);
+    self
+      .get_command_scope_typed(&key)
+      .map(|value| CommandScope {
+        value: ScopeRef::Shared(value),
+        source: Some((key, self)),
+      })
+  }
+
+  fn alias(&mut self, alias: ScopeKey, target: ScopeKey) -> Result<(), ScopeAliasError> {
+    if self.canonical_key(target) == alias {
+      return Err(ScopeAliasError::Cycle { alias, target });
+    }
+    self.aliases.insert(alias, target);
+    Ok(())
+  }
+
+  /// Follows the aliases of the given scope key. Cycles are rejected by [`Self::alias`].
+  fn canonical_key(&self, key: ScopeKey) -> ScopeKey {
+    let mut key = key;
+    while let Some(target) = self.aliases.get(&key) {
+      key = *target;
+    }
+    key
+  }
+
+  fn get_global_scope_typed<T: Send + Sync + DeserializeOwned + Debug + 'static>(
+    &self,
+  ) -> Result<Arc<ScopeValue<T>>, ScopeError> {
+    let cache = self.global_scope_cache.read().unwrap();
+    let cached = cache.try_get::<Arc<ScopeValue<T>>>();
+    self.record_cache_access(cached.is_some());
+    match cached {
+      Some(cached) => Ok(cached.clone()),
+      None => {
+        let scope = deserialize_scope::<T>(None, &self.global_scope, self.entry_validator())?;
+        let _ = cache.set(Arc::new(scope));
+        Ok(cache.get::<Arc<ScopeValue<T>>>().clone())
+      }
+    }
+  }
+
+  fn entry_validator<T: 'static>(&self) -> Option<&dyn ScopeEntryValidator<T>> {
+    self
+      .entry_validators
+      .get(&TypeId::of::<T>())
+      .and_then(|validator| validator.downcast_ref::<Box<dyn ScopeEntryValidator<T>>>())
+      .map(|validator| validator.as_ref())
+  }
+
+  /// Records the first type requested for the scope and rejects requests with a different type.
+  fn check_scope_type<T: 'static>(&self, key: ScopeKey) -> Result<(), ScopeError> {
+    let requested = (TypeId::of::<T>(), std::any::type_name::<T>());
+    let mut scope_types = self.scope_types.lock().unwrap();
+    let (first, first_name) = *scope_types.entry(key).or_insert(requested);
+    if first == requested.0 {
+      Ok(())
+    } else {
+      Err(ScopeError::TypeMismatch {
+        scope: key,
+        first: first_name,
+        requested: requested.1,
+      })
+    }
+  }
+
+  fn get_command_scope_typed<T: Send + Sync + DeserializeOwned + Debug + 'static>(
+    &self,
+    key: &ScopeKey,
+  ) -> Result<Arc<ScopeValue<T>>, ScopeError> {
+    let key = self.canonical_key(*key);
+    let command_cache = self.command_cache.read().unwrap();
+    let (Some(cache), Some(resolved)) = (command_cache.get(&key), self.command_scope.get(&key))
+    else {
+      return Err(ScopeError::NotFound(key));
+    };
+    self.check_scope_type::<T>(key)?;
+    let cached = cache.try_get::<Arc<ScopeValue<T>>>();
+    self.record_cache_access(cached.is_some());
+    match cached {
+      Some(cached) => Ok(cached.clone()),
+      None => {
+        let _ = cache.set(Arc::new(deserialize_scope::<T>(
+          Some(key),
+          resolved,
+          self.entry_validator(),
+        )?));
+        Ok(cache.get::<Arc<ScopeValue<T>>>().clone())
+      }
+    }
+  }
+}
+
+/// Deserializes and validates every entry of the scope,
+/// failing on the first entry that does not match `T` or is rejected by the validator.
+fn deserialize_scope<T: DeserializeOwned + Debug + Send + Sync + 'static>(
+  scope: Option<ScopeKey>,
+  resolved: &ResolvedScope,
+  validator: Option<&dyn ScopeEntryValidator<T>>,
+) -> Result<ScopeValue<T>, ScopeError> {
+  let entries = |values: &[tauri_utils::acl::Value]| {
+    values
+      .iter()
+      .map(|value| {
+        let entry = value
+          .deserialize()
+          .ok_or_else(|| ScopeError::InvalidEntry {
+            scope,
+            entry: serde_json::to_string(value).unwrap_or_default(),
+            type_name: std::any::type_name::<T>(),
+          })?;
+        if let Some(validator) = validator {
+          validator
+            .validate(&entry)
+            .map_err(|reason| ScopeError::RejectedEntry {
+              scope,
+              entry: serde_json::to_string(value).unwrap_or_default(),
+              reason,
+            })?;
+        }
+        Ok(entry)
+      })
+      .collect::<Result<Vec<T>, _>>()
+  };
+  Ok(ScopeValue {
+    allow: entries(&resolved.allow)?,
+    deny: entries(&resolved.deny)?,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use glob::Pattern;
+  use tauri_utils::acl::{
+    resolved::{CommandKey, Resolved, ResolvedCommand, ResolvedScope},
+    ExecutionContext, Value,
+  };
+
+  use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+  };
+
+  use crate::command::Origin;
+
+  use super::{
+    AccessError, AclWarning, Authority, AuthorityBuildError, CommandSchema, DecisionOutcome,
+    DecisionRecord, Divergence, GlobalScope, InstallError, MergeError, OriginMatcher, PathScope,
+    PluginAcl, Principal, RateLimiter, RuntimeAuthority, RuntimeAuthorityBuilder, ScopeAliasError,
+    ScopeDecision, ScopeError, ScopeRef, StringScope, ToggleStore,
+  };
+
+  #[test]
+  fn window_glob_pattern_matches() {
+    let command = CommandKey {
+      name: "my-command".into(),
+      context: ExecutionContext::Local,
+    };
+    let window = "main-*";
+
+    let resolved_cmd = ResolvedCommand {
+      windows: vec![Pattern::new(window).unwrap()],
+      scope: None,
+    };
+    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
+      .into_iter()
+      .collect();
+
+    let authority = RuntimeAuthority::new(Resolved {
+      allowed_commands,
+      denied_commands: Default::default(),
+      command_scope: Default::default(),
+      global_scope: Default::default(),
+    });
+
+    assert_eq!(
+      authority.resolve_access(
+        &command.name,
+        &window.replace('*', "something"),
+        &Origin::Local
+      ),
+      Some(&resolved_cmd)
+    );
+  }
+
+  #[test]
+  fn remote_domain_matches() {
+    let domain = "tauri.app";
+    let command = CommandKey {
+      name: "my-command".into(),
+      context: ExecutionContext::Remote {
+        domain: Pattern::new(domain).unwrap(),
+      },
+    };
+    let window = "main";
+
+    let resolved_cmd = ResolvedCommand {
+      windows: vec![Pattern::new(window).unwrap()],
+      scope: None,
+    };
+    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
+      .into_iter()
+      .collect();
+
+    let authority = RuntimeAuthority::new(Resolved {
+      allowed_commands,
+      denied_commands: Default::default(),
+      command_scope: Default::default(),
+      global_scope: Default::default(),
+    });
+
+    assert_eq!(
+      authority.resolve_access(
+        &command.name,
+        window,
+        &Origin::Remote {
+          domain: domain.into()
+        }
+      ),
+      Some(&resolved_cmd)
+    );
+  }
+
+  #[test]
+  fn remote_domain_glob_pattern_matches() {
+    let domain = "tauri.*";
+    let command = CommandKey {
+      name: "my-command".into(),
+      context: ExecutionContext::Remote {
+        domain: Pattern::new(domain).unwrap(),
+      },
+    };
+    let window = "main";
+
+    let resolved_cmd = ResolvedCommand {
+      windows: vec![Pattern::new(window).unwrap()],
+      scope: None,
+    };
+    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
+      .into_iter()
+      .collect();
+
+    let authority = RuntimeAuthority::new(Resolved {
+      allowed_commands,
+      denied_commands: Default::default(),
+      command_scope: Default::default(),
+      global_scope: Default::default(),
+    });
+
+    assert_eq!(
+      authority.resolve_access(
+        &command.name,
+        window,
+        &Origin::Remote {
+          domain: domain.replace('*', "studio")
+        }
+      ),
+      Some(&resolved_cmd)
+    );
+  }
+
+  #[test]
   fn remote_context_denied() {
     let command = CommandKey {
       name: "my-command".into(),
       context: ExecutionContext::Local,
     };
-    let window = "main";
+    let window = "main";
+
+    let resolved_cmd = ResolvedCommand {
+      windows: vec![Pattern::new(window).unwrap()],
+      scope: None,
+    };
+    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
+      .into_iter()
+      .collect();
+
+    let authority = RuntimeAuthority::new(Resolved {
+      allowed_commands,
+      denied_commands: Default::default(),
+      command_scope: Default::default(),
+      global_scope: Default::default(),
+    });
+
+    assert!(authority
+      .resolve_access(
+        &command.name,
+        window,
+        &Origin::Remote {
+          domain: "tauri.app".into()
+        }
+      )
+      .is_none());
+  }
+
+  #[test]
+  fn denied_command_takes_precendence() {
+    let command = CommandKey {
+      name: "my-command".into(),
+      context: ExecutionContext::Local,
+    };
+    let window = "main";
+    let windows = vec![Pattern::new(window).unwrap()];
+    let allowed_commands = [(
+      command.clone(),
+      ResolvedCommand {
+        windows: windows.clone(),
+        scope: None,
+      },
+    )]
+    .into_iter()
+    .collect();
+    let denied_commands = [(
+      command.clone(),
+      ResolvedCommand {
+        windows: windows.clone(),
+        scope: None,
+      },
+    )]
+    .into_iter()
+    .collect();
+
+    let authority = RuntimeAuthority::new(Resolved {
+      allowed_commands,
+      denied_commands,
+      command_scope: Default::default(),
+      global_scope: Default::default(),
+    });
+
+    assert!(authority
+      .resolve_access(&command.name, window, &Origin::Local)
+      .is_none());
+  }
+
+  #[test]
+  fn malformed_patterns_reported() {
+    let command = |window: &str| ResolvedCommand {
+      windows: vec![Pattern::new(window).unwrap()],
+      scope: None,
+    };
+    let key = |name: &str, context| CommandKey {
+      name: name.into(),
+      context,
+    };
+    let resolved = |window: &str, domain: &str| Resolved {
+      allowed_commands: [
+        (key("open", ExecutionContext::Local), command(window)),
+        (key("close", ExecutionContext::Local), command("main-[ab]")),
+        (
+          key(
+            "fetch",
+            ExecutionContext::Remote {
+              domain: Pattern::new(domain).unwrap(),
+            },
+          ),
+          command("*"),
+        ),
+      ]
+      .into_iter()
+      .collect(),
+      denied_commands: Default::default(),
+      command_scope: Default::default(),
+      global_scope: Default::default(),
+    };
+
+    let Some(AuthorityBuildError::InvalidPatterns(errors)) =
+      RuntimeAuthority::try_new(resolved("main]", "tauri.app]")).err()
+    else {
+      panic!("malformed patterns must be reported");
+    };
+    assert_eq!(
+      errors
+        .iter()
+        .map(|e| (e.command.as_str(), e.pattern.as_str()))
+        .collect::<Vec<_>>(),
+      vec![("fetch", "tauri.app]"), ("open", "main]")]
+    );
+
+    assert!(RuntimeAuthority::try_new(resolved("main", "*.tauri.app")).is_ok());
+  }
+
+  #[test]
+  fn unknown_scope_rejected() {
+    let scope = || ResolvedScope {
+      allow: vec![Value::String("$HOME/*".into())],
+      deny: Vec::new(),
+    };
+    let dangling = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main"])
+      .allow_scoped_command("write", ExecutionContext::Local, ["main"], 7)
+      .with_command_scope(1, scope())
+      .build()
+      .err();
+    assert_eq!(
+      dangling,
+      Some(AuthorityBuildError::UnknownScope {
+        command: "write".into(),
+        scope: 7,
+      })
+    );
+    assert_eq!(
+      dangling.unwrap().to_string(),
+      "command write references the unknown scope 7"
+    );
+
+    assert!(RuntimeAuthorityBuilder::new()
+      .allow_scoped_command("write", ExecutionContext::Local, ["main"], 7)
+      .with_command_scope(7, scope())
+      .build()
+      .is_ok());
+  }
+
+  #[test]
+  #[should_panic(expected = "command write references the unknown scope 7")]
+  fn unknown_scope_panics() {
+    RuntimeAuthority::new(Resolved {
+      allowed_commands: [(
+        CommandKey {
+          name: "write".into(),
+          context: ExecutionContext::Local,
+        },
+        ResolvedCommand {
+          windows: vec![Pattern::new("main").unwrap()],
+          scope: Some(7),
+        },
+      )]
+      .into_iter()
+      .collect(),
+      denied_commands: Default::default(),
+      command_scope: Default::default(),
+      global_scope: Default::default(),
+    });
+  }
+
+  /// Invokes the command through the IPC of an app dispatching every app command, with the `main` window.
+  fn invoke_through_ipc<F: FnOnce(&mut RuntimeAuthority) + Send + 'static>(
+    configure: F,
+    command: &str,
+  ) -> Result<serde_json::Value, serde_json::Value> {
+    invoke_all_through_ipc(configure, &[command]).remove(0)
+  }
+
+  /// Same as [`invoke_through_ipc`], but invokes each command in turn on the same app.
+  fn invoke_all_through_ipc<F: FnOnce(&mut RuntimeAuthority) + Send + 'static>(
+    configure: F,
+    commands: &[&str],
+  ) -> Vec<Result<serde_json::Value, serde_json::Value>> {
+    use crate::{
+      ipc::CallbackFn,
+      test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+
+    let app = mock_builder()
+      .configure_runtime_authority(configure)
+      .invoke_handler(|invoke| {
+        invoke.resolver.resolve("handled");
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    commands
+      .iter()
+      .map(|command| {
+        get_ipc_response(
+          &window,
+          InvokeRequest {
+            cmd: command.to_string(),
+            callback: CallbackFn(0),
+            error: CallbackFn(1),
+            body: serde_json::json!({}).into(),
+            headers: Default::default(),
+          },
+        )
+        .map(|body| body.into_json())
+      })
+      .collect()
+  }
+
+  #[test]
+  fn step_up_without_acl_entry() {
+    let configure = |step_up: bool| {
+      move |authority: &mut RuntimeAuthority| {
+        if step_up {
+          authority.require_step_up("reveal");
+        }
+      }
+    };
+    assert_eq!(
+      invoke_through_ipc(configure(false), "reveal"),
+      Ok(serde_json::json!("handled"))
+    );
+    assert_eq!(
+      invoke_through_ipc(configure(true), "reveal"),
+      Err(serde_json::json!(AccessError::StepUpRequired.to_string()))
+    );
+  }
+
+  fn step_up_authority() -> (CommandKey, RuntimeAuthority) {
+    let command = CommandKey {
+      name: "my-command".into(),
+      context: ExecutionContext::Local,
+    };
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command(command.name.clone(), ExecutionContext::Local, ["main"])
+      .build()
+      .unwrap();
+    authority.require_step_up(command.name.clone());
+    authority.set_step_up_freshness(Duration::from_secs(60));
+
+    (command, authority)
+  }
+
+  #[test]
+  fn fresh_step_up_allowed() {
+    let (command, authority) = step_up_authority();
+
+    let principal = Principal {
+      last_step_up: Some(Instant::now()),
+    };
+    assert!(authority
+      .resolve_access_for_principal(&command.name, "main", &Origin::Local, &principal)
+      .is_ok());
+  }
+
+  #[test]
+  fn stale_or_absent_step_up_denied() {
+    let (command, authority) = step_up_authority();
+
+    assert_eq!(
+      authority.resolve_access_detailed(&command.name, "main", &Origin::Local),
+      Err(AccessError::StepUpRequired)
+    );
+
+    if let Some(last_step_up) = Instant::now().checked_sub(Duration::from_secs(120)) {
+      let principal = Principal {
+        last_step_up: Some(last_step_up),
+      };
+      assert_eq!(
+        authority.resolve_access_for_principal(&command.name, "main", &Origin::Local, &principal),
+        Err(AccessError::StepUpRequired)
+      );
+    }
+  }
+
+  #[test]
+  fn step_up_per_window() {
+    use crate::{
+      ipc::CallbackFn,
+      test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+    use serde_json::json;
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        *authority = RuntimeAuthorityBuilder::new()
+          .allow_command("reveal", ExecutionContext::Local, ["*"])
+          .build()
+          .unwrap();
+        authority.require_step_up("reveal");
+      })
+      .invoke_handler(|invoke| {
+        invoke.resolver.resolve(());
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let main = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let other = WindowBuilder::new(&app, "other", Default::default())
+      .build()
+      .unwrap();
+    let invoke = |window| {
+      get_ipc_response(
+        window,
+        InvokeRequest {
+          cmd: "reveal".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({}).into(),
+          headers: Default::default(),
+        },
+      )
+      .map(|_| ())
+    };
+
+    main.set_principal(Principal {
+      last_step_up: Some(Instant::now()),
+    });
+    assert!(invoke(&main).is_ok());
+    // the step-up of another window does not count
+    assert_eq!(
+      invoke(&other),
+      Err(json!(AccessError::StepUpRequired.to_string()))
+    );
+  }
+
+  #[test]
+  fn scope_aliases_share_cache_entry() {
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .with_command_scope(
+        0,
+        ResolvedScope {
+          allow: vec![Value::from("$DOCUMENT/**".to_string())],
+          deny: Vec::new(),
+        },
+      )
+      .build()
+      .unwrap();
+    authority.alias_scope(1, 0).unwrap();
+    authority.alias_scope(2, 1).unwrap();
+
+    let first = authority
+      .scope_manager
+      .get_command_scope_typed::<String>(&1)
+      .unwrap();
+    let second = authority
+      .scope_manager
+      .get_command_scope_typed::<String>(&2)
+      .unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(second.allow, vec!["$DOCUMENT/**".to_string()]);
+
+    assert_eq!(
+      authority.alias_scope(0, 2),
+      Err(ScopeAliasError::Cycle {
+        alias: 0,
+        target: 2
+      })
+    );
+    assert_eq!(
+      authority.alias_scope(3, 3),
+      Err(ScopeAliasError::Cycle {
+        alias: 3,
+        target: 3
+      })
+    );
+  }
+
+  fn documents_authority() -> RuntimeAuthority {
+    RuntimeAuthorityBuilder::new()
+      .with_command_scope(
+        0,
+        ResolvedScope {
+          allow: vec![Value::Map(
+            [("path".to_string(), Value::from("/home/docs".to_string()))]
+              .into_iter()
+              .collect(),
+          )],
+          deny: vec![Value::Map(
+            [(
+              "path".to_string(),
+              Value::from("/home/docs/secret".to_string()),
+            )]
+            .into_iter()
+            .collect(),
+          )],
+        },
+      )
+      .build()
+      .unwrap()
+  }
+
+  #[test]
+  fn consent_hook_grants_for_session() {
+    let mut authority = documents_authority();
+    authority.record_scope_accesses(true);
+    authority.set_scope_consent_hook(|path| async move { path.starts_with("/home/music") });
+    let scope = authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .unwrap();
+
+    let song = Path::new("/home/music/song.mp3");
+    assert!(!scope.is_allowed(song));
+    assert!(crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(song)
+    ));
+    // the grant is kept for the rest of the session
+    assert!(scope.is_allowed(song));
+
+    // denied entries never reach the hook
+    assert!(!crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(Path::new("/home/docs/secret/key"))
+    ));
+    // nor do paths escaping a granted directory
+    assert!(!crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(Path::new("/home/music/../docs/secret/key"))
+    ));
+    // the checks are not reads of the scope by the command
+    assert!(authority.take_scope_accesses().is_empty());
+  }
+
+  #[test]
+  fn parent_dir_components_escape_nothing() {
+    let authority = documents_authority();
+    let scope = authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .unwrap();
+
+    assert!(scope.is_allowed(Path::new("/home/docs/report.txt")));
+    assert!(!scope.is_allowed(Path::new("/home/docs/../../etc/passwd")));
+    assert!(!scope.is_allowed(Path::new("/home/docs/sub/../report.txt")));
+  }
+
+  #[test]
+  fn consent_hook_denies() {
+    let mut authority = documents_authority();
+    authority.set_scope_consent_hook(|_| async { false });
+    let scope = authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .unwrap();
+
+    let photo = Path::new("/home/photos/cat.png");
+    assert!(!crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(photo)
+    ));
+    assert!(!scope.is_allowed(photo));
+    assert!(crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(Path::new("/home/docs/report.txt"))
+    ));
+  }
+
+  #[test]
+  fn scope_entry_validator() {
+    let mut authority = documents_authority();
+    authority.set_scope_entry_validator(|entry: &PathScope| {
+      if entry
+        .path
+        .components()
+        .any(|c| c == std::path::Component::ParentDir)
+      {
+        Err(format!("{} escapes its directory", entry.path.display()))
+      } else {
+        Ok(())
+      }
+    });
+    assert!(authority.prewarm_scope::<PathScope>(0).is_ok());
+
+    authority.scope_manager.command_scope.insert(
+      0,
+      ResolvedScope {
+        allow: vec![Value::Map(
+          [(
+            "path".to_string(),
+            Value::from("/home/docs/../..".to_string()),
+          )]
+          .into_iter()
+          .collect(),
+        )],
+        deny: Vec::new(),
+      },
+    );
+    authority.clear_scope_cache();
+    let err = authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .unwrap_err();
+    assert_eq!(
+      err,
+      ScopeError::RejectedEntry {
+        scope: Some(0),
+        entry: r#"{"path":"/home/docs/../.."}"#.into(),
+        reason: "/home/docs/../.. escapes its directory".into(),
+      }
+    );
+    assert_eq!(
+      err.to_string(),
+      r#"scope 0 entry {"path":"/home/docs/../.."} is rejected: /home/docs/../.. escapes its directory"#
+    );
+  }
+
+  #[test]
+  fn clear_scope_cache() {
+    let mut authority = documents_authority();
+    let song = Path::new("/home/music/song.mp3");
+    let permits = |authority: &RuntimeAuthority| {
+      authority
+        .scope_manager
+        .command_scope::<PathScope>(0)
+        .unwrap()
+        .is_allowed(song)
+    };
+    assert!(!permits(&authority));
+
+    authority.scope_manager.command_scope.insert(
+      0,
+      ResolvedScope {
+        allow: vec![Value::Map(
+          [("path".to_string(), Value::from("/home/music".to_string()))]
+            .into_iter()
+            .collect(),
+        )],
+        deny: Vec::new(),
+      },
+    );
+    // the cached scope is still served
+    assert!(!permits(&authority));
+
+    authority.clear_scope_cache();
+    assert!(permits(&authority));
+  }
+
+  #[test]
+  fn clear_scope_cache_at_runtime() {
+    use crate::{
+      test::{mock_builder, mock_context, noop_assets},
+      Manager,
+    };
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority
+          .install_plugin(PluginAcl {
+            command_scope: [(100, ResolvedScope::default())].into_iter().collect(),
+            ..Default::default()
+          })
+          .unwrap();
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let authority = app.runtime_authority();
+    let cached = || authority.scope_manager.command_cache.read().unwrap()[&100].len();
+
+    let scope = authority
+      .scope_manager
+      .command_scope::<String>(100)
+      .unwrap();
+    assert_eq!(cached(), 1);
+    assert!(matches!(
+      authority.prewarm_scope::<u64>(100),
+      Err(ScopeError::TypeMismatch { .. })
+    ));
+
+    authority.clear_scope_cache();
+    assert_eq!(cached(), 0);
+    // scopes handed out before the clear stay usable
+    assert!(scope.allows().is_empty());
+    // and the scope can be read again, as any type
+    authority.prewarm_scope::<u64>(100).unwrap();
+    assert_eq!(cached(), 1);
+  }
+
+  #[test]
+  fn prewarm_scope() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .with_command_scope(0, ResolvedScope::default())
+      .with_command_scope(
+        1,
+        ResolvedScope {
+          allow: vec![Value::from(42)],
+          deny: Vec::new(),
+        },
+      )
+      .build()
+      .unwrap();
+    let cached = |key: usize| authority.scope_manager.command_cache.read().unwrap()[&key].len();
+
+    authority.prewarm_scope::<String>(0).unwrap();
+    assert_eq!(cached(0), 1);
+    assert_eq!(cached(1), 0);
+
+    // prewarming another scope keeps the first one cached
+    authority.prewarm_scope::<u64>(1).unwrap();
+    assert_eq!(cached(0), 1);
+    assert_eq!(cached(1), 1);
+    assert_eq!(
+      authority
+        .scope_manager
+        .get_command_scope_typed::<u64>(&1)
+        .unwrap()
+        .allow,
+      vec![42]
+    );
+
+    assert_eq!(
+      authority.prewarm_scope::<String>(1),
+      Err(ScopeError::TypeMismatch {
+        scope: 1,
+        first: "u64",
+        requested: "alloc::string::String",
+      })
+    );
+    assert_eq!(
+      authority.prewarm_scope::<u64>(2),
+      Err(ScopeError::NotFound(2))
+    );
+  }
+
+  #[cfg(feature = "metrics")]
+  #[test]
+  fn scope_cache_metrics() {
+    let authority = documents_authority();
+    assert_eq!(authority.scope_cache_metrics(), Default::default());
+
+    authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .unwrap();
+    assert_eq!(authority.scope_cache_metrics().misses, 1);
+    assert_eq!(authority.scope_cache_metrics().hits, 0);
+
+    for _ in 0..3 {
+      authority
+        .scope_manager
+        .command_scope::<PathScope>(0)
+        .unwrap();
+    }
+    assert_eq!(
+      authority.scope_cache_metrics(),
+      super::ScopeCacheMetrics { hits: 3, misses: 1 }
+    );
+  }
+
+  #[cfg(feature = "prometheus")]
+  #[test]
+  fn render_prometheus() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main"])
+      .deny_command("write", ExecutionContext::Local, ["main"])
+      .allow_command("plugin:*:list", ExecutionContext::Local, ["main"])
+      .build()
+      .unwrap();
+    // queries are not counted
+    let _ = authority.resolve_access("read", "main", &Origin::Local);
+
+    let resolve = |command| {
+      let _ = authority.authorize_invoke(command, "main", &Origin::Local, &Principal::default());
+    };
+    resolve("read");
+    resolve("read");
+    resolve("write");
+    resolve("plugin:fs:list");
+    resolve("plugin:store:list");
+    resolve("missing");
+    resolve("other-missing");
+
+    let rendered = authority.render_prometheus();
+    for line in [
+      "# TYPE tauri_ipc_decisions_total counter",
+      r#"tauri_ipc_decisions_total{command="read",outcome="allowed"} 2"#,
+      r#"tauri_ipc_decisions_total{command="read",outcome="denied"} 0"#,
+      r#"tauri_ipc_decisions_total{command="write",outcome="denied"} 1"#,
+      r#"tauri_ipc_decisions_total{command="plugin:*:list",outcome="allowed"} 2"#,
+      r#"tauri_ipc_decisions_total{command="<unknown>",outcome="not_found"} 2"#,
+      "tauri_scope_cache_hits_total 0",
+      "tauri_scope_cache_misses_total 0",
+    ] {
+      assert!(rendered.lines().any(|l| l == line), "missing {line}");
+    }
+    assert!(!rendered.contains("missing"));
+    assert!(!rendered.contains("plugin:fs:list"));
+  }
+
+  fn plugin_bundle(command: &str, scope: usize) -> PluginAcl {
+    PluginAcl {
+      allowed_commands: [(
+        CommandKey {
+          name: command.into(),
+          context: ExecutionContext::Local,
+        },
+        ResolvedCommand {
+          windows: vec![Pattern::new("*").unwrap()],
+          scope: Some(scope),
+        },
+      )]
+      .into_iter()
+      .collect(),
+      denied_commands: Default::default(),
+      command_scope: [(scope, ResolvedScope::default())].into_iter().collect(),
+    }
+  }
+
+  #[test]
+  fn install_plugin() {
+    let mut authority = documents_authority();
+    let id = authority
+      .install_plugin(plugin_bundle("plugin:fs|read", 1))
+      .unwrap();
+
+    let resolved = authority
+      .resolve_access("plugin:fs|read", "main", &Origin::Local)
+      .unwrap();
+    assert_eq!(resolved.scope, Some(1));
+    assert!(authority
+      .scope_manager
+      .command_scope::<PathScope>(1)
+      .is_ok());
+    assert_eq!(authority.installed_plugins.len(), 1);
+    assert!(authority.installed_plugins.contains_key(&id));
+  }
+
+  #[test]
+  fn conflicting_install_rolls_back() {
+    let mut authority = documents_authority();
+    authority
+      .install_plugin(plugin_bundle("plugin:fs|read", 1))
+      .unwrap();
+
+    let mut bundle = plugin_bundle("plugin:fs|read", 2);
+    assert_eq!(
+      authority.install_plugin(bundle),
+      Err(InstallError::CommandConflict("plugin:fs|read".into()))
+    );
+
+    // the scope conflicts with the documents scope, nothing from the bundle is added
+    bundle = plugin_bundle("plugin:fs|write", 0);
+    assert_eq!(
+      authority.install_plugin(bundle),
+      Err(InstallError::ScopeConflict(0))
+    );
+    assert!(authority
+      .resolve_access("plugin:fs|write", "main", &Origin::Local)
+      .is_none());
+    assert!(!authority.scope_manager.command_scope.contains_key(&2));
+    assert_eq!(authority.installed_plugins.len(), 1);
+  }
+
+  #[test]
+  fn uninstall_plugin() {
+    let mut authority = documents_authority();
+    let id = authority
+      .install_plugin(plugin_bundle("plugin:fs|read", 1))
+      .unwrap();
+
+    assert!(authority.uninstall_plugin(id));
+    assert!(authority
+      .resolve_access("plugin:fs|read", "main", &Origin::Local)
+      .is_none());
+    assert!(authority
+      .scope_manager
+      .command_scope::<PathScope>(1)
+      .is_err());
+    // the scopes that were not added by the plugin are kept
+    assert!(authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .is_ok());
+    assert!(!authority.uninstall_plugin(id));
+
+    // the same bundle can be installed again
+    authority
+      .install_plugin(plugin_bundle("plugin:fs|read", 1))
+      .unwrap();
+  }
+
+  #[test]
+  fn schema_for_origin() {
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main"])
+      .allow_command("write", ExecutionContext::Local, ["main"])
+      .allow_command(
+        "read",
+        ExecutionContext::remote("tauri.app").unwrap(),
+        ["main"],
+      )
+      .build()
+      .unwrap();
+    for name in ["read", "write", "unlisted"] {
+      authority.register_command_schema(CommandSchema {
+        name: name.into(),
+        args: vec!["path".into()],
+      });
+    }
+
+    let names = |schemas: Vec<CommandSchema>| {
+      schemas
+        .into_iter()
+        .map(|schema| schema.name)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      names(authority.schema_for_origin("main", &Origin::Local)),
+      vec!["read", "write"]
+    );
+    assert_eq!(
+      names(authority.schema_for_origin(
+        "main",
+        &Origin::Remote {
+          domain: "tauri.app".into()
+        }
+      )),
+      vec!["read"]
+    );
+    assert!(authority
+      .schema_for_origin("other", &Origin::Local)
+      .is_empty());
+  }
+
+  #[test]
+  fn scope_audit_sink() {
+    let decisions = Arc::new(Mutex::new(Vec::new()));
+    let decisions_ = decisions.clone();
+    let mut authority = documents_authority();
+    authority
+      .set_scope_audit_sink(move |decision| decisions_.lock().unwrap().push(decision.clone()));
+    authority.set_scope_consent_hook(|path| async move { path.starts_with("/home/music") });
+
+    let scope = authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .unwrap();
+    assert!(scope.is_allowed(Path::new("/home/docs/a.txt")));
+    assert!(!scope.is_allowed(Path::new("/home/docs/secret/b.txt")));
+    assert!(crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(Path::new("/home/music/c.mp3"))
+    ));
+    assert!(!crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(Path::new("/etc/passwd"))
+    ));
+    let global = GlobalScope {
+      value: ScopeRef::Shared(
+        authority
+          .scope_manager
+          .get_global_scope_typed::<PathScope>()
+          .unwrap(),
+      ),
+      manager: Some(&authority.scope_manager),
+    };
+    assert!(!global.is_allowed(Path::new("/home/docs/a.txt")));
+
+    let decision = |scope, path: &str, allowed| ScopeDecision {
+      scope,
+      path: path.into(),
+      allowed,
+    };
+    assert_eq!(
+      *decisions.lock().unwrap(),
+      vec![
+        decision(Some(0), "/home/docs/a.txt", true),
+        decision(Some(0), "/home/docs/secret/b.txt", false),
+        decision(Some(0), "/home/music/c.mp3", true),
+        decision(Some(0), "/etc/passwd", false),
+        decision(None, "/home/docs/a.txt", false),
+      ]
+    );
+  }
+
+  #[test]
+  fn dispatch_for_test() {
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command("plugin:fs|read", ExecutionContext::Local, ["main"])
+      .build()
+      .unwrap();
+    authority.register_test_handler("plugin:fs|read", |payload| {
+      let path = payload.into_json()["path"].as_str().unwrap().to_string();
+      Ok(serde_json::json!({ "contents": format!("contents of {path}") }).into())
+    });
+
+    let body = authority
+      .dispatch_for_test(
+        "plugin:fs|read",
+        "main",
+        &Origin::Local,
+        serde_json::json!({ "path": "a.txt" }).into(),
+      )
+      .unwrap();
+    assert_eq!(
+      body.into_json(),
+      serde_json::json!({ "contents": "contents of a.txt" })
+    );
+
+    let err = authority
+      .dispatch_for_test(
+        "plugin:fs|read",
+        "other",
+        &Origin::Local,
+        serde_json::json!({ "path": "a.txt" }).into(),
+      )
+      .unwrap_err();
+    assert_eq!(err.0, "NOT ALLOWED");
+
+    let err = authority
+      .dispatch_for_test("missing", "main", &Origin::Local, Vec::new().into())
+      .unwrap_err();
+    assert_eq!(err.0, "Command missing not found");
+  }
+
+  #[test]
+  fn feature_toggle() {
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command("sync", ExecutionContext::Local, ["main"])
+      .build()
+      .unwrap();
+    authority.require_feature("sync", "cloud");
+    let features = authority.feature_registry();
+    let resolve = |authority: &RuntimeAuthority, window| {
+      authority
+        .resolve_access_detailed("sync", window, &Origin::Local)
+        .map(|_| ())
+    };
+
+    assert_eq!(
+      resolve(&authority, "main"),
+      Err(AccessError::FeatureUnavailable("cloud".into()))
+    );
+    features.enable("cloud");
+    assert_eq!(resolve(&authority, "main"), Ok(()));
+    // ACL denials take precedence
+    assert_eq!(
+      resolve(&authority, "other"),
+      Err(AccessError::WindowNotAllowed)
+    );
+    features.disable("cloud");
+    assert_eq!(
+      resolve(&authority, "main"),
+      Err(AccessError::FeatureUnavailable("cloud".into()))
+    );
+  }
+
+  #[test]
+  fn feature_without_acl_entry() {
+    let configure = |enabled: bool| {
+      move |authority: &mut RuntimeAuthority| {
+        authority.require_feature("sync", "cloud");
+        if enabled {
+          authority.feature_registry().enable("cloud");
+        }
+      }
+    };
+    assert_eq!(
+      invoke_through_ipc(configure(false), "sync"),
+      Err(serde_json::json!(AccessError::FeatureUnavailable(
+        "cloud".into()
+      )
+      .to_string()))
+    );
+    assert_eq!(
+      invoke_through_ipc(configure(true), "sync"),
+      Ok(serde_json::json!("handled"))
+    );
+  }
+
+  #[derive(Clone, Default)]
+  struct Toggles {
+    off: Arc<Mutex<BTreeSet<String>>>,
+    lookups: Arc<std::sync::atomic::AtomicUsize>,
+  }
+
+  impl ToggleStore for Toggles {
+    fn is_on(&self, command: &str) -> bool {
+      self
+        .lookups
+        .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+      !self.off.lock().unwrap().contains(command)
+    }
+  }
+
+  #[test]
+  fn toggle_store() {
+    let toggle_authority = |toggles: &Toggles, ttl| {
+      let mut authority = RuntimeAuthorityBuilder::new()
+        .allow_command("export", ExecutionContext::Local, ["main"])
+        .build()
+        .unwrap();
+      authority.set_toggle_store(toggles.clone(), ttl);
+      authority
+    };
+    let resolve = |authority: &RuntimeAuthority| {
+      authority
+        .resolve_access_detailed("export", "main", &Origin::Local)
+        .map(|_| ())
+    };
+
+    let toggles = Toggles::default();
+    let authority = toggle_authority(&toggles, Duration::ZERO);
+    assert_eq!(resolve(&authority), Ok(()));
+    toggles.off.lock().unwrap().insert("export".into());
+    assert_eq!(resolve(&authority), Err(AccessError::ToggledOff));
+    toggles.off.lock().unwrap().clear();
+    assert_eq!(resolve(&authority), Ok(()));
+
+    // answers are cached for the configured duration
+    let toggles = Toggles::default();
+    let authority = toggle_authority(&toggles, Duration::from_secs(60));
+    resolve(&authority).unwrap();
+    resolve(&authority).unwrap();
+    assert_eq!(toggles.lookups.load(std::sync::atomic::Ordering::SeqCst), 1);
+  }
+
+  #[test]
+  fn toggle_without_acl_entry() {
+    let configure = |off: &'static [&'static str]| {
+      move |authority: &mut RuntimeAuthority| {
+        let toggles = Toggles::default();
+        toggles
+          .off
+          .lock()
+          .unwrap()
+          .extend(off.iter().map(|c| c.to_string()));
+        authority.set_toggle_store(toggles, Duration::ZERO);
+      }
+    };
+    assert_eq!(
+      invoke_through_ipc(configure(&[]), "export"),
+      Ok(serde_json::json!("handled"))
+    );
+    assert_eq!(
+      invoke_through_ipc(configure(&["export"]), "export"),
+      Err(serde_json::json!(AccessError::ToggledOff.to_string()))
+    );
+  }
+
+  fn read_write_authority(commands: &[&str]) -> RuntimeAuthority {
+    commands
+      .iter()
+      .fold(RuntimeAuthorityBuilder::new(), |builder, name| {
+        builder.allow_command(*name, ExecutionContext::Local, ["main"])
+      })
+      .build()
+      .unwrap()
+  }
+
+  #[test]
+  fn replay_decisions() {
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let records_ = records.clone();
+    let mut authority = read_write_authority(&["read", "write"]);
+    authority.set_decision_recorder(move |record| records_.lock().unwrap().push(record.clone()));
+    // queries are not recorded
+    let _ = authority.resolve_access("read", "main", &Origin::Local);
+    assert!(records.lock().unwrap().is_empty());
+
+    for (command, window) in [("read", "main"), ("write", "main"), ("write", "other")] {
+      let _ = authority.authorize_invoke(command, window, &Origin::Local, &Principal::default());
+    }
+
+    // records survive a serialization round trip
+    let records: Vec<DecisionRecord> =
+      serde_json::from_str(&serde_json::to_string(&*records.lock().unwrap()).unwrap()).unwrap();
+    assert_eq!(records.len(), 3);
+    assert!(authority.replay(&records).is_empty());
+
+    // the detailed errors are compared
+    let tightened = read_write_authority(&["read"]);
+    assert_eq!(
+      tightened.replay(&records),
+      vec![
+        Divergence {
+          record: DecisionRecord {
+            command: "write".into(),
+            window: "main".into(),
+            origin: Origin::Local,
+            outcome: DecisionOutcome::Allowed,
+          },
+          replayed: DecisionOutcome::Denied(AccessError::CommandNotFound),
+        },
+        Divergence {
+          record: DecisionRecord {
+            command: "write".into(),
+            window: "other".into(),
+            origin: Origin::Local,
+            outcome: DecisionOutcome::Denied(AccessError::WindowNotAllowed),
+          },
+          replayed: DecisionOutcome::Denied(AccessError::CommandNotFound),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn payload_transforms_chain() {
+    use crate::{
+      ipc::{CallbackFn, InvokeBody, InvokeError},
+      test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+    use serde_json::{json, Value as JsonValue};
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        // decodes the raw bytes as JSON
+        authority.add_payload_transform(|payload| match payload {
+          InvokeBody::Raw(bytes) => serde_json::from_slice::<JsonValue>(&bytes)
+            .map(Into::into)
+            .map_err(InvokeError::from_error),
+          json => Ok(json),
+        });
+        // normalizes the decoded value, which requires the previous transform
+        authority.add_payload_transform(|payload| match payload {
+          InvokeBody::Json(JsonValue::String(s)) => Ok(json!(s.to_uppercase()).into()),
+          InvokeBody::Json(_) => Err(InvokeError::from("expected a string")),
+          InvokeBody::Raw(_) => Err(InvokeError::from("payload was not decoded")),
+        });
+      })
+      .invoke_handler(|invoke| {
+        invoke.resolver.resolve(invoke.message.payload().clone());
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let invoke = |body: InvokeBody| {
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: "echo".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body,
+          headers: Default::default(),
+        },
+      )
+      .map(|body| body.deserialize::<JsonValue>().unwrap())
+    };
+
+    assert_eq!(invoke(b"\"hello\"".to_vec().into()), Ok(json!("HELLO")));
+    // the first error short-circuits
+    assert!(invoke(b"{".to_vec().into())
+      .unwrap_err()
+      .as_str()
+      .unwrap()
+      .contains("EOF"));
+    assert_eq!(invoke(json!(1).into()), Err(json!("expected a string")));
+  }
+
+  #[test]
+  fn payload_codec() {
+    use crate::{
+      ipc::CallbackFn,
+      test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+    use http::{header::CONTENT_TYPE, HeaderMap, HeaderValue};
+    use serde_json::{json, Value as JsonValue};
+
+    #[derive(serde::Deserialize)]
+    struct Size {
+      width: u32,
+      height: u32,
+    }
+
+    #[crate::command(root = "crate")]
+    fn area(size: Size) -> u32 {
+      size.width * size.height
+    }
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority.register_payload_codec("application/msgpack", |bytes: &[u8]| {
+          rmp_serde::from_slice::<JsonValue>(bytes).map_err(|e| e.to_string())
+        });
+      })
+      .invoke_handler(crate::generate_handler![area])
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let invoke = |body: Vec<u8>, content_type: &'static str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: "area".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: body.into(),
+          headers,
+        },
+      )
+    };
+
+    let body = rmp_serde::to_vec_named(&json!({ "size": { "width": 2, "height": 3 } })).unwrap();
+    assert_eq!(
+      invoke(body.clone(), "application/msgpack")
+        .unwrap()
+        .deserialize::<u32>()
+        .unwrap(),
+      6
+    );
+    // other content types keep the raw bytes
+    assert_eq!(
+      invoke(body, "application/octet-stream").unwrap_err()["type"],
+      "PayloadTypeMismatch"
+    );
+    assert!(invoke(vec![0xc1], "application/msgpack")
+      .unwrap_err()
+      .as_str()
+      .unwrap()
+      .starts_with("failed to decode the application/msgpack payload"));
+  }
+
+  #[test]
+  fn command_interceptors() {
+    use super::CommandInterceptor;
+    use crate::{
+      ipc::{CallbackFn, InvokeError},
+      test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+    use serde_json::json;
+
+    struct Log(Arc<Mutex<Vec<String>>>);
+
+    impl CommandInterceptor for Log {
+      fn intercept(&self, command: &str, window: &str, origin: &Origin) -> Result<(), InvokeError> {
+        assert!(matches!(origin, Origin::LocalUrl { .. }));
+        self.0.lock().unwrap().push(format!("{window}:{command}"));
+        Ok(())
+      }
+    }
+
+    struct RejectDelete;
+
+    impl CommandInterceptor for RejectDelete {
+      fn intercept(
+        &self,
+        command: &str,
+        _window: &str,
+        _origin: &Origin,
+      ) -> Result<(), InvokeError> {
+        if command == "delete" {
+          Err(InvokeError::from("session expired"))
+        } else {
+          Ok(())
+        }
+      }
+    }
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log_ = log.clone();
+    let handled = Arc::new(Mutex::new(Vec::new()));
+    let handled_ = handled.clone();
+    let app = mock_builder()
+      .configure_runtime_authority(move |authority| {
+        authority.add_command_interceptor(Log(log_));
+        authority.add_command_interceptor(RejectDelete);
+        authority.add_command_interceptor(Log(Arc::new(Mutex::new(Vec::new()))));
+      })
+      .invoke_handler(move |invoke| {
+        handled_
+          .lock()
+          .unwrap()
+          .push(invoke.message.command().to_string());
+        invoke.resolver.resolve(());
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let invoke = |cmd: &str| {
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({}).into(),
+          headers: Default::default(),
+        },
+      )
+      .map(|_| ())
+    };
+
+    assert_eq!(invoke("read"), Ok(()));
+    assert_eq!(invoke("delete"), Err(json!("session expired")));
+    assert_eq!(*log.lock().unwrap(), ["main:read", "main:delete"]);
+    // the rejected command never reached its handler
+    assert_eq!(*handled.lock().unwrap(), ["read"]);
+
+    // plugin commands denied by the access control list are not intercepted
+    assert_eq!(invoke("plugin:fs|read"), Err(json!("NOT ALLOWED")));
+    assert_eq!(*log.lock().unwrap(), ["main:read", "main:delete"]);
+  }
+
+  #[test]
+  fn deny_reason() {
+    use crate::{
+      ipc::CallbackFn,
+      sealed::ManagerBase,
+      test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+    use serde_json::json;
+
+    let key = |name: &str| CommandKey {
+      name: name.into(),
+      context: ExecutionContext::Local,
+    };
+    let app = mock_builder()
+      .configure_runtime_authority(move |authority| {
+        *authority = RuntimeAuthorityBuilder::new()
+          .deny_command("export", ExecutionContext::Local, ["*"])
+          .build()
+          .unwrap();
+        authority.set_deny_reason(key("export"), "feature disabled in trial");
+      })
+      .invoke_handler(|invoke| {
+        invoke.resolver.resolve(());
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let invoke = |cmd: &str| {
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({}).into(),
+          headers: Default::default(),
+        },
+      )
+      .map(|_| ())
+    };
+
+    assert_eq!(invoke("export"), Err(json!("feature disabled in trial")));
+    assert!(invoke("import").is_ok());
+    assert_eq!(
+      app
+        .manager()
+        .runtime_authority
+        .resolve_access_detailed("export", "main", &Origin::Local),
+      Err(AccessError::DeniedWithReason(
+        "feature disabled in trial".into()
+      ))
+    );
+  }
+
+  #[test]
+  fn plain_deny_of_app_command() {
+    let deny_export = |authority: &mut RuntimeAuthority| {
+      *authority = RuntimeAuthorityBuilder::new()
+        .deny_command("export", ExecutionContext::Local, ["*"])
+        .build()
+        .unwrap();
+    };
+    assert_eq!(
+      invoke_all_through_ipc(deny_export, &["export", "import"]),
+      [
+        Err(serde_json::json!("NOT ALLOWED")),
+        Ok(serde_json::json!("handled"))
+      ]
+    );
+
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .deny_command("export", ExecutionContext::Local, ["*"])
+      .build()
+      .unwrap();
+    authority.register_test_handler("export", Ok);
+    authority.register_test_handler("import", Ok);
+    assert_eq!(
+      authority
+        .dispatch_for_test("export", "main", &Origin::Local, Default::default())
+        .unwrap_err()
+        .0,
+      serde_json::json!("NOT ALLOWED")
+    );
+    assert!(authority
+      .dispatch_for_test("import", "main", &Origin::Local, Default::default())
+      .is_ok());
+  }
+
+  #[test]
+  fn detailed_access_errors() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main"])
+      .deny_command(
+        "read",
+        ExecutionContext::remote("evil.com").unwrap(),
+        ["main"],
+      )
+      .build()
+      .unwrap();
+    let resolve = |command, window, origin| {
+      authority
+        .resolve_access_detailed(command, window, &origin)
+        .map(|_| ())
+    };
+    let remote = |domain: &str| Origin::Remote {
+      domain: domain.into(),
+    };
+
+    assert_eq!(resolve("read", "main", Origin::Local), Ok(()));
+    assert_eq!(
+      resolve("read", "main", remote("evil.com")),
+      Err(AccessError::Denied)
+    );
+    assert_eq!(
+      resolve("read", "main", remote("tauri.app")),
+      Err(AccessError::OriginMismatch)
+    );
+    assert_eq!(
+      resolve("read", "other", Origin::Local),
+      Err(AccessError::WindowNotAllowed)
+    );
+    assert_eq!(
+      resolve("write", "main", Origin::Local),
+      Err(AccessError::CommandNotFound)
+    );
+    assert!(authority
+      .resolve_access("write", "main", &Origin::Local)
+      .is_none());
+  }
+
+  #[test]
+  fn requires_focus() {
+    use crate::{
+      ipc::CallbackFn,
+      test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority.require_focus("reveal_secret");
+        authority.require_focus("plugin:vault|reveal");
+      })
+      .invoke_handler(|invoke| {
+        invoke.resolver.resolve("secret");
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let focused = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    focused.set_focus().unwrap();
+    let background = WindowBuilder::new(&app, "background", Default::default())
+      .build()
+      .unwrap();
+    let invoke = |window, cmd: &str| {
+      get_ipc_response(
+        window,
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: Default::default(),
+          headers: Default::default(),
+        },
+      )
+    };
+
+    assert!(invoke(&focused, "reveal_secret").is_ok());
+    assert_eq!(
+      invoke(&background, "reveal_secret").unwrap_err(),
+      AccessError::WindowNotFocused.to_string()
+    );
+    // other commands do not need focus
+    assert!(invoke(&background, "list").is_ok());
+    // the access control list rejection comes first
+    assert_eq!(
+      invoke(&background, "plugin:vault|reveal").unwrap_err(),
+      "NOT ALLOWED"
+    );
+  }
+
+  #[test]
+  fn deny_entries_per_context() {
+    let remote = || ExecutionContext::remote("evil.com").unwrap();
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("sync", ExecutionContext::Local, ["main"])
+      .deny_command("sync", remote(), ["main"])
+      .deny_command("wipe", ExecutionContext::Local, ["main"])
+      .deny_command("wipe", remote(), ["main"])
+      .build()
+      .unwrap();
+    // deny entries that only differ by context do not shadow each other
+    assert_eq!(authority.denied_commands.len(), 3);
+
+    let resolve = |command, origin| {
+      authority
+        .resolve_access_detailed(command, "main", &origin)
+        .map(|_| ())
+    };
+    let evil = || Origin::Remote {
+      domain: "evil.com".into(),
+    };
 
-    let resolved_cmd = ResolvedCommand {
-      windows: vec![Pattern::new(window).unwrap()],
-      scope: None,
+    // denied remotely but allowed locally
+    assert_eq!(resolve("sync", Origin::Local), Ok(()));
+    assert_eq!(resolve("sync", evil()), Err(AccessError::Denied));
+
+    assert_eq!(resolve("wipe", Origin::Local), Err(AccessError::Denied));
+    assert_eq!(resolve("wipe", evil()), Err(AccessError::Denied));
+  }
+
+  #[test]
+  fn authority_builder() {
+    let scope = ResolvedScope {
+      allow: vec![Value::from("/home/docs".to_string())],
+      deny: Vec::new(),
+    };
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main", "settings-*"])
+      .allow_scoped_command("open", ExecutionContext::Local, ["main"], 0)
+      .with_command_scope(0, scope)
+      .deny_command(
+        "read",
+        ExecutionContext::Remote {
+          domain: Pattern::new("evil.com").unwrap(),
+        },
+        ["*"],
+      )
+      .build()
+      .unwrap();
+    let resolve = |command, window, origin| {
+      authority
+        .resolve_access_detailed(command, window, &origin)
+        .map(|resolved| resolved.scope)
     };
-    let allowed_commands = [(command.clone(), resolved_cmd.clone())]
-      .into_iter()
-      .collect();
 
-    let authority = RuntimeAuthority::new(Resolved {
-      allowed_commands,
-      denied_commands: Default::default(),
-      command_scope: Default::default(),
-      global_scope: Default::default(),
-    });
+    assert_eq!(resolve("read", "settings-1", Origin::Local), Ok(None));
+    assert_eq!(resolve("open", "main", Origin::Local), Ok(Some(0)));
+    assert_eq!(
+      resolve(
+        "read",
+        "main",
+        Origin::Remote {
+          domain: "evil.com".into()
+        }
+      ),
+      Err(AccessError::Denied)
+    );
+
+    let conflict = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main"])
+      .deny_command("read", ExecutionContext::Local, ["*"])
+      .build()
+      .err();
+    assert_eq!(
+      conflict,
+      Some(AuthorityBuildError::AllowDenyConflict("read".into()))
+    );
+
+    let invalid = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["[main", "main]"])
+      .build()
+      .err();
+    assert!(matches!(
+      invalid,
+      Some(AuthorityBuildError::InvalidPatterns(errors)) if errors.len() == 1 && errors[0].pattern == "[main"
+    ));
+  }
+
+  #[test]
+  fn remote_domain_case_insensitive() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command(
+        "read",
+        ExecutionContext::Remote {
+          domain: Pattern::new("tauri.app").unwrap(),
+        },
+        ["main"],
+      )
+      .allow_command(
+        "write",
+        ExecutionContext::Remote {
+          domain: Pattern::new("*.tauri.app").unwrap(),
+        },
+        ["main"],
+      )
+      .build()
+      .unwrap();
+    let resolve = |command, domain: &str| {
+      authority
+        .resolve_access(
+          command,
+          "main",
+          &Origin::Remote {
+            domain: domain.into(),
+          },
+        )
+        .is_some()
+    };
+
+    assert!(resolve("read", "Tauri.App"));
+    assert!(resolve("read", "TAURI.APP"));
+    assert!(resolve("write", "API.tauri.app"));
+    assert!(resolve("write", "Beta.Tauri.App"));
+    assert!(!resolve("write", "tauri.app.evil.com"));
+  }
+
+  #[test]
+  fn local_url() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command(
+        "internal",
+        ExecutionContext::local_url("tauri://localhost").unwrap(),
+        ["main"],
+      )
+      .allow_command("any", ExecutionContext::Local, ["main"])
+      .build()
+      .unwrap();
+    let resolve =
+      |command, origin: Origin| authority.resolve_access(command, "main", &origin).is_some();
+    let local = |url: &str| Origin::LocalUrl { url: url.into() };
+
+    assert!(resolve("internal", local("tauri://localhost")));
+    assert!(resolve("internal", local("TAURI://LOCALHOST")));
+    assert!(!resolve("internal", local("https://tauri.localhost")));
+    // origins without the URL can't satisfy a URL restriction
+    assert!(!resolve("internal", Origin::Local));
+
+    assert!(resolve("any", local("tauri://localhost")));
+    assert!(resolve("any", local("https://tauri.localhost")));
+    assert!(resolve("any", Origin::Local));
+    assert!(!resolve(
+      "any",
+      Origin::Remote {
+        domain: "localhost".into()
+      }
+    ));
+  }
+
+  #[test]
+  fn remote_scheme_and_port() {
+    let remote = |pattern| ExecutionContext::remote(pattern).unwrap();
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("secure", remote("https://tauri.app"), ["main"])
+      .allow_command("dev", remote("localhost:1430"), ["main"])
+      .allow_command("any", remote("*.tauri.app"), ["main"])
+      .build()
+      .unwrap();
+    let resolve = |command, url: &str| {
+      let url: url::Url = url.parse().unwrap();
+      authority
+        .resolve_access(
+          command,
+          "main",
+          &Origin::RemoteUrl {
+            scheme: url.scheme().into(),
+            domain: url.domain().unwrap().into(),
+            port: url.port_or_known_default(),
+          },
+        )
+        .is_some()
+    };
+
+    assert!(resolve("secure", "https://tauri.app"));
+    assert!(resolve("secure", "https://tauri.app:8443"));
+    assert!(!resolve("secure", "http://tauri.app"));
+    assert!(!resolve("secure", "http://tauri.app:443"));
+
+    assert!(resolve("dev", "http://localhost:1430"));
+    assert!(resolve("dev", "https://localhost:1430"));
+    assert!(!resolve("dev", "http://localhost:1431"));
+    assert!(!resolve("dev", "http://localhost"));
+
+    assert!(resolve("any", "http://api.tauri.app"));
+    assert!(resolve("any", "https://api.tauri.app:8443"));
+
+    // origins without URL information can't satisfy scheme or port restrictions
+    let resolve_domain = |command| {
+      authority
+        .resolve_access(
+          command,
+          "main",
+          &Origin::Remote {
+            domain: "tauri.app".into(),
+          },
+        )
+        .is_some()
+    };
+    assert!(!resolve_domain("secure"));
+  }
+
+  #[test]
+  fn commands_iter() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main"])
+      .allow_command("write", ExecutionContext::Local, ["main", "editor-*"])
+      .deny_command("delete", ExecutionContext::Local, ["*"])
+      .build()
+      .unwrap();
+
+    let allowed = authority
+      .allowed_commands_iter()
+      .map(|(key, command)| {
+        (
+          key.name.as_str(),
+          command
+            .windows
+            .iter()
+            .map(|w| w.as_str())
+            .collect::<Vec<_>>(),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      allowed,
+      vec![("read", vec!["main"]), ("write", vec!["main", "editor-*"])]
+    );
+
+    let denied = authority
+      .denied_commands_iter()
+      .map(|(key, _)| key)
+      .collect::<Vec<_>>();
+    assert_eq!(
+      denied,
+      vec![&CommandKey {
+        name: "delete".into(),
+        context: ExecutionContext::Local,
+      }]
+    );
+  }
+
+  #[test]
+  fn command_scope_permits() {
+    let entry = |value: &str| {
+      Value::Map(
+        [("value".to_string(), Value::from(value.to_string()))]
+          .into_iter()
+          .collect(),
+      )
+    };
+    let authority = RuntimeAuthorityBuilder::new()
+      .with_command_scope(
+        0,
+        ResolvedScope {
+          allow: vec![entry("light"), entry("dark"), entry("high-contrast")],
+          deny: vec![entry("high-contrast")],
+        },
+      )
+      .build()
+      .unwrap();
+    let scope = authority
+      .scope_manager
+      .command_scope::<StringScope>(0)
+      .unwrap();
+
+    assert!(scope.permits("light"));
+    assert!(scope.permits("dark"));
+    // deny takes precedence
+    assert!(!scope.permits("high-contrast"));
+    assert!(!scope.permits("sepia"));
+
+    let prefix = |entry: &StringScope, query: &str| query.starts_with(entry.value.as_str());
+    assert!(scope.permits_with("dark-blue", prefix));
+    assert!(!scope.permits_with("high-contrast-yellow", prefix));
+    assert!(!scope.permits_with("blue", prefix));
+  }
+
+  #[test]
+  fn synthetic_scopes() {
+    use super::CommandScope;
+
+    let entry = |value: &str| StringScope {
+      value: value.into(),
+    };
+    let scope = CommandScope::from_values(vec![entry("light"), entry("dark")], vec![entry("dark")]);
+    assert_eq!(scope.allows(), &vec![entry("light"), entry("dark")]);
+    assert_eq!(scope.denies(), &vec![entry("dark")]);
+    assert!(scope.permits("light"));
+    assert!(!scope.permits("dark"));
+    assert!(!scope.permits("sepia"));
+
+    let scope = CommandScope::from_values(
+      vec![PathScope {
+        path: "/home/docs".into(),
+      }],
+      Vec::new(),
+    );
+    assert!(crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(Path::new("/home/docs/a.txt"))
+    ));
+    assert!(!crate::async_runtime::block_on(
+      scope.is_allowed_with_consent(Path::new("/etc/passwd"))
+    ));
+
+    let global = GlobalScope::from_values(
+      vec![PathScope {
+        path: "/home".into(),
+      }],
+      vec![PathScope {
+        path: "/home/secret".into(),
+      }],
+    );
+    assert_eq!(global.allows().len(), 1);
+    assert_eq!(global.denies()[0].path, Path::new("/home/secret"));
+    assert!(global.is_allowed(Path::new("/home/a.txt")));
+    assert!(!global.is_allowed(Path::new("/home/secret/b.txt")));
+  }
+
+  #[test]
+  fn origin_matcher() {
+    struct Allowlist;
+
+    impl OriginMatcher for Allowlist {
+      fn matches(&self, origin: &Origin, context: &ExecutionContext) -> Option<bool> {
+        match (origin, context) {
+          (Origin::Remote { domain }, ExecutionContext::Remote { .. }) => match domain.as_str() {
+            "partner.com" => Some(true),
+            "tauri.app" => Some(false),
+            _ => None,
+          },
+          _ => None,
+        }
+      }
+    }
+
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command(
+        "read",
+        ExecutionContext::Remote {
+          domain: Pattern::new("*.app").unwrap(),
+        },
+        ["main"],
+      )
+      .build()
+      .unwrap();
+    authority.set_origin_matcher(Allowlist);
+    let resolve = |domain: &str| {
+      authority
+        .resolve_access(
+          "read",
+          "main",
+          &Origin::Remote {
+            domain: domain.into(),
+          },
+        )
+        .is_some()
+    };
+
+    assert!(resolve("partner.com"));
+    assert!(!resolve("tauri.app"));
+    // falls back to the glob pattern
+    assert!(resolve("beta.app"));
+    assert!(!resolve("evil.com"));
+  }
+
+  #[test]
+  fn rate_limiter() {
+    #[derive(Default)]
+    struct TwoCalls(Mutex<BTreeMap<(String, String), u32>>);
+
+    impl RateLimiter for TwoCalls {
+      fn check(&self, command: &str, window: &str, origin: &Origin) -> bool {
+        assert!(matches!(origin, Origin::Local | Origin::LocalUrl { .. }));
+        let mut calls = self.0.lock().unwrap();
+        let calls = calls.entry((command.into(), window.into())).or_default();
+        *calls += 1;
+        *calls <= 2
+      }
+    }
+
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command("export", ExecutionContext::Local, ["main", "settings"])
+      .build()
+      .unwrap();
+    let resolve = |authority: &RuntimeAuthority, window: &str| {
+      authority
+        .authorize_invoke("export", window, &Origin::Local, &Principal::default())
+        .map(|_| ())
+    };
+
+    // no limiter by default
+    for _ in 0..3 {
+      assert_eq!(resolve(&authority, "main"), Ok(()));
+    }
+
+    authority.set_rate_limiter(TwoCalls::default());
+    assert_eq!(resolve(&authority, "main"), Ok(()));
+    assert_eq!(resolve(&authority, "main"), Ok(()));
+    assert_eq!(
+      resolve(&authority, "main"),
+      Err(AccessError::TooManyRequests)
+    );
+    // access queries don't use the budget
+    for _ in 0..3 {
+      assert!(authority
+        .resolve_access("export", "settings", &Origin::Local)
+        .is_some());
+    }
+    // limited per window
+    assert_eq!(resolve(&authority, "settings"), Ok(()));
+    // plugin commands the IPC rejects don't reach the limiter
+    for _ in 0..3 {
+      assert_eq!(
+        authority
+          .authorize_invoke(
+            "plugin:fs|export",
+            "main",
+            &Origin::Local,
+            &Principal::default()
+          )
+          .map(|_| ()),
+        Err(AccessError::CommandNotFound)
+      );
+    }
+
+    // app commands without an entry are limited too
+    let responses = invoke_all_through_ipc(
+      |authority| authority.set_rate_limiter(TwoCalls::default()),
+      &["save", "save", "save"],
+    );
+    assert_eq!(
+      responses,
+      [
+        Ok(serde_json::json!("handled")),
+        Ok(serde_json::json!("handled")),
+        Err(serde_json::json!(AccessError::TooManyRequests.to_string())),
+      ]
+    );
+  }
+
+  #[test]
+  fn scope_entry_type_mismatch() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .with_command_scope(
+        0,
+        ResolvedScope {
+          allow: vec![Value::from("/home/docs".to_string())],
+          deny: Vec::new(),
+        },
+      )
+      .with_global_scope(ResolvedScope {
+        allow: Vec::new(),
+        deny: vec![Value::from(true)],
+      })
+      .build()
+      .unwrap();
+
+    let err = authority
+      .scope_manager
+      .command_scope::<PathScope>(0)
+      .unwrap_err();
+    assert_eq!(
+      err,
+      ScopeError::InvalidEntry {
+        scope: Some(0),
+        entry: "\"/home/docs\"".into(),
+        type_name: std::any::type_name::<PathScope>(),
+      }
+    );
+    assert!(err.to_string().starts_with("scope 0 entry"));
+
+    let err = authority
+      .scope_manager
+      .get_global_scope_typed::<PathScope>()
+      .unwrap_err();
+    assert!(err.to_string().starts_with("global scope entry true"));
+
+    assert_eq!(
+      authority
+        .scope_manager
+        .command_scope::<String>(1)
+        .unwrap_err(),
+      ScopeError::NotFound(1)
+    );
+  }
+
+  #[test]
+  fn scope_type_conflict() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_scoped_command("read_file", ExecutionContext::Local, ["main"], 0)
+      .allow_scoped_command("open_url", ExecutionContext::Local, ["main"], 0)
+      .with_command_scope(
+        0,
+        ResolvedScope {
+          allow: vec![Value::Map(
+            [
+              ("path".to_string(), Value::from("/home".to_string())),
+              (
+                "url".to_string(),
+                Value::from("https://tauri.app".to_string()),
+              ),
+            ]
+            .into_iter()
+            .collect(),
+          )],
+          deny: Vec::new(),
+        },
+      )
+      .build()
+      .unwrap();
+    let scope_of = |command| {
+      authority
+        .resolve_access(command, "main", &Origin::Local)
+        .unwrap()
+        .scope
+        .unwrap()
+    };
 
     assert!(authority
-      .resolve_access(
-        &command.name,
-        window,
-        Origin::Remote {
-          domain: "tauri.app".into()
+      .scope_manager
+      .command_scope::<PathScope>(scope_of("read_file"))
+      .is_ok());
+    let err = authority
+      .scope_manager
+      .command_scope::<StringScope>(scope_of("open_url"))
+      .unwrap_err();
+    assert_eq!(
+      err,
+      ScopeError::TypeMismatch {
+        scope: 0,
+        first: std::any::type_name::<PathScope>(),
+        requested: std::any::type_name::<StringScope>(),
+      }
+    );
+    // the first type keeps working
+    assert!(authority
+      .scope_manager
+      .command_scope::<PathScope>(scope_of("read_file"))
+      .is_ok());
+  }
+
+  #[test]
+  fn missing_companion() {
+    let remote = || ExecutionContext::Remote {
+      domain: Pattern::new("tauri.app").unwrap(),
+    };
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command("begin_transaction", ExecutionContext::Local, ["main"])
+      .allow_command("commit_transaction", ExecutionContext::Local, ["main"])
+      .allow_command("begin_transaction", remote(), ["main"])
+      .build()
+      .unwrap();
+    assert!(authority.validate().is_empty());
+
+    authority.require_companion("begin_transaction", "commit_transaction");
+    let warnings = authority.validate();
+    assert_eq!(
+      warnings,
+      vec![AclWarning::MissingCompanion {
+        command: "begin_transaction".into(),
+        companion: "commit_transaction".into(),
+        context: remote(),
+      }]
+    );
+    assert_eq!(
+      warnings[0].to_string(),
+      "command begin_transaction is allowed but its companion command commit_transaction is not"
+    );
+
+    // companions of commands that are not allowed are not checked
+    authority.require_companion("rollback_transaction", "commit_transaction");
+    assert_eq!(authority.validate().len(), 1);
+  }
+
+  #[test]
+  #[allow(deprecated)]
+  fn resolve_access_owned() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main"])
+      .allow_command(
+        "fetch",
+        ExecutionContext::remote("tauri.app").unwrap(),
+        ["main"],
+      )
+      .deny_command("write", ExecutionContext::Local, ["*"])
+      .build()
+      .unwrap();
+
+    let remote = Origin::RemoteUrl {
+      scheme: "https".into(),
+      domain: "tauri.app".into(),
+      port: Some(443),
+    };
+    for command in ["read", "fetch", "write", "unknown"] {
+      for window in ["main", "settings"] {
+        for origin in [Origin::Local, remote.clone()] {
+          assert_eq!(
+            authority
+              .resolve_access(command, window, &origin)
+              .map(|resolved| resolved.windows.len()),
+            authority
+              .resolve_access_owned(command, window, origin.clone())
+              .map(|resolved| resolved.windows.len()),
+          );
         }
+      }
+    }
+  }
+
+  #[test]
+  fn debug_snapshot() {
+    struct Toggles;
+
+    impl ToggleStore for Toggles {
+      fn is_on(&self, command: &str) -> bool {
+        command != "export"
+      }
+    }
+
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main", "settings-*"])
+      .allow_scoped_command(
+        "open",
+        ExecutionContext::remote("https://*.tauri.app").unwrap(),
+        ["main"],
+        3,
       )
-      .is_none());
+      .with_command_scope(3, Default::default())
+      .allow_command("export", ExecutionContext::Local, ["main"])
+      .deny_command("delete", ExecutionContext::Local, ["*"])
+      .build()
+      .unwrap();
+    authority.require_step_up("export");
+    authority.require_focus("open");
+    authority.require_feature("open", "beta");
+    authority.feature_registry().enable("beta");
+    authority.set_toggle_store(Toggles, Duration::from_secs(60));
+    let _ = authority.resolve_access("export", "main", &Origin::Local);
+
+    let snapshot = authority.debug_snapshot();
+    for line in [
+      "allowed:",
+      "  read local windows=[main,settings-*] scope=-",
+      "  open remote(https://*.tauri.app) windows=[main] scope=3",
+      "denied:",
+      "  delete local windows=[*] scope=-",
+      "step-up: export",
+      "focus: open",
+      "required features: open=beta",
+      "enabled features: beta",
+      "toggles: export=off",
+    ] {
+      assert!(
+        snapshot.lines().any(|l| l == line),
+        "missing `{line}` in\n{snapshot}"
+      );
+    }
   }
 
   #[test]
-  fn denied_command_takes_precendence() {
-    let command = CommandKey {
-      name: "my-command".into(),
-      context: ExecutionContext::Local,
+  fn wildcard_commands() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("fs:*", ExecutionContext::Local, ["main"])
+      .allow_command("http:fetch_*", ExecutionContext::Local, ["main"])
+      .allow_command("shell:open", ExecutionContext::Local, ["main"])
+      .deny_command("fs:remove_file", ExecutionContext::Local, ["*"])
+      .deny_command("shell:*", ExecutionContext::Local, ["*"])
+      .build()
+      .unwrap();
+    let resolve = |command: &str| {
+      authority
+        .resolve_access_detailed(command, "main", &Origin::Local)
+        .map(|_| ())
     };
-    let window = "main";
-    let windows = vec![Pattern::new(window).unwrap()];
-    let allowed_commands = [(
-      command.clone(),
-      ResolvedCommand {
-        windows: windows.clone(),
-        scope: None,
-      },
-    )]
-    .into_iter()
-    .collect();
-    let denied_commands = [(
-      command.clone(),
-      ResolvedCommand {
-        windows: windows.clone(),
-        scope: None,
-      },
-    )]
-    .into_iter()
-    .collect();
 
-    let authority = RuntimeAuthority::new(Resolved {
-      allowed_commands,
-      denied_commands,
-      command_scope: Default::default(),
-      global_scope: Default::default(),
-    });
+    assert_eq!(resolve("fs:read_file"), Ok(()));
+    assert_eq!(resolve("fs:write_file"), Ok(()));
+    assert_eq!(resolve("http:fetch_json"), Ok(()));
+    // a specific deny carves out one command of a wildcard allow
+    assert_eq!(resolve("fs:remove_file"), Err(AccessError::Denied));
+    // deny wildcards take precedence over specific allows
+    assert_eq!(resolve("shell:open"), Err(AccessError::Denied));
+    assert_eq!(
+      resolve("fs:read_file:all"),
+      Err(AccessError::CommandNotFound)
+    );
+    assert_eq!(resolve("http:post"), Err(AccessError::CommandNotFound));
+  }
+
+  #[test]
+  fn every_matching_entry_tried_for_the_window() {
+    let authority = RuntimeAuthorityBuilder::new()
+      .allow_command("fs:read", ExecutionContext::Local, ["main"])
+      .allow_command("fs:*", ExecutionContext::Local, ["settings"])
+      .build()
+      .unwrap();
+    let resolve = |window: &str| {
+      authority
+        .resolve_access_detailed("fs:read", window, &Origin::Local)
+        .map(|_| ())
+    };
+
+    assert_eq!(resolve("main"), Ok(()));
+    // the exact entry does not shadow the wildcard one allowing the window
+    assert_eq!(resolve("settings"), Ok(()));
+    assert_eq!(resolve("other"), Err(AccessError::WindowNotAllowed));
+  }
+
+  #[test]
+  fn authority_trait_object() {
+    fn callable(authority: &dyn Authority, window: &str) -> Vec<String> {
+      authority
+        .allowed_commands_for_window(window, &Origin::Local)
+        .into_iter()
+        .map(ToString::to_string)
+        .collect()
+    }
+
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_command("read", ExecutionContext::Local, ["main", "settings-*"])
+      .allow_command("plugin:fs:*", ExecutionContext::Local, ["main"])
+      .allow_command(
+        "fetch",
+        ExecutionContext::remote("https://tauri.app").unwrap(),
+        ["main"],
+      )
+      .deny_command(
+        "plugin:fs:*",
+        ExecutionContext::remote("https://tauri.app").unwrap(),
+        ["*"],
+      )
+      .deny_command("write", ExecutionContext::Local, ["*"])
+      .build()
+      .unwrap();
+    // the queries have no side effect
+    struct Unreachable;
+    impl RateLimiter for Unreachable {
+      fn check(&self, _command: &str, _window: &str, _origin: &Origin) -> bool {
+        unreachable!("queries don't consult the rate limiter")
+      }
+    }
+    authority.set_rate_limiter(Unreachable);
+    authority.set_decision_recorder(|_| unreachable!("queries are not recorded"));
+    let authority: &dyn Authority = &authority;
+
+    assert_eq!(callable(authority, "main"), ["plugin:fs:*", "read"]);
+    assert_eq!(callable(authority, "settings-1"), ["read"]);
+    assert!(callable(authority, "other").is_empty());
+
+    assert!(authority.is_allowed("plugin:fs:read", "main", &Origin::Local));
+    assert!(!authority.is_allowed("read", "other", &Origin::Local));
+    assert_eq!(
+      authority
+        .resolve_access_detailed("write", "main", &Origin::Local)
+        .unwrap_err(),
+      AccessError::Denied
+    );
+    assert_eq!(
+      authority
+        .resolve_access_detailed("fetch", "main", &Origin::Local)
+        .unwrap_err(),
+      AccessError::OriginMismatch
+    );
+    assert_eq!(authority.allowed_commands().count(), 3);
+    assert_eq!(authority.denied_commands().count(), 2);
+  }
+
+  #[test]
+  fn merge() {
+    let resolved = |allowed: &[&str], denied: &[&str], scope: Option<usize>| {
+      let command = |name: &&str| {
+        (
+          CommandKey {
+            name: name.to_string(),
+            context: ExecutionContext::Local,
+          },
+          ResolvedCommand {
+            windows: vec![Pattern::new("main").unwrap()],
+            scope,
+          },
+        )
+      };
+      Resolved {
+        allowed_commands: allowed.iter().map(command).collect(),
+        denied_commands: denied.iter().map(command).collect(),
+        command_scope: scope
+          .map(|key| (key, ResolvedScope::default()))
+          .into_iter()
+          .collect(),
+        global_scope: ResolvedScope {
+          allow: vec![Value::String(format!("{allowed:?}"))],
+          deny: Vec::new(),
+        },
+      }
+    };
+    let resolve = |authority: &RuntimeAuthority, command| {
+      authority
+        .resolve_access_detailed(command, "main", &Origin::Local)
+        .map(|_| ())
+    };
+
+    // plugin A allows `share`, plugin B denies it
+    let mut authority = RuntimeAuthority::new(resolved(&["share", "read"], &[], Some(1)));
+    authority
+      .merge(resolved(&["write"], &["share"], Some(2)))
+      .unwrap();
+    assert_eq!(resolve(&authority, "share"), Err(AccessError::Denied));
+    assert_eq!(resolve(&authority, "read"), Ok(()));
+    assert_eq!(resolve(&authority, "write"), Ok(()));
+    assert!(authority.scope_manager.command_scope.contains_key(&2));
+    assert_eq!(authority.scope_manager.global_scope.allow.len(), 2);
+
+    // the other way around
+    let mut authority = RuntimeAuthority::new(resolved(&[], &["share"], None));
+    authority.merge(resolved(&["share"], &[], None)).unwrap();
+    assert_eq!(resolve(&authority, "share"), Err(AccessError::Denied));
+  }
+
+  #[test]
+  fn merge_scope_conflict() {
+    let mut authority = RuntimeAuthorityBuilder::new()
+      .allow_scoped_command("read", ExecutionContext::Local, ["main"], 1)
+      .with_command_scope(1, ResolvedScope::default())
+      .build()
+      .unwrap();
 
+    let conflicting = Resolved {
+      allowed_commands: [(
+        CommandKey {
+          name: "write".into(),
+          context: ExecutionContext::Local,
+        },
+        ResolvedCommand {
+          windows: vec![Pattern::new("main").unwrap()],
+          scope: Some(1),
+        },
+      )]
+      .into_iter()
+      .collect(),
+      denied_commands: Default::default(),
+      command_scope: [(1, ResolvedScope::default())].into_iter().collect(),
+      global_scope: Default::default(),
+    };
+    assert_eq!(
+      authority.merge(conflicting),
+      Err(MergeError::ScopeConflict(1))
+    );
+    // nothing was merged
     assert!(authority
-      .resolve_access(&command.name, window, Origin::Local)
+      .resolve_access("write", "main", &Origin::Local)
       .is_none());
   }
 }
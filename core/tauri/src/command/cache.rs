@@ -0,0 +1,555 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{
+  collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+  hash::{Hash, Hasher},
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use http::HeaderMap;
+use serde_json::Value as JsonValue;
+
+use crate::ipc::{InvokeBody, InvokeResponse};
+
+use super::Origin;
+
+/// The header carrying the idempotency key of an invoke.
+pub(crate) const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// The error of the retries still waiting when the first execution runs for longer than the TTL.
+const IDEMPOTENCY_TIMEOUT_ERROR: &str = "idempotent command timed out";
+
+/// The key of a cached response.
+///
+/// Responses are computed under the scopes of a window and origin, so they are never shared with another one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ResponseCacheKey {
+  command: String,
+  window: String,
+  origin: Origin,
+  /// The hash of the JSON arguments.
+  arguments: u64,
+}
+
+/// A cache of serialized command responses for commands marked as cacheable.
+#[derive(Debug, Default)]
+pub(crate) struct ResponseCache {
+  ttls: BTreeMap<String, Duration>,
+  entries: Mutex<HashMap<ResponseCacheKey, (Instant, InvokeBody)>>,
+}
+
+impl ResponseCache {
+  pub(crate) fn enable(&mut self, command: String, ttl: Duration) {
+    self.ttls.insert(command, ttl);
+  }
+
+  /// Computes the cache key for the given invoke, or `None` if it must not be cached.
+  ///
+  /// Raw payloads always bypass the cache.
+  pub(crate) fn key(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+    payload: &InvokeBody,
+  ) -> Option<ResponseCacheKey> {
+    if !self.ttls.contains_key(command) {
+      return None;
+    }
+    match payload {
+      InvokeBody::Json(v) => {
+        let mut hasher = DefaultHasher::new();
+        v.to_string().hash(&mut hasher);
+        Some(ResponseCacheKey {
+          command: command.into(),
+          window: window.into(),
+          origin: origin.clone(),
+          arguments: hasher.finish(),
+        })
+      }
+      InvokeBody::Raw(_) => None,
+    }
+  }
+
+  fn is_fresh(&self, key: &ResponseCacheKey, cached_at: Instant) -> bool {
+    self
+      .ttls
+      .get(&key.command)
+      .is_some_and(|ttl| cached_at.elapsed() <= *ttl)
+  }
+
+  /// Gets the cached response for the given key if it has not expired yet.
+  pub(crate) fn get(&self, key: &ResponseCacheKey) -> Option<InvokeBody> {
+    let mut entries = self.entries.lock().unwrap();
+    match entries.get(key) {
+      Some((cached_at, body)) if self.is_fresh(key, *cached_at) => Some(body.clone()),
+      Some(_) => {
+        entries.remove(key);
+        None
+      }
+      None => None,
+    }
+  }
+
+  /// Stores the response of a command that ran, evicting the expired entries.
+  pub(crate) fn insert(&self, key: ResponseCacheKey, body: InvokeBody) {
+    let mut entries = self.entries.lock().unwrap();
+    entries.retain(|key, (cached_at, _)| self.is_fresh(key, *cached_at));
+    entries.insert(key, (Instant::now(), body));
+  }
+
+  #[cfg(test)]
+  fn len(&self) -> usize {
+    self.entries.lock().unwrap().len()
+  }
+}
+
+/// The key of an idempotent invoke: its [`IDEMPOTENCY_KEY_HEADER`] header, scoped to the command, window and origin.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct IdempotencyKey {
+  command: String,
+  window: String,
+  origin: Origin,
+  key: String,
+}
+
+/// A cached result of an idempotent command.
+pub(crate) type IdempotentResult = Result<InvokeBody, JsonValue>;
+
+/// Resolves a retry with the result of the first execution.
+type IdempotencyWaiter = Box<dyn FnOnce(IdempotentResult) + Send>;
+
+enum IdempotencyEntry {
+  /// The first execution is running since the given instant, retries wait for its result.
+  Running(Instant, Vec<IdempotencyWaiter>),
+  /// The result of the first execution and when it completed.
+  Done(Instant, IdempotentResult),
+}
+
+/// What to do with an idempotent invoke, see [`IdempotencyCache::reserve`].
+pub(crate) enum Reservation {
+  /// The key is reserved, the command must run and its result be stored with [`IdempotencyCache::complete`].
+  Run,
+  /// The first execution completed with this result.
+  Replay(IdempotentResult),
+  /// The first execution is running, the invoke is resolved once it completes.
+  Wait,
+}
+
+/// A cache of the results of idempotent commands, keyed by the [`IDEMPOTENCY_KEY_HEADER`] header.
+#[derive(Default)]
+pub(crate) struct IdempotencyCache {
+  ttls: BTreeMap<String, Duration>,
+  entries: Mutex<HashMap<IdempotencyKey, IdempotencyEntry>>,
+}
+
+impl std::fmt::Debug for IdempotencyCache {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("IdempotencyCache")
+      .field("ttls", &self.ttls)
+      .finish_non_exhaustive()
+  }
+}
+
+impl IdempotencyCache {
+  pub(crate) fn enable(&mut self, command: String, ttl: Duration) {
+    self.ttls.insert(command, ttl);
+  }
+
+  /// Computes the key for the given invoke, or `None` if the command is not idempotent or has no idempotency key.
+  pub(crate) fn key(
+    &self,
+    command: &str,
+    window: &str,
+    origin: &Origin,
+    headers: &HeaderMap,
+  ) -> Option<IdempotencyKey> {
+    if !self.ttls.contains_key(command) {
+      return None;
+    }
+    let key = headers.get(IDEMPOTENCY_KEY_HEADER)?.to_str().ok()?;
+    Some(IdempotencyKey {
+      command: command.into(),
+      window: window.into(),
+      origin: origin.clone(),
+      key: key.into(),
+    })
+  }
+
+  /// Reserves the key for the first execution, or tells how to answer a retry.
+  ///
+  /// Retries arriving while the first execution is running are resolved by `waiter` once it completes.
+  /// Expired results are evicted, and so are executions running for longer than the TTL,
+  /// failing the retries waiting for them.
+  pub(crate) fn reserve<F: FnOnce(IdempotentResult) + Send + 'static>(
+    &self,
+    key: &IdempotencyKey,
+    waiter: F,
+  ) -> Reservation {
+    let mut timed_out = Vec::new();
+    let reservation = {
+      let mut entries = self.entries.lock().unwrap();
+      entries.retain(|key, entry| {
+        let (IdempotencyEntry::Running(since, _) | IdempotencyEntry::Done(since, _)) = entry;
+        let fresh = self
+          .ttls
+          .get(&key.command)
+          .is_some_and(|ttl| since.elapsed() <= *ttl);
+        if !fresh {
+          if let IdempotencyEntry::Running(_, waiters) = entry {
+            timed_out.append(waiters);
+          }
+        }
+        fresh
+      });
+      match entries.get_mut(key) {
+        Some(IdempotencyEntry::Done(_, result)) => Reservation::Replay(result.clone()),
+        Some(IdempotencyEntry::Running(_, waiters)) => {
+          waiters.push(Box::new(waiter));
+          Reservation::Wait
+        }
+        None => {
+          entries.insert(
+            key.clone(),
+            IdempotencyEntry::Running(Instant::now(), Vec::new()),
+          );
+          Reservation::Run
+        }
+      }
+    };
+    // resolved outside of the lock since the waiters answer the webview
+    for waiter in timed_out {
+      waiter(Err(JsonValue::String(IDEMPOTENCY_TIMEOUT_ERROR.into())));
+    }
+    reservation
+  }
+
+  /// Stores the result of the first execution for the given key and resolves the retries waiting for it.
+  pub(crate) fn complete(&self, key: IdempotencyKey, response: &InvokeResponse) {
+    let result = match response {
+      InvokeResponse::Ok(body) => Ok(body.clone()),
+      InvokeResponse::Err(e) => Err(e.0.clone()),
+    };
+    let previous = self
+      .entries
+      .lock()
+      .unwrap()
+      .insert(key, IdempotencyEntry::Done(Instant::now(), result.clone()));
+    if let Some(IdempotencyEntry::Running(_, waiters)) = previous {
+      for waiter in waiters {
+        waiter(result.clone());
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    sync::{
+      atomic::{AtomicUsize, Ordering},
+      Arc,
+    },
+    time::Duration,
+  };
+
+  use http::HeaderMap;
+  use serde_json::json;
+
+  use super::{
+    IdempotencyCache, Reservation, ResponseCache, IDEMPOTENCY_KEY_HEADER, IDEMPOTENCY_TIMEOUT_ERROR,
+  };
+  use crate::{
+    command::Origin,
+    ipc::{CallbackFn, InvokeBody},
+    test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+    window::InvokeRequest,
+    WindowBuilder,
+  };
+
+  fn request(body: InvokeBody) -> InvokeRequest {
+    InvokeRequest {
+      cmd: "expensive".into(),
+      callback: CallbackFn(0),
+      error: CallbackFn(1),
+      body,
+      headers: Default::default(),
+    }
+  }
+
+  #[test]
+  fn cached_response_skips_command() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_ = runs.clone();
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority.cache_command("expensive", Duration::from_secs(60));
+      })
+      .invoke_handler(move |invoke| {
+        let run = runs_.fetch_add(1, Ordering::SeqCst) + 1;
+        invoke.resolver.resolve(run);
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let first = get_ipc_response(&window, request(json!({ "id": 1 }).into()))
+      .unwrap()
+      .deserialize::<usize>()
+      .unwrap();
+    let second = get_ipc_response(&window, request(json!({ "id": 1 }).into()))
+      .unwrap()
+      .deserialize::<usize>()
+      .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // different arguments and raw payloads are not served from the cache
+    get_ipc_response(&window, request(json!({ "id": 2 }).into())).unwrap();
+    get_ipc_response(&window, request(vec![1, 2, 3].into())).unwrap();
+    get_ipc_response(&window, request(vec![1, 2, 3].into())).unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 4);
+  }
+
+  #[test]
+  fn cached_response_not_shared() {
+    use crate::{
+      command::RuntimeAuthorityBuilder, plugin::Builder as PluginBuilder, test::MockRuntime,
+    };
+    use tauri_utils::acl::ExecutionContext;
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_ = runs.clone();
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        *authority = RuntimeAuthorityBuilder::new()
+          .allow_command("plugin:files|list", ExecutionContext::Local, ["a"])
+          .build()
+          .unwrap();
+        authority.cache_command("plugin:files|list", Duration::from_secs(60));
+        authority.cache_command("expensive", Duration::from_secs(60));
+      })
+      .plugin(
+        PluginBuilder::<MockRuntime>::new("files")
+          .invoke_handler(|invoke| {
+            invoke.resolver.resolve(["a.txt"]);
+            true
+          })
+          .build(),
+      )
+      .invoke_handler(move |invoke| {
+        let run = runs_.fetch_add(1, Ordering::SeqCst) + 1;
+        invoke.resolver.resolve(run);
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let a = WindowBuilder::new(&app, "a", Default::default())
+      .build()
+      .unwrap();
+    let b = WindowBuilder::new(&app, "b", Default::default())
+      .build()
+      .unwrap();
+
+    // each window runs the command
+    get_ipc_response(&a, request(json!({ "id": 1 }).into())).unwrap();
+    get_ipc_response(&b, request(json!({ "id": 1 }).into())).unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    // the response cached for an allowed window is not served to a denied one
+    let list = |window| {
+      get_ipc_response(
+        window,
+        InvokeRequest {
+          cmd: "plugin:files|list".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({}).into(),
+          headers: Default::default(),
+        },
+      )
+    };
+    assert!(list(&a).is_ok());
+    assert!(list(&a).is_ok());
+    assert_eq!(list(&b).unwrap_err(), json!("NOT ALLOWED"));
+  }
+
+  #[test]
+  fn expired_responses_evicted() {
+    let mut cache = ResponseCache::default();
+    cache.enable("expensive".into(), Duration::ZERO);
+    for id in 0..3 {
+      let key = cache
+        .key(
+          "expensive",
+          "main",
+          &Origin::Local,
+          &json!({ "id": id }).into(),
+        )
+        .unwrap();
+      cache.insert(key, json!(id).into());
+    }
+    assert_eq!(cache.len(), 1);
+  }
+
+  #[test]
+  fn idempotent_command_runs_once() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_ = runs.clone();
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority.idempotent_command("charge", Duration::from_secs(60));
+      })
+      .invoke_handler(move |invoke| {
+        let run = runs_.fetch_add(1, Ordering::SeqCst) + 1;
+        invoke.resolver.resolve(json!({ "receipt": run }));
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let charge = |key: Option<&str>| {
+      let mut headers = HeaderMap::new();
+      if let Some(key) = key {
+        headers.insert(IDEMPOTENCY_KEY_HEADER, key.parse().unwrap());
+      }
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: "charge".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({ "amount": 10 }).into(),
+          headers,
+        },
+      )
+      .unwrap()
+      .deserialize::<serde_json::Value>()
+      .unwrap()
+    };
+
+    let first = charge(Some("order-1"));
+    let retry = charge(Some("order-1"));
+    assert_eq!(first, retry);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // a new key or no key at all runs the command again
+    assert_ne!(charge(Some("order-2")), first);
+    charge(None);
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+  }
+
+  #[test]
+  fn concurrent_retries_wait_for_first_execution() {
+    use crate::{
+      ipc::{InvokeResolver, InvokeResponse},
+      test::MockRuntime,
+    };
+    use std::sync::Mutex;
+
+    let pending: Arc<Mutex<Vec<InvokeResolver<MockRuntime>>>> = Default::default();
+    let pending_ = pending.clone();
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority.idempotent_command("charge", Duration::from_secs(60));
+      })
+      .invoke_handler(move |invoke| {
+        // the first execution is still running when the retries arrive
+        pending_.lock().unwrap().push(invoke.resolver);
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let main = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let other = WindowBuilder::new(&app, "other", Default::default())
+      .build()
+      .unwrap();
+
+    let responses: Arc<Mutex<Vec<(String, InvokeResponse)>>> = Default::default();
+    let charge = |window: &crate::Window<MockRuntime>| {
+      let mut headers = HeaderMap::new();
+      headers.insert(IDEMPOTENCY_KEY_HEADER, "order-1".parse().unwrap());
+      let responses = responses.clone();
+      window.clone().on_message(
+        InvokeRequest {
+          cmd: "charge".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({ "amount": 10 }).into(),
+          headers,
+        },
+        Box::new(move |window, _cmd, response, _callback, _error| {
+          responses
+            .lock()
+            .unwrap()
+            .push((window.label().to_string(), response));
+        }),
+      );
+    };
+
+    charge(&main);
+    charge(&main);
+    assert_eq!(pending.lock().unwrap().len(), 1);
+    assert!(responses.lock().unwrap().is_empty());
+
+    // the key is scoped to the window
+    charge(&other);
+    assert_eq!(pending.lock().unwrap().len(), 2);
+
+    let first = pending.lock().unwrap().remove(0);
+    first.resolve(json!({ "receipt": 1 }));
+    let responses = responses.lock().unwrap();
+    assert_eq!(responses.len(), 2);
+    for (window, response) in responses.iter() {
+      assert_eq!(window, "main");
+      match response {
+        InvokeResponse::Ok(body) => assert_eq!(
+          body.clone().deserialize::<serde_json::Value>().unwrap(),
+          json!({ "receipt": 1 })
+        ),
+        InvokeResponse::Err(e) => panic!("unexpected error {e:?}"),
+      }
+    }
+  }
+
+  #[test]
+  fn running_idempotent_command_times_out() {
+    use std::sync::Mutex;
+
+    let mut cache = IdempotencyCache::default();
+    cache.enable("charge".into(), Duration::from_millis(100));
+    let mut headers = HeaderMap::new();
+    headers.insert(IDEMPOTENCY_KEY_HEADER, "order-1".parse().unwrap());
+    let key = cache
+      .key("charge", "main", &Origin::Local, &headers)
+      .unwrap();
+
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let waiter = || {
+      let results = results.clone();
+      move |result: super::IdempotentResult| results.lock().unwrap().push(result.unwrap_err())
+    };
+    assert!(matches!(cache.reserve(&key, waiter()), Reservation::Run));
+    assert!(matches!(cache.reserve(&key, waiter()), Reservation::Wait));
+    assert!(results.lock().unwrap().is_empty());
+
+    // the first execution never completed, the waiting retry fails and the key is reserved again
+    std::thread::sleep(Duration::from_millis(150));
+    assert!(matches!(cache.reserve(&key, waiter()), Reservation::Run));
+    assert_eq!(
+      *results.lock().unwrap(),
+      vec![json!(IDEMPOTENCY_TIMEOUT_ERROR)]
+    );
+  }
+}
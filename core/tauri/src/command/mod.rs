@@ -8,17 +8,54 @@
 //! attribute macro along the way and used by [`crate::generate_handler`] macro.
 
 use crate::{
-  ipc::{InvokeBody, InvokeError, InvokeMessage},
+  ipc::{InvokeBody, InvokeError, InvokeMessage, InvokeResolver, IpcResponse},
+  runtime::Dispatch,
   Runtime,
 };
 use serde::{
   de::{Error, Visitor},
   Deserialize, Deserializer,
 };
+use std::future::Future;
 
+mod args;
 mod authority;
+pub(crate) mod cache;
+mod trie;
 
-pub use authority::{CommandScope, GlobalScope, Origin, RuntimeAuthority};
+pub use args::{
+  AllowedWindows, BoundedLatLng, ByteSize, CommandSuffixOp, CountryCode, Deadline,
+  DefaultFromState, DroppedFiles, EnvArgs, Extract, FlagSet, Flags, GeoBounds, GlobArg, GlobLimits,
+  Header, HeaderKey, Headers, InvokeContext, JsonPath, JsonPathKey, LatLng, LogLevel, Matrix,
+  MessageCatalog, MessageKey, NotificationPermission, NotificationPermissionSource, OrDefault,
+  RawBody, ScopedString, SettingKey, SettingsStore, SortDirection, SortField, SortSpec,
+  StateMachine, Temperature, Transition, UniqueEnumSet, UnknownVariant, WebviewUrl, WindowLabel,
+  WithUnknown,
+};
+#[cfg(feature = "time")]
+pub use args::{Birthdate, Clock, MinimumAge, TimeOrNow};
+#[cfg(feature = "cursor")]
+pub use args::{Cursor, CursorKey};
+#[cfg(feature = "grant")]
+pub use args::{Grant, GrantError, GrantKey};
+#[cfg(feature = "mime-type")]
+pub use args::{MimeAllowList, MimeType};
+#[cfg(feature = "unicode-normalization")]
+pub use args::{NormalizationOptions, NormalizedStr};
+#[cfg(feature = "regex")]
+pub use args::{RegexArg, RegexLimits};
+#[cfg(feature = "metrics")]
+pub use authority::ScopeCacheMetrics;
+pub use authority::{
+  AccessError, AclWarning, Authority, AuthorityBuildError, CommandInterceptor, CommandSchema,
+  CommandScope, DecisionOutcome, DecisionRecord, Divergence, FeatureRegistry, GlobalScope,
+  InstallError, MergeError, OptionalCommandScope, OptionalGlobalScope, Origin, OriginMatcher,
+  PathScope, PatternError, PluginAcl, PluginId, Principal, RateLimiter, RuntimeAuthority,
+  RuntimeAuthorityBuilder, ScopeAliasError, ScopeDecision, ScopeEntryValidator, ScopeError,
+  ScopeMatch, StringScope, ToggleStore,
+};
+#[cfg(any(test, feature = "test"))]
+pub use authority::{ArgumentAccess, ScopeAccess, ScopeAccessKind};
 use tauri_utils::acl::resolved::ResolvedCommand;
 
 /// Represents a custom command.
@@ -36,6 +73,14 @@ pub struct CommandItem<'a, R: Runtime> {
   pub acl: &'a Option<ResolvedCommand>,
 }
 
+impl<'a, R: Runtime> Clone for CommandItem<'a, R> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'a, R: Runtime> Copy for CommandItem<'a, R> {}
+
 /// Trait implemented by command arguments to derive a value from a [`CommandItem`].
 ///
 /// # Command Arguments
@@ -58,6 +103,67 @@ pub trait CommandArg<'de, R: Runtime>: Sized {
   fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError>;
 }
 
+/// Trait implemented by command arguments that need async work to derive their value,
+/// e.g. validating a token against a store before the command runs.
+///
+/// The arguments of async commands are awaited in order before the command is called,
+/// so async arguments can only be used by async commands and must be [`Send`].
+/// Every [`CommandArg`] is an [`AsyncCommandArg`] that is ready immediately.
+///
+/// Implement it for the runtime of the app, e.g. `tauri::Wry`: an implementation generic over
+/// the runtime would overlap with the one for [`CommandArg`] types, since other crates may implement
+/// [`CommandArg`] for your type with their own runtime.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::{
+///   command::{AsyncCommandArg, CommandArg, CommandItem},
+///   ipc::InvokeError,
+///   Wry,
+/// };
+/// use futures_util::future::BoxFuture;
+///
+/// struct Session {
+///   user: String,
+/// }
+///
+/// impl<'de> AsyncCommandArg<'de, Wry> for Session {
+///   type Future = BoxFuture<'de, Result<Self, InvokeError>>;
+///
+///   fn from_command_async(command: CommandItem<'de, Wry>) -> Self::Future {
+///     Box::pin(async move {
+///       let token = String::from_command(command)?;
+///       // look the token up in a store
+///       Ok(Session { user: token })
+///     })
+///   }
+/// }
+///
+/// #[tauri::command]
+/// async fn profile(session: Session) -> Result<String, ()> {
+///   Ok(session.user)
+/// }
+/// ```
+pub trait AsyncCommandArg<'de, R: Runtime>: Sized {
+  /// The future deriving the argument.
+  type Future: Future<Output = Result<Self, InvokeError>>;
+
+  /// Derives an instance of `Self` from the [`CommandItem`].
+  ///
+  /// If the derivation fails, the command is not called and the message is rejected.
+  fn from_command_async(command: CommandItem<'de, R>) -> Self::Future;
+}
+
+impl<'de, R: Runtime, T: CommandArg<'de, R>> AsyncCommandArg<'de, R> for T {
+  type Future = std::future::Ready<Result<T, InvokeError>>;
+
+  #[inline(always)]
+  fn from_command_async(command: CommandItem<'de, R>) -> Self::Future {
+    std::future::ready(T::from_command(command))
+  }
+}
+
 /// Automatically implement [`CommandArg`] for any type that can be deserialized.
 impl<'de, D: Deserialize<'de>, R: Runtime> CommandArg<'de, R> for D {
   fn from_command(command: CommandItem<'de, R>) -> Result<D, InvokeError> {
@@ -65,15 +171,179 @@ impl<'de, D: Deserialize<'de>, R: Runtime> CommandArg<'de, R> for D {
     let arg = command.key;
     #[cfg(feature = "tracing")]
     let _span = tracing::trace_span!("ipc::request::deserialize_arg", arg = arg).entered();
-    Self::deserialize(command).map_err(|e| crate::Error::InvalidArgs(name, arg, e).into())
+    Self::deserialize(command).map_err(|e| match command.message.payload {
+      InvokeBody::Raw(_) if !arg.is_empty() => command.payload_type_mismatch().into(),
+      _ => crate::Error::InvalidArgs(name, arg, e).into(),
+    })
+  }
+}
+
+impl<'a, R: Runtime> CommandItem<'a, R> {
+  /// Rejects values nested deeper than [`RuntimeAuthority::set_max_payload_depth`] before deserializing them.
+  fn check_depth(&self, value: &serde_json::Value) -> Result<(), serde_json::Error> {
+    let max_depth = self
+      .message
+      .window
+      .manager
+      .runtime_authority
+      .max_payload_depth();
+    if exceeds_depth(value, max_depth) {
+      return Err(serde_json::Error::custom(format!(
+        "command {} argument {} is nested deeper than the maximum depth of {}",
+        self.name, self.key, max_depth
+      )));
+    }
+    Ok(())
+  }
+
+  /// Rejects object keys that are not declared fields of the struct
+  /// if enabled with [`RuntimeAuthority::deny_unknown_argument_fields`].
+  fn check_fields(
+    &self,
+    value: &serde_json::Value,
+    fields: &[&str],
+  ) -> Result<(), serde_json::Error> {
+    let enabled = self
+      .message
+      .window
+      .manager
+      .runtime_authority
+      .unknown_argument_fields_denied(self.message.command());
+    if let (true, serde_json::Value::Object(map)) = (enabled, value) {
+      if let Some(field) = map.keys().find(|k| !fields.contains(&k.as_str())) {
+        return Err(serde_json::Error::custom(format!(
+          "command {} argument {} has unknown field `{}`",
+          self.name, self.key, field
+        )));
+      }
+    }
+    Ok(())
+  }
+
+  /// The error returned when a named argument is read from a bytes payload.
+  fn payload_type_mismatch(&self) -> crate::Error {
+    crate::Error::PayloadTypeMismatch {
+      command: self.name,
+      key: self.key,
+      expected: "json",
+    }
+  }
+
+  /// Looks up the argument in the payload, trying the other case convention of the key
+  /// if enabled with [`RuntimeAuthority::normalize_argument_keys`] and the exact key is missing.
+  fn lookup<'v>(&self, payload: &'v serde_json::Value) -> Option<&'v serde_json::Value> {
+    let value = payload.get(self.key).or_else(|| {
+      self
+        .message
+        .window
+        .manager
+        .runtime_authority
+        .argument_key_normalization_enabled(self.message.command())
+        .then(|| alternate_case(self.key))
+        .flatten()
+        .and_then(|key| payload.get(key))
+    });
+    if value.is_some() {
+      self.record_access();
+    }
+    value
+  }
+
+  /// Records that the argument was resolved, see [`crate::test::take_argument_accesses`].
+  fn record_access(&self) {
+    #[cfg(any(test, feature = "test"))]
+    self
+      .message
+      .window
+      .manager
+      .runtime_authority
+      .record_argument_access(self.name, self.key);
+  }
+
+  /// Reads the argument from the query parameters of the invoking URL,
+  /// if enabled with [`RuntimeAuthority::allow_query_fallback`] and the origin is remote.
+  ///
+  /// Parameters that are valid JSON are parsed, otherwise they are used as strings.
+  fn query_fallback(&self) -> Option<serde_json::Value> {
+    let window = &self.message.window;
+    if !window
+      .manager
+      .runtime_authority
+      .query_fallback_enabled(self.message.command())
+    {
+      return None;
+    }
+    let url = window.window.dispatcher.url().ok()?;
+    if window.is_local_url(&url) {
+      return None;
+    }
+    let value = url
+      .query_pairs()
+      .find(|(key, _)| key == self.key)
+      .map(|(_, value)| {
+        serde_json::from_str(&value).unwrap_or_else(|_| serde_json::Value::String(value.into()))
+      });
+    if value.is_some() {
+      self.record_access();
+    }
+    value
   }
 }
 
+/// Converts a `snake_case` key to `camelCase` and a `camelCase` key to `snake_case`.
+///
+/// Returns `None` if the key is the same in both conventions.
+fn alternate_case(key: &str) -> Option<String> {
+  let mut converted = String::with_capacity(key.len() + 4);
+  if key.contains('_') {
+    let mut upper = false;
+    for c in key.chars() {
+      match c {
+        '_' if !converted.is_empty() => upper = true,
+        c if upper => {
+          converted.extend(c.to_uppercase());
+          upper = false;
+        }
+        c => converted.push(c),
+      }
+    }
+  } else if key.chars().any(char::is_uppercase) {
+    for c in key.chars() {
+      if c.is_uppercase() {
+        if !converted.is_empty() {
+          converted.push('_');
+        }
+        converted.extend(c.to_lowercase());
+      } else {
+        converted.push(c);
+      }
+    }
+  }
+  (!converted.is_empty() && converted != key).then_some(converted)
+}
+
+/// Checks if the arrays and objects of the value are nested deeper than `max_depth`, without recursing.
+fn exceeds_depth(value: &serde_json::Value, max_depth: usize) -> bool {
+  let mut stack = vec![(value, 0)];
+  while let Some((value, depth)) = stack.pop() {
+    let children: Box<dyn Iterator<Item = &serde_json::Value>> = match value {
+      serde_json::Value::Array(items) => Box::new(items.iter()),
+      serde_json::Value::Object(map) => Box::new(map.values()),
+      _ => continue,
+    };
+    if depth + 1 > max_depth {
+      return true;
+    }
+    stack.extend(children.map(|child| (child, depth + 1)));
+  }
+  false
+}
+
 /// Pass the result of [`serde_json::Value::get`] into [`serde_json::Value`]'s deserializer.
 ///
 /// Returns an error if the [`CommandItem`]'s key does not exist in the value.
 macro_rules! pass {
-  ($fn:ident, $($arg:ident: $argt:ty),+) => {
+  ($fn:ident, $($arg:ident: $argt:ty),+ $(; $check:ident($($check_arg:ident),*))?) => {
     fn $fn<V: Visitor<'de>>(self, $($arg: $argt),*) -> Result<V::Value, Self::Error> {
       if self.key.is_empty() {
         return Err(serde_json::Error::custom(format!(
@@ -83,22 +353,36 @@ macro_rules! pass {
       }
 
       match &self.message.payload {
-        InvokeBody::Raw(_body) => {
-          Err(serde_json::Error::custom(format!(
-            "command {} expected a value for key {} but the IPC call used a bytes payload",
-            self.name, self.key
-          )))
-        }
+        InvokeBody::Raw(_body) => Err(serde_json::Error::custom(self.payload_type_mismatch())),
         InvokeBody::Json(v) => {
-          match v.get(self.key) {
-            Some(value) => value.$fn($($arg),*),
-            None => {
-              Err(serde_json::Error::custom(format!(
+          // the query parameters go through the same checks as the payload
+          let (is_null, result) = match self.lookup(v) {
+            Some(value) => {
+              self.check_depth(value)?;
+              $(self.$check(value, $($check_arg),*)?;)?
+              (value.is_null(), value.$fn($($arg),*))
+            }
+            None => match self.query_fallback() {
+              Some(value) => {
+                self.check_depth(&value)?;
+                $(self.$check(&value, $($check_arg),*)?;)?
+                (value.is_null(), value.$fn($($arg),*))
+              }
+              None => return Err(serde_json::Error::custom(format!(
                 "command {} missing required key {}",
                 self.name, self.key
-              )))
-            }
-          }
+              ))),
+            },
+          };
+          // tell an explicit `null` apart from a missing key
+          result.map_err(|e| if is_null {
+            serde_json::Error::custom(format!(
+              "command {} got null for required key {}",
+              self.name, self.key
+            ))
+          } else {
+            e
+          })
         }
       }
     }
@@ -110,6 +394,7 @@ macro_rules! pass {
 /// If the key doesn't exist, an error will be returned if the deserialized type is not expecting
 /// an optional item. If the key does exist, the value will be called with
 /// [`Value`](serde_json::Value)'s [`Deserializer`] implementation.
+/// An explicit `null` rejected by a non-optional item is reported apart from a missing key.
 impl<'de, R: Runtime> Deserializer<'de> for CommandItem<'de, R> {
   type Error = serde_json::Error;
 
@@ -133,13 +418,19 @@ impl<'de, R: Runtime> Deserializer<'de> for CommandItem<'de, R> {
 
   fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
     match &self.message.payload {
-      InvokeBody::Raw(_body) => Err(serde_json::Error::custom(format!(
-        "command {} expected a value for key {} but the IPC call used a bytes payload",
-        self.name, self.key
-      ))),
-      InvokeBody::Json(v) => match v.get(self.key) {
-        Some(value) => value.deserialize_option(visitor),
-        None => visitor.visit_none(),
+      InvokeBody::Raw(_body) => Err(serde_json::Error::custom(self.payload_type_mismatch())),
+      InvokeBody::Json(v) => match self.lookup(v) {
+        Some(value) => {
+          self.check_depth(value)?;
+          value.deserialize_option(visitor)
+        }
+        None => match self.query_fallback() {
+          Some(value) => {
+            self.check_depth(&value)?;
+            value.deserialize_option(visitor)
+          }
+          None => visitor.visit_none(),
+        },
       },
     }
   }
@@ -163,7 +454,8 @@ impl<'de, R: Runtime> Deserializer<'de> for CommandItem<'de, R> {
     deserialize_struct,
     name: &'static str,
     fields: &'static [&'static str],
-    visitor: V
+    visitor: V;
+    check_fields(fields)
   );
 
   pass!(
@@ -177,20 +469,70 @@ impl<'de, R: Runtime> Deserializer<'de> for CommandItem<'de, R> {
   pass!(deserialize_ignored_any, visitor: V);
 }
 
+/// Resolves the invoke with the value inline, on the current thread and without spawning a task.
+///
+/// This is the path synchronous commands returning an [`IpcResponse`] go through,
+/// for invoke handlers written without the command macro.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// tauri::Builder::default().invoke_handler(|invoke| {
+///   let greeting = format!("hello from {}", invoke.message.command());
+///   tauri::command::respond_blocking(invoke.resolver, greeting);
+///   true
+/// });
+/// ```
+pub fn respond_blocking<R: Runtime, T: IpcResponse>(resolver: InvokeResolver<R>, value: T) {
+  private::ResponseTag.block(value, resolver)
+}
+
 /// [Autoref-based stable specialization](https://github.com/dtolnay/case-studies/blob/master/autoref-specialization/README.md)
 ///
 /// Nothing in this module is considered stable.
 #[doc(hidden)]
 pub mod private {
+  use super::{AsyncCommandArg, CommandItem};
   use crate::{
-    ipc::{InvokeBody, InvokeError, InvokeResolver, IpcResponse},
+    ipc::{EventStream, InvokeBody, InvokeError, InvokeResolver, IpcResponse},
     Runtime,
   };
-  use futures_util::{FutureExt, TryFutureExt};
-  use std::future::Future;
+  use futures_util::{FutureExt, Stream, TryFutureExt};
+  use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+  };
   #[cfg(feature = "tracing")]
   pub use tracing;
 
+  // ===== impl AsyncCommandArg =====
+
+  /// The future of an [`AsyncCommandArg`], whose output names the argument type
+  /// so it is inferred from the command parameter.
+  ///
+  /// The argument bound is only on the [`Future`] implementation, since async commands
+  /// borrowing the message require the future to be [`Send`] for every lifetime.
+  pub struct ArgFuture<F, T>(F, PhantomData<fn() -> T>);
+
+  impl<F: Future<Output = Result<T, InvokeError>>, T> Future for ArgFuture<F, T> {
+    type Output = Result<T, InvokeError>;
+
+    #[inline(always)]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+      // SAFETY: the inner future is never moved out of the pinned wrapper
+      unsafe { self.map_unchecked_mut(|future| &mut future.0) }.poll(cx)
+    }
+  }
+
+  #[inline(always)]
+  pub fn from_command_async<'de, R: Runtime, T: AsyncCommandArg<'de, R>>(
+    command: CommandItem<'de, R>,
+  ) -> ArgFuture<T::Future, T> {
+    ArgFuture(T::from_command_async(command), PhantomData)
+  }
+
   // ===== impl IpcResponse =====
 
   pub struct ResponseTag;
@@ -297,6 +639,98 @@ pub mod private {
     }
   }
 
+  // ===== Stream<Item = Result<InvokeBody, InvokeError>> =====
+
+  pub struct StreamTag;
+
+  pub trait StreamKind {
+    #[inline(always)]
+    fn async_kind(&self) -> StreamTag {
+      StreamTag
+    }
+  }
+  impl<S: Stream<Item = Result<InvokeBody, InvokeError>>> StreamKind for S {}
+
+  impl StreamTag {
+    #[inline(always)]
+    pub fn future<S>(self, value: S) -> impl Future<Output = Result<InvokeBody, InvokeError>>
+    where
+      S: Stream<Item = Result<InvokeBody, InvokeError>> + Send + 'static,
+    {
+      crate::ipc::forward_stream(value)
+    }
+  }
+
+  // ===== Future<Output = Stream<Item = Result<InvokeBody, InvokeError>>> =====
+
+  pub struct StreamFutureTag;
+
+  pub trait StreamFutureKind {
+    #[inline(always)]
+    fn async_kind(&self) -> StreamFutureTag {
+      StreamFutureTag
+    }
+  }
+  impl<S: Stream<Item = Result<InvokeBody, InvokeError>>, F: Future<Output = S>> StreamFutureKind
+    for &F
+  {
+  }
+
+  impl StreamFutureTag {
+    #[inline(always)]
+    pub fn future<S, F>(self, value: F) -> impl Future<Output = Result<InvokeBody, InvokeError>>
+    where
+      S: Stream<Item = Result<InvokeBody, InvokeError>> + Send + 'static,
+      F: Future<Output = S> + Send + 'static,
+    {
+      value.then(crate::ipc::forward_stream)
+    }
+  }
+
+  // ===== EventStream =====
+
+  pub struct EventStreamTag;
+
+  pub trait EventStreamKind {
+    #[inline(always)]
+    fn async_kind(&self) -> EventStreamTag {
+      EventStreamTag
+    }
+  }
+  impl EventStreamKind for EventStream {}
+
+  impl EventStreamTag {
+    #[inline(always)]
+    pub fn future(
+      self,
+      value: EventStream,
+    ) -> impl Future<Output = Result<InvokeBody, InvokeError>> {
+      value.forward()
+    }
+  }
+
+  // ===== Future<Output = EventStream> =====
+
+  pub struct EventStreamFutureTag;
+
+  pub trait EventStreamFutureKind {
+    #[inline(always)]
+    fn async_kind(&self) -> EventStreamFutureTag {
+      EventStreamFutureTag
+    }
+  }
+  impl<F: Future<Output = EventStream>> EventStreamFutureKind for &F {}
+
+  impl EventStreamFutureTag {
+    #[inline(always)]
+    pub fn future<F>(self, value: F) -> impl Future<Output = Result<InvokeBody, InvokeError>>
+    where
+      F: Future<Output = EventStream> + Send + 'static,
+    {
+      value.then(EventStream::forward)
+    }
+  }
+
   // ===== Future<Output = Result<impl Serialize, impl Into<InvokeError>>> =====
 
   pub struct ResultFutureTag;
@@ -327,3 +761,513 @@ pub mod private {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use serde_json::{json, Value as JsonValue};
+
+  use crate::ipc::{InvokeBody, InvokeError};
+
+  use super::args::tests::{extract, invoke_message};
+  use super::CommandArg;
+  use crate::test::{mock_app, mock_builder, mock_context, noop_assets, MockRuntime};
+
+  /// A session resolved from the token argument after an await point.
+  struct Session(String);
+
+  impl<'de> super::AsyncCommandArg<'de, MockRuntime> for Session {
+    type Future = futures_util::future::BoxFuture<'de, Result<Self, InvokeError>>;
+
+    fn from_command_async(command: super::CommandItem<'de, MockRuntime>) -> Self::Future {
+      Box::pin(async move {
+        let token = String::from_command(command)?;
+        crate::async_runtime::spawn(async {}).await.unwrap();
+        match token.as_str() {
+          "secret" => Ok(Session("tauri".into())),
+          _ => Err(InvokeError::from_anyhow(anyhow::anyhow!(
+            "invalid token {token}"
+          ))),
+        }
+      })
+    }
+  }
+
+  #[test]
+  fn async_command_arg() {
+    use crate::{ipc::CallbackFn, test::get_ipc_response, window::InvokeRequest, WindowBuilder};
+
+    #[crate::command(root = "crate")]
+    async fn greet_session(token: Session, greeting: &str) -> Result<String, ()> {
+      Ok(format!("{greeting}, {}!", token.0))
+    }
+
+    let app = mock_builder()
+      .invoke_handler(crate::generate_handler![greet_session])
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let greet = |body: JsonValue| {
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: "greet_session".into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: body.into(),
+          headers: Default::default(),
+        },
+      )
+    };
+
+    assert_eq!(
+      greet(json!({ "token": "secret", "greeting": "Hello" }))
+        .unwrap()
+        .deserialize::<String>()
+        .unwrap(),
+      "Hello, tauri!"
+    );
+    assert_eq!(
+      greet(json!({ "token": "guess", "greeting": "Hello" })).unwrap_err(),
+      "invalid token guess"
+    );
+    // synchronous arguments are still extracted after the async ones
+    assert_eq!(
+      greet(json!({ "token": "secret" })).unwrap_err()["data"],
+      json!({ "command": "greet_session", "arg": "greeting" })
+    );
+  }
+
+  fn nested(depth: usize) -> JsonValue {
+    (0..depth).fold(json!(1), |value, _| json!([value]))
+  }
+
+  #[test]
+  fn payload_depth_limit() {
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| authority.set_max_payload_depth(4))
+      .build(mock_context(noop_assets()))
+      .unwrap();
+
+    let message = invoke_message(&app, "test", json!({ "value": nested(4) }));
+    assert!(extract::<JsonValue>(&message, "value").is_ok());
+    assert!(extract::<Option<JsonValue>>(&message, "value").is_ok());
+
+    let message = invoke_message(&app, "test", json!({ "value": nested(5) }));
+    let err = extract::<JsonValue>(&message, "value").unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .contains("maximum depth of 4"));
+    assert!(extract::<Option<JsonValue>>(&message, "value").is_err());
+  }
+
+  #[test]
+  fn query_fallback() {
+    #[derive(Debug, serde::Deserialize)]
+    struct Size {
+      width: u32,
+    }
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| authority.allow_query_fallback("open"))
+      .build(mock_context(noop_assets()))
+      .unwrap();
+
+    let message = invoke_message(&app, "open", json!({ "name": "body" }));
+    message.window().navigate(
+      "https://remote.example/page?id=42&name=query&tag=a%20b"
+        .parse()
+        .unwrap(),
+    );
+    assert_eq!(extract::<u32>(&message, "id").unwrap(), 42);
+    assert_eq!(extract::<String>(&message, "tag").unwrap(), "a b");
+    assert_eq!(extract::<Option<u32>>(&message, "id").unwrap(), Some(42));
+    // the body takes precedence
+    assert_eq!(extract::<String>(&message, "name").unwrap(), "body");
+    assert!(extract::<u32>(&message, "missing").is_err());
+
+    // not enabled for this command
+    let message = invoke_message(&app, "close", json!({}));
+    assert!(extract::<u32>(&message, "id").is_err());
+
+    // local origins never fall back
+    let message = invoke_message(&app, "open", json!({}));
+    message
+      .window()
+      .navigate("tauri://localhost/?id=42".parse().unwrap());
+    assert!(extract::<u32>(&message, "id").is_err());
+
+    // the query parameters go through the payload checks
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority.allow_query_fallback("open");
+        authority.set_max_payload_depth(2);
+        authority.deny_unknown_argument_fields("open");
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let message = invoke_message(&app, "open", json!({}));
+    message.window().navigate(
+      "https://remote.example/page?deep=[[[1]]]&size={\"width\":1,\"depth\":3}&id=null&valid={\"width\":1}"
+        .parse()
+        .unwrap(),
+    );
+    let message_of = |err: InvokeError| err.0["message"].as_str().unwrap().to_string();
+    assert!(
+      message_of(extract::<JsonValue>(&message, "deep").unwrap_err())
+        .contains("maximum depth of 2")
+    );
+    assert!(extract::<Option<JsonValue>>(&message, "deep").is_err());
+    assert!(message_of(extract::<Size>(&message, "size").unwrap_err())
+      .contains("argument size has unknown field `depth`"));
+    assert!(message_of(extract::<u32>(&message, "id").unwrap_err())
+      .contains("got null for required key id"));
+    assert_eq!(extract::<Size>(&message, "valid").unwrap().width, 1);
+  }
+
+  #[test]
+  fn payload_type_mismatch() {
+    let app = mock_app();
+    let message = invoke_message(&app, "upload", vec![1, 2, 3]);
+    let expected = json!({
+      "type": "PayloadTypeMismatch",
+      "data": { "command": "test", "key": "name", "expected": "json" },
+      "message": "command test expected a value for key name but the IPC call used a bytes payload",
+    });
+    assert_eq!(extract::<String>(&message, "name").unwrap_err().0, expected);
+    assert_eq!(
+      extract::<Option<String>>(&message, "name").unwrap_err().0,
+      expected
+    );
+
+    // missing keys are still reported as invalid args
+    let message = invoke_message(&app, "upload", json!({}));
+    let err = extract::<String>(&message, "name").unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .contains("missing required key name"));
+  }
+
+  #[test]
+  fn alternate_case() {
+    assert_eq!(super::alternate_case("my_param").unwrap(), "myParam");
+    assert_eq!(super::alternate_case("myParam").unwrap(), "my_param");
+    assert_eq!(super::alternate_case("a_b_c").unwrap(), "aBC");
+    assert_eq!(super::alternate_case("name"), None);
+  }
+
+  #[test]
+  fn argument_key_normalization() {
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| authority.normalize_argument_keys("open"))
+      .build(mock_context(noop_assets()))
+      .unwrap();
+
+    // snake_case key in the payload, camelCase key in the command
+    let message = invoke_message(&app, "open", json!({ "my_param": 1 }));
+    assert_eq!(extract::<u32>(&message, "myParam").unwrap(), 1);
+    assert_eq!(
+      extract::<Option<u32>>(&message, "myParam").unwrap(),
+      Some(1)
+    );
+
+    // camelCase key in the payload, snake_case key in the command
+    let message = invoke_message(&app, "open", json!({ "myParam": 2 }));
+    assert_eq!(extract::<u32>(&message, "my_param").unwrap(), 2);
+
+    // the exact key takes precedence
+    let message = invoke_message(&app, "open", json!({ "myParam": 3, "my_param": 4 }));
+    assert_eq!(extract::<u32>(&message, "myParam").unwrap(), 3);
+    assert_eq!(extract::<u32>(&message, "my_param").unwrap(), 4);
+
+    // not enabled for this command
+    let message = invoke_message(&app, "close", json!({ "my_param": 1 }));
+    assert!(extract::<u32>(&message, "myParam").is_err());
+    assert_eq!(extract::<Option<u32>>(&message, "myParam").unwrap(), None);
+  }
+
+  #[test]
+  fn deny_unknown_argument_fields() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Size {
+      width: u32,
+      height: u32,
+    }
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| authority.deny_unknown_argument_fields("resize"))
+      .build(mock_context(noop_assets()))
+      .unwrap();
+
+    let message = invoke_message(
+      &app,
+      "resize",
+      json!({ "size": { "width": 1, "height": 2 } }),
+    );
+    assert_eq!(
+      extract::<Size>(&message, "size").unwrap(),
+      Size {
+        width: 1,
+        height: 2
+      }
+    );
+
+    let message = invoke_message(
+      &app,
+      "resize",
+      json!({ "size": { "width": 1, "height": 2, "depth": 3 } }),
+    );
+    let err = extract::<Size>(&message, "size").unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .contains("argument size has unknown field `depth`"));
+
+    // not enabled for this command
+    let message = invoke_message(
+      &app,
+      "scale",
+      json!({ "size": { "width": 1, "height": 2, "depth": 3 } }),
+    );
+    assert!(extract::<Size>(&message, "size").is_ok());
+  }
+
+  #[test]
+  fn argument_accesses_recorded() {
+    use crate::{command::ArgumentAccess, test::take_argument_accesses};
+
+    // not recorded by default
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "a": 1 }));
+    assert_eq!(extract::<u32>(&message, "a").unwrap(), 1);
+    assert!(take_argument_accesses(&app).is_empty());
+
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| authority.record_argument_accesses(true))
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let message = invoke_message(&app, "test", json!({ "a": 1, "b": 2 }));
+    assert_eq!(extract::<u32>(&message, "a").unwrap(), 1);
+    // missing keys are not recorded
+    assert_eq!(extract::<Option<u32>>(&message, "c").unwrap(), None);
+
+    assert_eq!(
+      take_argument_accesses(&app),
+      vec![ArgumentAccess {
+        command: "test".into(),
+        key: "a".into(),
+      }]
+    );
+    assert!(take_argument_accesses(&app).is_empty());
+  }
+
+  #[test]
+  fn missing_key() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "other": 1 }));
+    let err = extract::<u64>(&message, "count").unwrap_err();
+    assert_eq!(
+      err.0["message"].as_str().unwrap(),
+      "invalid args `count` for command `test`: command test missing required key count"
+    );
+  }
+
+  #[test]
+  fn null_key() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "count": null }));
+    let err = extract::<u64>(&message, "count").unwrap_err();
+    assert_eq!(
+      err.0["message"].as_str().unwrap(),
+      "invalid args `count` for command `test`: command test got null for required key count"
+    );
+
+    // null is still a valid value for optional and unit types
+    assert_eq!(extract::<Option<u64>>(&message, "count").unwrap(), None);
+    assert_eq!(
+      extract::<JsonValue>(&message, "count").unwrap(),
+      JsonValue::Null
+    );
+    extract::<()>(&message, "count").unwrap();
+
+    // other invalid values keep the deserializer error
+    let message = invoke_message(&app, "test", json!({ "count": "one" }));
+    let err = extract::<u64>(&message, "count").unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .contains("invalid type: string"));
+  }
+
+  #[test]
+  fn default_payload_depth_limit() {
+    let app = mock_app();
+    let message = invoke_message(&app, "test", json!({ "value": nested(128) }));
+    assert!(extract::<JsonValue>(&message, "value").is_ok());
+
+    let message = invoke_message(&app, "test", json!({ "value": nested(1_000) }));
+    assert!(extract::<JsonValue>(&message, "value").is_err());
+  }
+
+  #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  enum Permission {
+    Read,
+    Write,
+  }
+
+  #[test]
+  fn enum_keyed_map() {
+    use std::collections::BTreeMap;
+
+    let app = mock_app();
+    let message = invoke_message(
+      &app,
+      "test",
+      json!({ "permissions": { "read": true, "write": false } }),
+    );
+    assert_eq!(
+      extract::<BTreeMap<Permission, bool>>(&message, "permissions").unwrap(),
+      [(Permission::Read, true), (Permission::Write, false)]
+        .into_iter()
+        .collect()
+    );
+
+    let message = invoke_message(
+      &app,
+      "test",
+      json!({ "permissions": { "read": true, "execute": true } }),
+    );
+    let err = extract::<BTreeMap<Permission, bool>>(&message, "permissions").unwrap_err();
+    assert!(err.0["message"]
+      .as_str()
+      .unwrap()
+      .contains("unknown variant `execute`, expected `read` or `write`"));
+  }
+
+  #[crate::command(root = "crate")]
+  fn greet(name: String) -> String {
+    format!("hello {name}")
+  }
+
+  #[test]
+  fn blocking_command() {
+    use crate::{ipc::CallbackFn, window::InvokeRequest, WindowBuilder};
+    use std::sync::mpsc::channel;
+
+    let app = mock_builder()
+      .invoke_handler(|invoke| match invoke.message.command() {
+        "greet" => __cmd__greet!(greet, invoke),
+        _ => {
+          super::respond_blocking(invoke.resolver, "manual");
+          true
+        }
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let invoke = |cmd: &str| {
+      let (tx, rx) = channel();
+      window.clone().on_message(
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({ "name": "tauri" }).into(),
+          headers: Default::default(),
+        },
+        Box::new(move |_window, _cmd, response, _callback, _error| {
+          tx.send((std::thread::current().id(), response)).unwrap();
+        }),
+      );
+      // resolved inline: the response is already there, sent from this thread
+      let (thread, response) = rx.try_recv().unwrap();
+      assert_eq!(thread, std::thread::current().id());
+      match response {
+        crate::ipc::InvokeResponse::Ok(body) => body.deserialize::<String>().unwrap(),
+        crate::ipc::InvokeResponse::Err(e) => panic!("unexpected error {:?}", e.0),
+      }
+    };
+
+    assert_eq!(invoke("greet"), "hello tauri");
+    assert_eq!(invoke("manual"), "manual");
+  }
+
+  #[crate::command(root = "crate", async)]
+  fn count(to: u32) -> impl futures_util::Stream<Item = Result<InvokeBody, InvokeError>> {
+    futures_util::stream::iter((1..=to).map(|i| Ok(json!(i).into())))
+  }
+
+  #[crate::command(root = "crate")]
+  async fn tail(
+    lines: Vec<String>,
+  ) -> impl futures_util::Stream<Item = Result<InvokeBody, InvokeError>> {
+    futures_util::stream::iter(lines.into_iter().map(|line| match line.as_str() {
+      "eof" => Err("unexpected end of file".into()),
+      line => Ok(json!(line).into()),
+    }))
+  }
+
+  #[test]
+  fn streamed_response() {
+    use crate::{
+      ipc::{CallbackFn, STREAM_EVENT_PREFIX},
+      test::get_ipc_response,
+      window::InvokeRequest,
+      WindowBuilder,
+    };
+    use std::sync::{Arc, Mutex};
+
+    let app = mock_builder()
+      .invoke_handler(|invoke| match invoke.message.command() {
+        "count" => __cmd__count!(count, invoke),
+        _ => __cmd__tail!(tail, invoke),
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let invoke = |cmd: &str, callback: u32, body: JsonValue| {
+      let chunks = Arc::new(Mutex::new(Vec::new()));
+      let chunks_ = chunks.clone();
+      window.listen(format!("{STREAM_EVENT_PREFIX}{callback}"), move |event| {
+        let chunk: JsonValue = serde_json::from_str(event.payload()).unwrap();
+        chunks_.lock().unwrap().push(chunk);
+      });
+      let response = get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(callback),
+          error: CallbackFn(callback + 1),
+          body: body.into(),
+          headers: Default::default(),
+        },
+      );
+      let chunks = chunks.lock().unwrap().clone();
+      (response.map(InvokeBody::into_json), chunks)
+    };
+
+    assert_eq!(
+      invoke("count", 0, json!({ "to": 3 })),
+      (Ok(json!(null)), vec![json!(1), json!(2), json!(3)])
+    );
+    assert_eq!(
+      invoke("tail", 10, json!({ "lines": ["a", "b"] })),
+      (Ok(json!(null)), vec![json!("a"), json!("b")])
+    );
+    // the first error rejects the invoke
+    assert_eq!(
+      invoke("tail", 20, json!({ "lines": ["a", "eof", "b"] })),
+      (Err(json!("unexpected end of file")), vec![json!("a")])
+    );
+  }
+}
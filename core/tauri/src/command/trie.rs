@@ -0,0 +1,183 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The separator of the segments of a hierarchical command name, e.g. `plugin:subsystem:action`.
+const SEPARATOR: char = ':';
+/// A pattern segment matching exactly one segment.
+const WILDCARD: &str = "*";
+/// A pattern segment matching any number of segments, including none.
+const MULTI_WILDCARD: &str = "**";
+
+/// A prefix tree of command name patterns, split on `:`.
+///
+/// A `*` segment matches exactly one segment and a `**` segment matches any number of segments,
+/// e.g. `plugin:*:read` matches `plugin:fs:read` and `plugin:**` matches every plugin command.
+/// Other segments with glob characters match a single segment like window patterns do,
+/// e.g. `fs:read_*` matches `fs:read_file` and `fs:read_dir`.
+#[derive(Debug)]
+pub(crate) struct CommandTrie<V> {
+  root: Node<V>,
+}
+
+#[derive(Debug)]
+struct Node<V> {
+  children: BTreeMap<String, Node<V>>,
+  globs: Vec<(glob::Pattern, Node<V>)>,
+  values: Vec<V>,
+}
+
+impl<V> Default for Node<V> {
+  fn default() -> Self {
+    Self {
+      children: BTreeMap::new(),
+      globs: Vec::new(),
+      values: Vec::new(),
+    }
+  }
+}
+
+impl<V> Node<V> {
+  /// Gets or creates the child node of the pattern segment.
+  fn child(&mut self, segment: &str) -> &mut Node<V> {
+    let is_glob =
+      segment != WILDCARD && segment != MULTI_WILDCARD && segment.contains(['*', '?', '[']);
+    match glob::Pattern::new(segment) {
+      Ok(pattern) if is_glob => {
+        let position = match self.globs.iter().position(|(p, _)| p == &pattern) {
+          Some(position) => position,
+          None => {
+            self.globs.push((pattern, Node::default()));
+            self.globs.len() - 1
+          }
+        };
+        &mut self.globs[position].1
+      }
+      // invalid patterns only match themselves
+      _ => self.children.entry(segment.to_string()).or_default(),
+    }
+  }
+}
+
+impl<V> Default for CommandTrie<V> {
+  fn default() -> Self {
+    Self {
+      root: Node::default(),
+    }
+  }
+}
+
+impl<V: Ord> CommandTrie<V> {
+  /// Adds a value for the command name pattern.
+  pub(crate) fn insert(&mut self, pattern: &str, value: V) {
+    let node = pattern
+      .split(SEPARATOR)
+      .fold(&mut self.root, |node, segment| node.child(segment));
+    node.values.push(value);
+  }
+
+  /// Returns the values of all patterns matching the command name, in order.
+  pub(crate) fn matches(&self, command: &str) -> BTreeSet<&V> {
+    let segments: Vec<&str> = command.split(SEPARATOR).collect();
+    let mut matches = BTreeSet::new();
+    Self::collect(&self.root, &segments, &mut matches);
+    matches
+  }
+
+  fn collect<'a>(node: &'a Node<V>, segments: &[&str], matches: &mut BTreeSet<&'a V>) {
+    if let Some(multi) = node.children.get(MULTI_WILDCARD) {
+      for skip in 0..=segments.len() {
+        Self::collect(multi, &segments[skip..], matches);
+      }
+    }
+
+    let Some((segment, rest)) = segments.split_first() else {
+      matches.extend(&node.values);
+      return;
+    };
+    if let Some(child) = node.children.get(*segment) {
+      Self::collect(child, rest, matches);
+    }
+    if *segment != WILDCARD {
+      if let Some(child) = node.children.get(WILDCARD) {
+        Self::collect(child, rest, matches);
+      }
+    }
+    for (pattern, child) in &node.globs {
+      if pattern.matches(segment) {
+        Self::collect(child, rest, matches);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::CommandTrie;
+
+  fn trie(patterns: &[&'static str]) -> CommandTrie<&'static str> {
+    let mut trie = CommandTrie::default();
+    for pattern in patterns {
+      trie.insert(pattern, *pattern);
+    }
+    trie
+  }
+
+  fn matches(trie: &CommandTrie<&'static str>, command: &str) -> Vec<&'static str> {
+    trie.matches(command).into_iter().copied().collect()
+  }
+
+  #[test]
+  fn exact() {
+    let trie = trie(&["plugin:fs:read", "plugin:fs:write", "read"]);
+    assert_eq!(matches(&trie, "plugin:fs:read"), ["plugin:fs:read"]);
+    assert_eq!(matches(&trie, "read"), ["read"]);
+    assert!(matches(&trie, "plugin:fs").is_empty());
+    assert!(matches(&trie, "plugin:fs:read:all").is_empty());
+  }
+
+  #[test]
+  fn single_level_wildcard() {
+    let trie = trie(&["plugin:*:read", "plugin:fs:read", "plugin:*"]);
+    assert_eq!(
+      matches(&trie, "plugin:fs:read"),
+      ["plugin:*:read", "plugin:fs:read"]
+    );
+    assert_eq!(matches(&trie, "plugin:http:read"), ["plugin:*:read"]);
+    assert_eq!(matches(&trie, "plugin:http"), ["plugin:*"]);
+    assert!(matches(&trie, "plugin:http:fetch").is_empty());
+    assert!(matches(&trie, "plugin:a:b:read").is_empty());
+  }
+
+  #[test]
+  fn glob_segment() {
+    let trie = trie(&["fs:read_*", "fs:?et_*", "fs:[rw]*:all", "fs:[invalid"]);
+    assert_eq!(matches(&trie, "fs:read_file"), ["fs:read_*"]);
+    assert_eq!(matches(&trie, "fs:read_"), ["fs:read_*"]);
+    assert_eq!(matches(&trie, "fs:get_meta"), ["fs:?et_*"]);
+    assert_eq!(matches(&trie, "fs:rename:all"), ["fs:[rw]*:all"]);
+    assert_eq!(matches(&trie, "fs:[invalid"), ["fs:[invalid"]);
+    assert!(matches(&trie, "fs:write_file").is_empty());
+    assert!(matches(&trie, "fs:read:file").is_empty());
+    assert!(matches(&trie, "fs:invalid").is_empty());
+  }
+
+  #[test]
+  fn multi_level_wildcard() {
+    let trie = trie(&["plugin:**", "plugin:**:read", "**:delete"]);
+    assert_eq!(matches(&trie, "plugin"), ["plugin:**"]);
+    assert_eq!(
+      matches(&trie, "plugin:a:b:read"),
+      ["plugin:**", "plugin:**:read"]
+    );
+    assert_eq!(
+      matches(&trie, "plugin:read"),
+      ["plugin:**", "plugin:**:read"]
+    );
+    assert_eq!(matches(&trie, "files:trash:delete"), ["**:delete"]);
+    assert_eq!(matches(&trie, "delete"), ["**:delete"]);
+    assert!(matches(&trie, "files:read").is_empty());
+  }
+}
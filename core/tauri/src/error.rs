@@ -52,6 +52,21 @@ pub enum Error {
   /// Invalid args when running a command.
   #[error("invalid args `{1}` for command `{0}`: {2}")]
   InvalidArgs(&'static str, &'static str, serde_json::Error),
+  /// A command argument was read from a payload of the wrong type,
+  /// e.g. a named argument when the IPC call used a bytes payload.
+  ///
+  /// Sent to the frontend as `{ "type": "PayloadTypeMismatch", "data": { command, key, expected }, "message": string }`.
+  #[error(
+    "command {command} expected a value for key {key} but the IPC call used a bytes payload"
+  )]
+  PayloadTypeMismatch {
+    /// The command name.
+    command: &'static str,
+    /// The argument key.
+    key: &'static str,
+    /// The expected payload type.
+    expected: &'static str,
+  },
   /// Encountered an error in the setup hook,
   #[error("error encountered during setup hook: {0}")]
   Setup(SetupError),
@@ -133,6 +148,24 @@ pub enum Error {
   /// The resource id is invalid.
   #[error("The resource id {0} is invalid.")]
   BadResourceId(crate::resources::ResourceId),
+  /// The ACL has window or domain patterns that can never match.
+  #[error("invalid ACL patterns: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+  InvalidAclPatterns(Vec<crate::command::PatternError>),
+  /// The ACL is inconsistent, e.g. a command references a scope that does not exist.
+  #[error("invalid ACL: {0}")]
+  InvalidAcl(crate::command::AuthorityBuildError),
+  /// The response of a command exceeds its size limit.
+  #[error(
+    "response of command `{command}` is {size} bytes which exceeds the limit of {limit} bytes"
+  )]
+  ResponseTooLarge {
+    /// The command name.
+    command: String,
+    /// The size of the serialized response.
+    size: usize,
+    /// The configured limit.
+    limit: usize,
+  },
   /// The anyhow crate error.
   #[error(transparent)]
   Anyhow(#[from] anyhow::Error),
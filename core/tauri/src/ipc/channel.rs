@@ -11,7 +11,9 @@ use std::{
   },
 };
 
+use futures_util::{Stream, StreamExt};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value as JsonValue;
 
 use crate::{
   command,
@@ -159,6 +161,50 @@ impl Channel {
   }
 }
 
+/// A command response that sends each item of a stream as an event through a [`Channel`],
+/// completing the invocation with `null` once the stream ends.
+///
+/// The stream is consumed asynchronously, so it can only be returned from async commands.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::ipc::{Channel, EventStream};
+///
+/// #[tauri::command]
+/// async fn live_stats(on_event: Channel) -> EventStream {
+///   let stats = futures_util::stream::iter([10, 20, 30]);
+///   EventStream::new(on_event, stats)
+/// }
+/// ```
+pub struct EventStream {
+  channel: Channel,
+  events: std::pin::Pin<Box<dyn Stream<Item = serde_json::Result<JsonValue>> + Send>>,
+}
+
+impl EventStream {
+  /// Creates a response sending the items of the stream through the channel.
+  pub fn new<S, T>(channel: Channel, events: S) -> Self
+  where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize,
+  {
+    Self {
+      channel,
+      events: Box::pin(events.map(|event| serde_json::to_value(event))),
+    }
+  }
+
+  /// Sends every item of the stream through the channel, resolving to `null` once the stream ends.
+  pub(crate) async fn forward(mut self) -> Result<InvokeBody, InvokeError> {
+    while let Some(event) = self.events.next().await {
+      let event = event.map_err(|e| InvokeError::from_error(crate::Error::from(e)))?;
+      self.channel.send(event).map_err(InvokeError::from_error)?;
+    }
+    Ok(JsonValue::Null.into())
+  }
+}
+
 impl<'de, R: Runtime> CommandArg<'de, R> for Channel {
   /// Grabs the [`Window`] from the [`CommandItem`] and returns the associated [`Channel`].
   fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
@@ -203,3 +249,67 @@ pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
     .invoke_handler(crate::generate_handler![fetch])
     .build()
 }
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use serde_json::json;
+
+  use super::{Channel, EventStream};
+  use crate::ipc::InvokeBody;
+
+  fn collecting_channel() -> (Channel, Arc<Mutex<Vec<InvokeBody>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_ = events.clone();
+    let channel = Channel::new(move |body| {
+      events_.lock().unwrap().push(body);
+      Ok(())
+    });
+    (channel, events)
+  }
+
+  #[test]
+  fn event_stream_delivers_events() {
+    let (channel, events) = collecting_channel();
+    let stream = EventStream::new(
+      channel,
+      futures_util::stream::iter(["connected", "update", "update"]),
+    );
+
+    let body = crate::async_runtime::block_on(stream.forward()).unwrap();
+    assert_eq!(body.into_json(), json!(null));
+    assert_eq!(
+      events
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(InvokeBody::into_json)
+        .collect::<Vec<_>>(),
+      vec![json!("connected"), json!("update"), json!("update")]
+    );
+  }
+
+  #[test]
+  fn event_stream_in_async_command() {
+    use crate::command::private::*;
+
+    async fn live_stats(channel: Channel) -> EventStream {
+      EventStream::new(channel, futures_util::stream::iter(1..=3))
+    }
+
+    let (channel, events) = collecting_channel();
+    let body = crate::async_runtime::block_on(async move {
+      crate::async_runtime::spawn(async move {
+        let result = live_stats(channel);
+        let kind = (&result).async_kind();
+        kind.future(result).await
+      })
+      .await
+      .unwrap()
+    })
+    .unwrap();
+    assert_eq!(body.into_json(), json!(null));
+    assert_eq!(events.lock().unwrap().len(), 3);
+  }
+}
@@ -6,7 +6,10 @@
 //!
 //! This module includes utilities to send messages to the JS layer of the webview.
 
-use std::sync::{Arc, Mutex};
+use std::{
+  sync::{Arc, Mutex},
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use futures_util::Future;
 use http::HeaderMap;
@@ -17,8 +20,8 @@ use tauri_macros::default_runtime;
 use tauri_utils::acl::resolved::ResolvedCommand;
 
 use crate::{
-  command::{CommandArg, CommandItem},
-  Runtime, StateManager, Window,
+  command::{CommandArg, CommandItem, Origin},
+  Manager, Runtime, StateManager, Window,
 };
 
 pub(crate) mod channel;
@@ -26,7 +29,58 @@ pub(crate) mod channel;
 pub(crate) mod format_callback;
 pub(crate) mod protocol;
 
-pub use channel::{Channel, JavaScriptChannelId};
+pub use channel::{Channel, EventStream, JavaScriptChannelId};
+
+/// The header carrying the deadline of an invoke, in milliseconds since the Unix epoch.
+pub(crate) const DEADLINE_HEADER: &str = "Tauri-Deadline";
+
+/// Reads the deadline of an invoke from its headers, ignoring malformed values.
+pub(crate) fn deadline(headers: &HeaderMap) -> Option<Instant> {
+  let millis: u64 = headers
+    .get(DEADLINE_HEADER)?
+    .to_str()
+    .ok()?
+    .trim()
+    .parse()
+    .ok()?;
+  let remaining = (UNIX_EPOCH + Duration::from_millis(millis))
+    .duration_since(SystemTime::now())
+    .unwrap_or_default();
+  Some(Instant::now() + remaining)
+}
+
+/// The prefix of the event carrying the chunks of a streamed command response,
+/// followed by the success callback id of the invoke.
+pub const STREAM_EVENT_PREFIX: &str = "tauri://invoke-stream/";
+
+/// Emits a chunk of a streamed command response.
+type ChunkEmitter = Arc<dyn Fn(InvokeBody) -> crate::Result<()> + Send + Sync>;
+
+tokio::task_local! {
+  /// The emitter of the chunks of the async command running in this task.
+  static CHUNK_EMITTER: ChunkEmitter;
+}
+
+/// Emits every item of the stream to the invoking window, see [`STREAM_EVENT_PREFIX`].
+///
+/// Resolves to `null` once the stream ends, or fails with the first error of the stream.
+pub(crate) async fn forward_stream<S>(stream: S) -> Result<InvokeBody, InvokeError>
+where
+  S: futures_util::Stream<Item = Result<InvokeBody, InvokeError>>,
+{
+  use futures_util::StreamExt;
+
+  let emit = CHUNK_EMITTER.try_with(Clone::clone).map_err(|_| {
+    InvokeError::from_anyhow(anyhow::anyhow!(
+      "streamed responses are only supported by async commands"
+    ))
+  })?;
+  futures_util::pin_mut!(stream);
+  while let Some(chunk) = stream.next().await {
+    emit(chunk?).map_err(InvokeError::from_error)?;
+  }
+  Ok(JsonValue::Null.into())
+}
 
 /// A closure that is run every time Tauri receives a message it doesn't explicitly handle.
 pub type InvokeHandler<R> = dyn Fn(Invoke<R>) -> bool + Send + Sync + 'static;
@@ -82,6 +136,14 @@ impl InvokeBody {
     }
   }
 
+  /// The size of the body in bytes, as a serialized JSON string for JSON bodies.
+  pub(crate) fn len(&self) -> usize {
+    match self {
+      Self::Json(v) => serde_json::to_vec(v).map(|v| v.len()).unwrap_or_default(),
+      Self::Raw(v) => v.len(),
+    }
+  }
+
   /// Attempts to deserialize the invoke body.
   pub fn deserialize<T: DeserializeOwned>(self) -> serde_json::Result<T> {
     match self {
@@ -92,6 +154,29 @@ impl InvokeBody {
 }
 
 /// The IPC request.
+///
+/// Commands invoked with a bytes payload can't read named arguments from it,
+/// but they can still combine the raw body with arguments that don't read the payload,
+/// such as a [`CommandScope`](crate::command::CommandScope):
+///
+/// ```rust
+/// use tauri::{command::{CommandScope, StringScope}, ipc::Request};
+///
+/// #[tauri::command]
+/// fn upload(request: Request<'_>, scope: CommandScope<'_, StringScope>) -> Result<(), String> {
+///   let name = request
+///     .headers()
+///     .get("file-name")
+///     .and_then(|name| name.to_str().ok())
+///     .ok_or("missing file name")?;
+///   if !scope.permits(name) {
+///     return Err(format!("{name} is not allowed"));
+///   }
+///   let bytes = request.raw_body().ok_or("expected a bytes payload")?;
+///   println!("uploading {} bytes to {name}", bytes.len());
+///   Ok(())
+/// }
+/// ```
 #[derive(Debug)]
 pub struct Request<'a> {
   body: &'a InvokeBody,
@@ -104,6 +189,14 @@ impl<'a> Request<'a> {
     self.body
   }
 
+  /// The request body if the IPC call used a bytes payload.
+  pub fn raw_body(&self) -> Option<&[u8]> {
+    match self.body {
+      InvokeBody::Raw(bytes) => Some(bytes),
+      InvokeBody::Json(_) => None,
+    }
+  }
+
   /// Thr request headers.
   pub fn headers(&self) -> &HeaderMap {
     self.headers
@@ -195,10 +288,200 @@ impl<T: Serialize> From<T> for InvokeError {
 impl From<crate::Error> for InvokeError {
   #[inline(always)]
   fn from(error: crate::Error) -> Self {
-    Self(JsonValue::String(error.to_string()))
+    match error {
+      crate::Error::PayloadTypeMismatch {
+        command,
+        key,
+        expected,
+      } => Self(serde_json::json!({
+        "type": "PayloadTypeMismatch",
+        "data": { "command": command, "key": key, "expected": expected },
+        "message": error.to_string(),
+      })),
+      crate::Error::InvalidArgs(command, arg, _) => Self(serde_json::json!({
+        "type": "InvalidArgs",
+        "data": { "command": command, "arg": arg },
+        "message": error.to_string(),
+      })),
+      error => Self(JsonValue::String(error.to_string())),
+    }
+  }
+}
+
+/// A command error that keeps its shape when sent to the frontend.
+///
+/// The error is serialized as `{ "type": string, "data": any }`, where `type` is the enum variant name
+/// (or the type name for non-enum errors) so the frontend can branch on it, and `data` is the variant content.
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::ipc::TypedError;
+///
+/// #[derive(serde::Serialize)]
+/// enum FileError {
+///   NotFound { path: String },
+///   PermissionDenied,
+/// }
+///
+/// #[tauri::command]
+/// fn open(path: String) -> Result<(), TypedError<FileError>> {
+///   Err(FileError::NotFound { path })?
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypedError<E: Serialize>(pub E);
+
+impl<E: Serialize> From<E> for TypedError<E> {
+  fn from(error: E) -> Self {
+    Self(error)
   }
 }
 
+impl<E: Serialize> Serialize for TypedError<E> {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    use serde::ser::{Error, SerializeStruct};
+
+    let value = serde_json::to_value(&self.0).map_err(S::Error::custom)?;
+    let (kind, data) = match (variant_name(&self.0), value) {
+      (Some(variant), JsonValue::Object(mut map)) => {
+        let data = map.remove(variant).unwrap_or_default();
+        (variant.to_string(), data)
+      }
+      (Some(variant), _) => (variant.to_string(), JsonValue::Null),
+      (None, data) => (
+        std::any::type_name::<E>()
+          .rsplit("::")
+          .next()
+          .unwrap_or_default()
+          .to_string(),
+        data,
+      ),
+    };
+
+    let mut s = serializer.serialize_struct("TypedError", 2)?;
+    s.serialize_field("type", &kind)?;
+    s.serialize_field("data", &data)?;
+    s.end()
+  }
+}
+
+/// Gets the variant name of the value if it serializes as an enum variant.
+fn variant_name<T: Serialize>(value: &T) -> Option<&'static str> {
+  use serde::ser::{Error, Impossible, Serializer};
+  use std::cell::Cell;
+
+  #[derive(Debug)]
+  struct Stop;
+
+  impl std::fmt::Display for Stop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      f.write_str("stop")
+    }
+  }
+
+  impl std::error::Error for Stop {}
+
+  impl Error for Stop {
+    fn custom<M: std::fmt::Display>(_msg: M) -> Self {
+      Stop
+    }
+  }
+
+  /// A serializer that only records the variant name of an enum and stops.
+  struct VariantName<'a>(&'a Cell<Option<&'static str>>);
+
+  macro_rules! stop {
+    ($($fn:ident($($arg:ty),*) -> $ret:ty;)*) => {
+      $(fn $fn(self, $(_: $arg),*) -> Result<$ret, Stop> {
+        Err(Stop)
+      })*
+    };
+  }
+
+  macro_rules! variant {
+    ($($fn:ident($($arg:ty),*) -> $ret:ty;)*) => {
+      $(fn $fn(self, _: &'static str, _: u32, variant: &'static str, $(_: $arg),*) -> Result<$ret, Stop> {
+        self.0.set(Some(variant));
+        Err(Stop)
+      })*
+    };
+  }
+
+  impl<'a> Serializer for VariantName<'a> {
+    type Ok = ();
+    type Error = Stop;
+    type SerializeSeq = Impossible<(), Stop>;
+    type SerializeTuple = Impossible<(), Stop>;
+    type SerializeTupleStruct = Impossible<(), Stop>;
+    type SerializeTupleVariant = Impossible<(), Stop>;
+    type SerializeMap = Impossible<(), Stop>;
+    type SerializeStruct = Impossible<(), Stop>;
+    type SerializeStructVariant = Impossible<(), Stop>;
+
+    stop! {
+      serialize_bool(bool) -> ();
+      serialize_i8(i8) -> ();
+      serialize_i16(i16) -> ();
+      serialize_i32(i32) -> ();
+      serialize_i64(i64) -> ();
+      serialize_u8(u8) -> ();
+      serialize_u16(u16) -> ();
+      serialize_u32(u32) -> ();
+      serialize_u64(u64) -> ();
+      serialize_f32(f32) -> ();
+      serialize_f64(f64) -> ();
+      serialize_char(char) -> ();
+      serialize_str(&str) -> ();
+      serialize_bytes(&[u8]) -> ();
+      serialize_none() -> ();
+      serialize_unit() -> ();
+      serialize_unit_struct(&'static str) -> ();
+      serialize_seq(Option<usize>) -> Self::SerializeSeq;
+      serialize_tuple(usize) -> Self::SerializeTuple;
+      serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+      serialize_map(Option<usize>) -> Self::SerializeMap;
+      serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+    }
+
+    variant! {
+      serialize_unit_variant() -> ();
+      serialize_tuple_variant(usize) -> Self::SerializeTupleVariant;
+      serialize_struct_variant(usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<(), Stop> {
+      Err(Stop)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+      self,
+      _: &'static str,
+      _: &T,
+    ) -> Result<(), Stop> {
+      Err(Stop)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+      self,
+      _: &'static str,
+      _: u32,
+      variant: &'static str,
+      _: &T,
+    ) -> Result<(), Stop> {
+      self.0.set(Some(variant));
+      Err(Stop)
+    }
+  }
+
+  let variant = Cell::new(None);
+  let _ = value.serialize(VariantName(&variant));
+  variant.get()
+}
+
 /// Response from a [`InvokeMessage`] passed to the [`InvokeResolver`].
 #[derive(Debug)]
 pub enum InvokeResponse {
@@ -248,6 +531,7 @@ pub struct InvokeResolver<R: Runtime> {
   cmd: String,
   pub(crate) callback: CallbackFn,
   pub(crate) error: CallbackFn,
+  deadline: Option<Instant>,
 }
 
 impl<R: Runtime> Clone for InvokeResolver<R> {
@@ -258,6 +542,7 @@ impl<R: Runtime> Clone for InvokeResolver<R> {
       cmd: self.cmd.clone(),
       callback: self.callback,
       error: self.error,
+      deadline: self.deadline,
     }
   }
 }
@@ -269,6 +554,7 @@ impl<R: Runtime> InvokeResolver<R> {
     cmd: String,
     callback: CallbackFn,
     error: CallbackFn,
+    deadline: Option<Instant>,
   ) -> Self {
     Self {
       window,
@@ -276,35 +562,47 @@ impl<R: Runtime> InvokeResolver<R> {
       cmd,
       callback,
       error,
+      deadline,
     }
   }
 
   /// Reply to the invoke promise with an async task.
+  ///
+  /// The task is cancelled if the deadline of the invoke passes.
   pub fn respond_async<T, F>(self, task: F)
   where
     T: IpcResponse,
     F: Future<Output = Result<T, InvokeError>> + Send + 'static,
   {
     crate::async_runtime::spawn(async move {
-      Self::return_task(
+      let result = until_deadline(self.deadline, self.cmd.clone(), task).await;
+      Self::return_closure(
         self.window,
         self.responder,
-        task,
+        || result,
         self.cmd,
         self.callback,
         self.error,
-      )
-      .await;
+      );
     });
   }
 
   /// Reply to the invoke promise with an async task which is already serialized.
+  ///
+  /// The task is cancelled if the deadline of the invoke passes.
+  /// Streamed responses emit their chunks to the invoking window,
+  /// as `tauri://invoke-stream/{callback}` events.
   pub fn respond_async_serialized<F>(self, task: F)
   where
     F: Future<Output = Result<InvokeBody, InvokeError>> + Send + 'static,
   {
+    let window = self.window.clone();
+    let event = format!("{STREAM_EVENT_PREFIX}{}", self.callback.0);
+    let emitter: ChunkEmitter =
+      Arc::new(move |chunk: InvokeBody| window.emit_to(window.label(), &event, chunk.into_json()));
     crate::async_runtime::spawn(async move {
-      let response = match task.await {
+      let task = until_deadline(self.deadline, self.cmd.clone(), task);
+      let response = match CHUNK_EMITTER.scope(emitter, task).await {
         Ok(ok) => InvokeResponse::Ok(ok),
         Err(err) => InvokeResponse::Err(err),
       };
@@ -413,6 +711,21 @@ impl<R: Runtime> InvokeResolver<R> {
     success_callback: CallbackFn,
     error_callback: CallbackFn,
   ) {
+    let response = match response {
+      InvokeResponse::Ok(body) => match window.manager.runtime_authority.max_response_bytes(&cmd) {
+        Some(limit) if body.len() > limit => InvokeResponse::Err(
+          crate::Error::ResponseTooLarge {
+            command: cmd.clone(),
+            size: body.len(),
+            limit,
+          }
+          .into(),
+        ),
+        _ => InvokeResponse::Ok(body),
+      },
+      response => response,
+    };
+
     (responder.lock().unwrap().take().expect("resolver consumed"))(
       window,
       cmd,
@@ -423,6 +736,24 @@ impl<R: Runtime> InvokeResolver<R> {
   }
 }
 
+/// Runs the task until the deadline, dropping it and failing once the deadline passes.
+async fn until_deadline<T, F: Future<Output = Result<T, InvokeError>>>(
+  deadline: Option<Instant>,
+  cmd: String,
+  task: F,
+) -> Result<T, InvokeError> {
+  match deadline {
+    Some(deadline) => tokio::time::timeout_at(deadline.into(), task)
+      .await
+      .unwrap_or_else(|_| {
+        Err(InvokeError::from_anyhow(anyhow::anyhow!(
+          "command {cmd} exceeded its deadline"
+        )))
+      }),
+    None => task.await,
+  }
+}
+
 /// An invoke message.
 #[default_runtime(crate::Wry, wry)]
 #[derive(Debug)]
@@ -437,6 +768,8 @@ pub struct InvokeMessage<R: Runtime> {
   pub(crate) payload: InvokeBody,
   /// The request headers.
   pub(crate) headers: HeaderMap,
+  /// The origin the access control list is checked against, computed once when the invoke is received.
+  pub(crate) origin: Origin,
 }
 
 impl<R: Runtime> Clone for InvokeMessage<R> {
@@ -447,6 +780,7 @@ impl<R: Runtime> Clone for InvokeMessage<R> {
       command: self.command.clone(),
       payload: self.payload.clone(),
       headers: self.headers.clone(),
+      origin: self.origin.clone(),
     }
   }
 }
@@ -460,12 +794,14 @@ impl<R: Runtime> InvokeMessage<R> {
     payload: InvokeBody,
     headers: HeaderMap,
   ) -> Self {
+    let origin = window.invoke_origin();
     Self {
       window,
       state,
       command,
       payload,
       headers,
+      origin,
     }
   }
 
@@ -515,3 +851,235 @@ impl<R: Runtime> InvokeMessage<R> {
 /// The `Callback` type is the return value of the `transformCallback` JavaScript function.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct CallbackFn(pub u32);
+
+#[cfg(test)]
+mod tests {
+  use serde::Serialize;
+  use serde_json::json;
+
+  use super::{CallbackFn, InvokeError, TypedError};
+  use crate::{
+    test::{get_ipc_response, mock_builder, mock_context, noop_assets},
+    window::InvokeRequest,
+    WindowBuilder,
+  };
+
+  #[derive(Serialize)]
+  enum FileError {
+    NotFound { path: String },
+    TooLarge(u64),
+    PermissionDenied,
+  }
+
+  #[test]
+  fn typed_error_keeps_shape() {
+    let app = mock_builder()
+      .invoke_handler(|invoke| {
+        let error = match invoke.message.command() {
+          "not_found" => FileError::NotFound {
+            path: "a.txt".into(),
+          },
+          "too_large" => FileError::TooLarge(42),
+          _ => FileError::PermissionDenied,
+        };
+        invoke.resolver.respond::<()>(Err(TypedError(error).into()));
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let invoke = |cmd: &str| {
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({}).into(),
+          headers: Default::default(),
+        },
+      )
+      .unwrap_err()
+    };
+
+    assert_eq!(
+      invoke("not_found"),
+      json!({ "type": "NotFound", "data": { "path": "a.txt" } })
+    );
+    assert_eq!(
+      invoke("too_large"),
+      json!({ "type": "TooLarge", "data": 42 })
+    );
+    assert_eq!(
+      invoke("denied"),
+      json!({ "type": "PermissionDenied", "data": null })
+    );
+  }
+
+  #[test]
+  fn typed_error_non_enum() {
+    #[derive(Serialize)]
+    struct QuotaError {
+      limit: u32,
+    }
+
+    assert_eq!(
+      InvokeError::from(TypedError(QuotaError { limit: 5 })).0,
+      json!({ "type": "QuotaError", "data": { "limit": 5 } })
+    );
+  }
+
+  #[test]
+  fn invalid_args_keep_context() {
+    #[crate::command(root = "crate")]
+    fn resize(width: u32, height: u32) -> u32 {
+      width * height
+    }
+
+    let app = mock_builder()
+      .invoke_handler(crate::generate_handler![resize])
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let err = get_ipc_response(
+      &window,
+      InvokeRequest {
+        cmd: "resize".into(),
+        callback: CallbackFn(0),
+        error: CallbackFn(1),
+        body: json!({ "width": 2, "height": "tall" }).into(),
+        headers: Default::default(),
+      },
+    )
+    .unwrap_err();
+    assert_eq!(err["type"], "InvalidArgs");
+    assert_eq!(err["data"], json!({ "command": "resize", "arg": "height" }));
+    assert!(err["message"]
+      .as_str()
+      .unwrap()
+      .starts_with("invalid args `height` for command `resize`: invalid type: string \"tall\""));
+  }
+
+  #[test]
+  fn max_response_bytes() {
+    let app = mock_builder()
+      .configure_runtime_authority(|authority| {
+        authority.set_max_response_bytes("report", 16);
+      })
+      .invoke_handler(|invoke| {
+        let len = invoke
+          .message
+          .payload()
+          .clone()
+          .deserialize::<usize>()
+          .unwrap();
+        invoke.resolver.resolve("x".repeat(len));
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let invoke = |cmd: &str, len: usize| {
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!(len).into(),
+          headers: Default::default(),
+        },
+      )
+    };
+
+    // the serialized JSON string includes its quotes
+    assert_eq!(
+      invoke("report", 14)
+        .unwrap()
+        .deserialize::<String>()
+        .unwrap(),
+      "x".repeat(14)
+    );
+    let err = invoke("report", 15).unwrap_err();
+    assert!(err
+      .as_str()
+      .unwrap()
+      .contains("exceeds the limit of 16 bytes"));
+    // other commands are not limited
+    assert!(invoke("export", 100).is_ok());
+  }
+
+  #[test]
+  fn deadline_cancels_async_commands() {
+    use std::{
+      sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+      },
+      time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    let finished = Arc::new(AtomicBool::new(false));
+    let finished_ = finished.clone();
+    let app = mock_builder()
+      .invoke_handler(move |invoke| {
+        let delay = Duration::from_millis(match invoke.message.command() {
+          "slow" => 500,
+          _ => 0,
+        });
+        let finished = finished_.clone();
+        invoke.resolver.respond_async(async move {
+          tokio::time::sleep(delay).await;
+          finished.store(true, Ordering::SeqCst);
+          Ok("done")
+        });
+        true
+      })
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let window = WindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+
+    let invoke = |cmd: &str| {
+      let deadline =
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::from_millis(100);
+      let mut headers = http::HeaderMap::new();
+      headers.insert(
+        super::DEADLINE_HEADER,
+        deadline.as_millis().to_string().parse().unwrap(),
+      );
+      get_ipc_response(
+        &window,
+        InvokeRequest {
+          cmd: cmd.into(),
+          callback: CallbackFn(0),
+          error: CallbackFn(1),
+          body: json!({}).into(),
+          headers,
+        },
+      )
+    };
+
+    assert_eq!(
+      invoke("fast").unwrap().deserialize::<String>().unwrap(),
+      "done"
+    );
+
+    finished.store(false, Ordering::SeqCst);
+    let err = invoke("slow").unwrap_err();
+    assert_eq!(err, json!("command slow exceeded its deadline"));
+    // the command future is dropped, not left running in the background
+    std::thread::sleep(Duration::from_millis(600));
+    assert!(!finished.load(Ordering::SeqCst));
+  }
+}
@@ -360,7 +360,7 @@ fn handle_ipc_message<R: Runtime>(message: String, manager: &AppManager<R>, labe
 }
 
 fn parse_invoke_request<R: Runtime>(
-  #[allow(unused_variables)] manager: &AppManager<R>,
+  manager: &AppManager<R>,
   request: http::Request<Vec<u8>>,
 ) -> std::result::Result<InvokeRequest, String> {
   #[allow(unused_mut)]
@@ -414,7 +414,11 @@ fn parse_invoke_request<R: Runtime>(
   #[cfg(feature = "tracing")]
   let span = tracing::trace_span!("ipc::request::deserialize").entered();
 
-  let body = if content_type == mime::APPLICATION_OCTET_STREAM {
+  let body = if content_type == mime::APPLICATION_OCTET_STREAM
+    || manager
+      .runtime_authority
+      .has_payload_codec(content_type.essence_str())
+  {
     body.into()
   } else if content_type == mime::APPLICATION_JSON {
     if cfg!(ipc_custom_protocol) {
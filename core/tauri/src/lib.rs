@@ -15,6 +15,14 @@
 //! - **wry** *(enabled by default)*: Enables the [wry](https://github.com/tauri-apps/wry) runtime. Only disable it if you want a custom runtime.
 //! - **tracing**: Enables [`tracing`](https://docs.rs/tracing/latest/tracing) for window startup, plugins, `Window::eval`, events, IPC, updater and custom protocol request handlers.
 //! - **test**: Enables the [`test`] module exposing unit test helpers.
+//! - **unicode-normalization**: Enables the [`command::NormalizedStr`] command argument.
+//! - **regex**: Enables the [`command::RegexArg`] command argument.
+//! - **time**: Enables the [`command::TimeOrNow`] and [`command::Birthdate`] command arguments.
+//! - **cursor**: Enables the [`command::Cursor`] command argument.
+//! - **mime-type**: Enables the [`command::MimeType`] command argument and its [`command::MimeAllowList`].
+//! - **grant**: Enables the [`command::Grant`] command argument.
+//! - **metrics**: Enables hit and miss counters on the command scope caches, see [`command::RuntimeAuthority::scope_cache_metrics`].
+//! - **prometheus**: Enables [`command::RuntimeAuthority::render_prometheus`] exposing the access decision counters and scope cache metrics in the Prometheus text format.
 //! - **objc-exception**: Wrap each msg_send! in a @try/@catch and panics if an exception is caught, preventing Objective-C from unwinding into Rust.
 //! - **linux-ipc-protocol**: Use custom protocol for faster IPC on Linux. Requires webkit2gtk v2.40 or above.
 //! - **linux-libxdo**: Enables linking to libxdo which enables Cut, Copy, Paste and SelectAll menu items to work on Linux.
@@ -571,6 +579,12 @@ pub trait Manager<R: Runtime>: sealed::ManagerBase<R> {
     self.manager().package_info()
   }
 
+  /// The [`RuntimeAuthority`](crate::command::RuntimeAuthority) checking the IPC calls,
+  /// e.g. to clear its scope cache with [`clear_scope_cache`](crate::command::RuntimeAuthority::clear_scope_cache).
+  fn runtime_authority(&self) -> &crate::command::RuntimeAuthority {
+    &self.manager().runtime_authority
+  }
+
   /// Listen to an event emitted on any window.
   ///
   /// # Examples
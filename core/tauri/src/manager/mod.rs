@@ -22,7 +22,7 @@ use tauri_utils::{
 
 use crate::{
   app::{AppHandle, GlobalWindowEventListener, OnPageLoad},
-  command::RuntimeAuthority,
+  command::{AuthorityBuildError, RuntimeAuthority},
   event::{assert_event_name_is_valid, Event, EventId, Listeners},
   ipc::{Invoke, InvokeHandler, InvokeResponder},
   plugin::PluginStore,
@@ -239,15 +239,21 @@ impl<R: Runtime> AppManager<R> {
       crate::app::GlobalMenuEventListener<Window<R>>,
     >,
     (invoke_responder, invoke_initialization_script): (Option<Arc<InvokeResponder<R>>>, String),
-  ) -> Self {
+  ) -> crate::Result<Self> {
     // generate a random isolation key at runtime
     #[cfg(feature = "isolation")]
     if let Pattern::Isolation { ref mut key, .. } = &mut context.pattern {
       *key = uuid::Uuid::new_v4().to_string();
     }
 
-    Self {
-      runtime_authority: RuntimeAuthority::new(context.resolved_acl),
+    let runtime_authority =
+      RuntimeAuthority::try_new(context.resolved_acl).map_err(|e| match e {
+        AuthorityBuildError::InvalidPatterns(errors) => crate::Error::InvalidAclPatterns(errors),
+        e => crate::Error::InvalidAcl(e),
+      })?;
+
+    Ok(Self {
+      runtime_authority,
       window: window::WindowManager {
         windows: Mutex::default(),
         invoke_handler,
@@ -257,6 +263,8 @@ impl<R: Runtime> AppManager<R> {
         event_listeners: Arc::new(window_event_listeners),
         invoke_responder,
         invoke_initialization_script,
+        dropped_files: Default::default(),
+        principals: Default::default(),
       },
       #[cfg(all(desktop, feature = "tray-icon"))]
       tray: tray::TrayManager {
@@ -281,7 +289,7 @@ impl<R: Runtime> AppManager<R> {
       package_info: context.package_info,
       pattern: Arc::new(context.pattern),
       resources_table: Arc::default(),
-    }
+    })
   }
 
   /// State managed by the application.
@@ -630,7 +638,8 @@ mod test {
       Default::default(),
       Default::default(),
       (None, "".into()),
-    );
+    )
+    .unwrap();
 
     #[cfg(custom_protocol)]
     {
@@ -25,6 +25,7 @@ use url::Url;
 
 use crate::{
   app::{GlobalWindowEventListener, OnPageLoad, UriSchemeResponder},
+  command::Principal,
   ipc::{InvokeHandler, InvokeResponder},
   pattern::PatternJavascript,
   window::PageLoadPayload,
@@ -93,6 +94,10 @@ pub struct WindowManager<R: Runtime> {
   pub invoke_responder: Option<Arc<InvokeResponder<R>>>,
   /// The script that initializes the invoke system.
   pub invoke_initialization_script: String,
+  /// The paths of the last file drop on each window.
+  pub dropped_files: Mutex<HashMap<String, Vec<PathBuf>>>,
+  /// The principal of each window, see [`Window::set_principal`].
+  pub principals: Mutex<HashMap<String, Principal>>,
 }
 
 impl<R: Runtime> fmt::Debug for WindowManager<R> {
@@ -121,6 +126,45 @@ impl<R: Runtime> WindowManager<R> {
       .insert(uri_scheme, protocol);
   }
 
+  /// Take the paths of the last file drop on the given window, so they are only handed to one command.
+  pub(crate) fn take_dropped_files(&self, label: &str) -> Vec<PathBuf> {
+    self
+      .dropped_files
+      .lock()
+      .unwrap()
+      .remove(label)
+      .unwrap_or_default()
+  }
+
+  /// Set the paths of the last file drop on the given window.
+  pub(crate) fn set_dropped_files(&self, label: &str, paths: Vec<PathBuf>) {
+    self
+      .dropped_files
+      .lock()
+      .unwrap()
+      .insert(label.into(), paths);
+  }
+
+  /// Get the principal of the given window.
+  pub(crate) fn principal(&self, label: &str) -> Principal {
+    self
+      .principals
+      .lock()
+      .unwrap()
+      .get(label)
+      .cloned()
+      .unwrap_or_default()
+  }
+
+  /// Set the principal of the given window.
+  pub(crate) fn set_principal(&self, label: &str, principal: Principal) {
+    self
+      .principals
+      .lock()
+      .unwrap()
+      .insert(label.into(), principal);
+  }
+
   /// Get a locked handle to the windows.
   pub(crate) fn windows_lock(&self) -> MutexGuard<'_, HashMap<String, Window<R>>> {
     self.windows.lock().expect("poisoned window manager")
@@ -648,6 +692,8 @@ fn on_window_event<R: Runtime>(
     WindowEvent::Destroyed => {
       window.emit(WINDOW_DESTROYED_EVENT, ())?;
       let label = window.label();
+      manager.window.dropped_files.lock().unwrap().remove(label);
+      manager.window.principals.lock().unwrap().remove(label);
       let windows_map = manager.window.windows_lock();
       let windows = windows_map.values();
       for window in windows {
@@ -689,10 +735,21 @@ fn on_window_event<R: Runtime>(
             let _ = scopes.allow_directory(path, false);
           }
         }
+        manager
+          .window
+          .set_dropped_files(window.label(), paths.clone());
         let payload = FileDropPayload { paths, position };
         window.emit(WINDOW_FILE_DROP_EVENT, payload)?
       }
-      FileDropEvent::Cancelled => window.emit(WINDOW_FILE_DROP_CANCELLED_EVENT, ())?,
+      FileDropEvent::Cancelled => {
+        manager
+          .window
+          .dropped_files
+          .lock()
+          .unwrap()
+          .remove(window.label());
+        window.emit(WINDOW_FILE_DROP_CANCELLED_EVENT, ())?
+      }
       _ => unimplemented!(),
     },
     WindowEvent::ThemeChanged(theme) => window.emit(WINDOW_THEME_CHANGED, theme.to_string())?,
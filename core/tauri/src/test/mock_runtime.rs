@@ -123,6 +123,7 @@ impl<T: UserEvent> RuntimeHandle<T> for MockRuntimeHandle {
         context: self.context.clone(),
         last_evaluated_script: Default::default(),
         url: Arc::new(Mutex::new(pending.url)),
+        focused: Default::default(),
       },
     })
   }
@@ -192,6 +193,7 @@ pub struct MockDispatcher {
   context: RuntimeContext,
   url: Arc<Mutex<String>>,
   last_evaluated_script: Arc<Mutex<Option<String>>>,
+  focused: Arc<AtomicBool>,
 }
 
 impl MockDispatcher {
@@ -426,7 +428,7 @@ impl<T: UserEvent> Dispatch<T> for MockDispatcher {
   }
 
   fn is_focused(&self) -> Result<bool> {
-    Ok(false)
+    Ok(self.focused.load(Ordering::Relaxed))
   }
 
   fn is_decorated(&self) -> Result<bool> {
@@ -538,6 +540,7 @@ impl<T: UserEvent> Dispatch<T> for MockDispatcher {
         context: self.context.clone(),
         last_evaluated_script: Default::default(),
         url: Arc::new(Mutex::new(pending.url)),
+        focused: Default::default(),
       },
     })
   }
@@ -641,6 +644,7 @@ impl<T: UserEvent> Dispatch<T> for MockDispatcher {
   }
 
   fn set_focus(&self) -> Result<()> {
+    self.focused.store(true, Ordering::Relaxed);
     Ok(())
   }
 
@@ -768,6 +772,7 @@ impl<T: UserEvent> Runtime<T> for MockRuntime {
         context: self.context.clone(),
         last_evaluated_script: Default::default(),
         url: Arc::new(Mutex::new(pending.url)),
+        focused: Default::default(),
       },
     })
   }
@@ -57,9 +57,10 @@ use serde::Serialize;
 use std::{borrow::Cow, collections::HashMap, fmt::Debug};
 
 use crate::{
+  command::{ArgumentAccess, ScopeAccess},
   ipc::{InvokeBody, InvokeError, InvokeResponse},
   window::InvokeRequest,
-  App, Builder, Context, Pattern, Window,
+  App, Builder, Context, Manager, Pattern, Runtime, Window,
 };
 use tauri_utils::{
   acl::resolved::Resolved,
@@ -274,6 +275,57 @@ pub fn get_ipc_response(
   }
 }
 
+/// Returns and clears the command scope entries read by commands since the last call,
+/// to assert which scope values a command observed.
+///
+/// Recording must be enabled with [`crate::command::RuntimeAuthority::record_scope_accesses`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::{
+///   command::ScopeAccessKind,
+///   test::{mock_builder, mock_context, noop_assets},
+/// };
+///
+/// let app = mock_builder()
+///   .configure_runtime_authority(|authority| authority.record_scope_accesses(true))
+///   .build(mock_context(noop_assets()))
+///   .unwrap();
+/// // run a scoped command...
+/// assert!(tauri::test::take_scope_accesses(&app)
+///   .iter()
+///   .all(|access| access.kind != ScopeAccessKind::Denies));
+/// ```
+pub fn take_scope_accesses<R: Runtime, M: Manager<R>>(manager: &M) -> Vec<ScopeAccess> {
+  manager.manager().runtime_authority.take_scope_accesses()
+}
+
+/// Returns and clears the command arguments resolved from invoke payloads since the last call,
+/// to compare the arguments a command reads with the ones the frontend sends.
+///
+/// Recording must be enabled with [`crate::command::RuntimeAuthority::record_argument_accesses`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tauri::test::{mock_builder, mock_context, noop_assets};
+///
+/// let app = mock_builder()
+///   .configure_runtime_authority(|authority| authority.record_argument_accesses(true))
+///   .build(mock_context(noop_assets()))
+///   .unwrap();
+/// // invoke a command with `{ "a": 1, "b": 2 }`...
+/// let read: Vec<String> = tauri::test::take_argument_accesses(&app)
+///   .into_iter()
+///   .map(|access| access.key)
+///   .collect();
+/// assert!(!read.contains(&"b".to_string()));
+/// ```
+pub fn take_argument_accesses<R: Runtime, M: Manager<R>>(manager: &M) -> Vec<ArgumentAccess> {
+  manager.manager().runtime_authority.take_argument_accesses()
+}
+
 #[cfg(test)]
 mod tests {
   use crate::WindowBuilder;
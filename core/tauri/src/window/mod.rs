@@ -16,10 +16,10 @@ use url::Url;
 use crate::TitleBarStyle;
 use crate::{
   app::{AppHandle, UriSchemeResponder},
-  command::{CommandArg, CommandItem, Origin},
+  command::{cache::Reservation, AccessError, CommandArg, CommandItem, Origin, Principal},
   event::{EmitArgs, Event, EventId},
   ipc::{
-    CallbackFn, Invoke, InvokeBody, InvokeError, InvokeMessage, InvokeResolver,
+    CallbackFn, Invoke, InvokeBody, InvokeError, InvokeMessage, InvokeResolver, InvokeResponse,
     OwnedInvokeResponder,
   },
   manager::AppManager,
@@ -2298,7 +2298,7 @@ impl<R: Runtime> Window<R> {
     self.window.dispatcher.navigate(url).unwrap();
   }
 
-  fn is_local_url(&self, current_url: &Url) -> bool {
+  pub(crate) fn is_local_url(&self, current_url: &Url) -> bool {
     self.manager.get_url().make_relative(current_url).is_some()
       || {
         let protocol_url = self.manager.protocol_url();
@@ -2308,19 +2308,78 @@ impl<R: Runtime> Window<R> {
       || (cfg!(dev) && current_url.domain() == Some("tauri.localhost"))
   }
 
+  /// The origin of the invokes sent by the current URL of this window, checked against the access control list.
+  pub(crate) fn invoke_origin(&self) -> Origin {
+    let current_url = self.url();
+    if self.is_local_url(&current_url) {
+      Origin::LocalUrl {
+        url: match current_url.port() {
+          Some(port) => format!(
+            "{}://{}:{port}",
+            current_url.scheme(),
+            current_url.host_str().unwrap_or_default()
+          ),
+          None => format!(
+            "{}://{}",
+            current_url.scheme(),
+            current_url.host_str().unwrap_or_default()
+          ),
+        },
+      }
+    } else {
+      Origin::RemoteUrl {
+        scheme: current_url.scheme().into(),
+        domain: current_url
+          .domain()
+          .map(|d| d.to_string())
+          .unwrap_or_default(),
+        port: current_url.port_or_known_default(),
+      }
+    }
+  }
+
+  /// Sets the principal of this window, used to authorize the commands it invokes.
+  ///
+  /// Call it after the user completes a step-up authentication in this window,
+  /// see [`RuntimeAuthority::require_step_up`](crate::command::RuntimeAuthority::require_step_up).
+  pub fn set_principal(&self, principal: Principal) {
+    self.manager.window.set_principal(self.label(), principal);
+  }
+
   /// Handles this window receiving an [`InvokeRequest`].
   pub fn on_message(self, request: InvokeRequest, responder: Box<OwnedInvokeResponder<R>>) {
     let manager = self.manager.clone();
-    let current_url = self.url();
-    let is_local = self.is_local_url(&current_url);
 
     let custom_responder = self.manager.window.invoke_responder.clone();
 
+    // only set once the command is dispatched, so only responses of commands passing every authorization check are cached
+    let cache_key = Arc::new(Mutex::new(None));
+    let cache_key_ = cache_key.clone();
+    // only set once the idempotency key is reserved by this invoke
+    let idempotency_key = Arc::new(Mutex::new(None));
+    let idempotency_key_ = idempotency_key.clone();
+    let cache_manager = manager.clone();
+
     let resolver = InvokeResolver::new(
       self.clone(),
       Arc::new(Mutex::new(Some(Box::new(
         #[allow(unused_variables)]
         move |window: Window<R>, cmd, response, callback, error| {
+          let cache_key = cache_key_.lock().unwrap().take();
+          if let (Some(key), InvokeResponse::Ok(body)) = (cache_key, &response) {
+            cache_manager
+              .runtime_authority
+              .response_cache
+              .insert(key, body.clone());
+          }
+          let idempotency_key = idempotency_key_.lock().unwrap().take();
+          if let Some(key) = idempotency_key {
+            cache_manager
+              .runtime_authority
+              .idempotency_cache
+              .complete(key, &response);
+          }
+
           if let Some(responder) = &custom_responder {
             (responder)(&window, &cmd, &response, callback, error);
           }
@@ -2331,12 +2390,13 @@ impl<R: Runtime> Window<R> {
       request.cmd.clone(),
       request.callback,
       request.error,
+      crate::ipc::deadline(&request.headers),
     );
 
     #[cfg(mobile)]
     let app_handle = self.app_handle.clone();
 
-    let message = InvokeMessage::new(
+    let mut message = InvokeMessage::new(
       self,
       manager.state(),
       request.cmd.to_string(),
@@ -2344,23 +2404,113 @@ impl<R: Runtime> Window<R> {
       request.headers,
     );
 
-    let resolved_acl = manager
+    let principal = manager.window.principal(&message.window.window.label);
+
+    let resolved_acl = match manager
       .runtime_authority
-      .resolve_access(
+      .authorize_invoke(
         &request.cmd,
         &message.window.window.label,
-        if is_local {
-          Origin::Local
-        } else {
-          Origin::Remote {
-            domain: current_url
-              .domain()
-              .map(|d| d.to_string())
-              .unwrap_or_default(),
-          }
-        },
+        &message.origin,
+        &principal,
       )
-      .cloned();
+      .map_err(AccessError::coarse)
+    {
+      Ok(resolved) => Some(resolved.clone()),
+      Err(AccessError::NotAllowed) => None,
+      // explicitly denied app commands are rejected like plugin commands
+      Err(AccessError::Denied) => {
+        resolver.reject("NOT ALLOWED");
+        return;
+      }
+      Err(e) => {
+        resolver.reject(e.to_string());
+        return;
+      }
+    };
+
+    if request.cmd.starts_with("plugin:")
+      && request.cmd != crate::ipc::channel::FETCH_CHANNEL_DATA_COMMAND
+      && resolved_acl.is_none()
+    {
+      resolver.reject("NOT ALLOWED");
+      return;
+    }
+
+    if manager.runtime_authority.requires_focus(&request.cmd)
+      && !message.window.is_focused().unwrap_or(false)
+    {
+      resolver.reject(AccessError::WindowNotFocused.to_string());
+      return;
+    }
+
+    if let Err(e) = manager.runtime_authority.intercept(
+      &request.cmd,
+      &message.window.window.label,
+      &message.origin,
+    ) {
+      resolver.invoke_error(e);
+      return;
+    }
+
+    let response_cache_key = manager.runtime_authority.response_cache.key(
+      &request.cmd,
+      &message.window.window.label,
+      &message.origin,
+      &message.payload,
+    );
+    if let Some(body) = response_cache_key
+      .as_ref()
+      .and_then(|key| manager.runtime_authority.response_cache.get(key))
+    {
+      resolver.resolve(body);
+      return;
+    }
+
+    if let Some(key) = manager.runtime_authority.idempotency_cache.key(
+      &request.cmd,
+      &message.window.window.label,
+      &message.origin,
+      &message.headers,
+    ) {
+      let waiter = resolver.clone();
+      let reservation = manager
+        .runtime_authority
+        .idempotency_cache
+        .reserve(&key, move |result| match result {
+          Ok(body) => waiter.resolve(body),
+          Err(e) => waiter.reject(e),
+        });
+      match reservation {
+        Reservation::Run => *idempotency_key.lock().unwrap() = Some(key),
+        Reservation::Replay(Ok(body)) => {
+          resolver.resolve(body);
+          return;
+        }
+        Reservation::Replay(Err(e)) => {
+          resolver.reject(e);
+          return;
+        }
+        Reservation::Wait => return,
+      }
+    }
+
+    match manager
+      .runtime_authority
+      .transform_payload(std::mem::take(&mut message.payload))
+      .and_then(|payload| {
+        manager
+          .runtime_authority
+          .decode_payload(&message.headers, payload)
+      }) {
+      Ok(payload) => message.payload = payload,
+      Err(e) => {
+        resolver.invoke_error(e);
+        return;
+      }
+    }
+
+    *cache_key.lock().unwrap() = response_cache_key;
 
     let mut invoke = Invoke {
       message,
@@ -2369,11 +2519,6 @@ impl<R: Runtime> Window<R> {
     };
 
     if request.cmd.starts_with("plugin:") {
-      if request.cmd != crate::ipc::channel::FETCH_CHANNEL_DATA_COMMAND && invoke.acl.is_none() {
-        invoke.resolver.reject("NOT ALLOWED");
-        return;
-      }
-
       let command = invoke.message.command.replace("plugin:", "");
       let mut tokens = command.split('|');
       // safe to unwrap: split always has a least one item